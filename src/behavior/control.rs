@@ -0,0 +1,167 @@
+use crate::behavior::pathfinding::Cost;
+use crate::generation::{PlayerId, Unit};
+use crate::objects::Point;
+use crate::state::{Context, Teams};
+use crate::state::traits::HasPosition;
+use std::collections::{HashMap, HashSet};
+
+/// extra impedance for planning a path through an enemy zone
+pub const ZONE_COST: Cost = 50.0;
+
+/// extra actions needed to leave an enemy zone
+pub const DISENGAGE: u8 = 20;
+
+/// Hexes controlled by enemy units, from the point
+/// of view of a single player.
+pub struct Control {
+    zones: HashSet<Point>,
+    occupied: HashSet<Point>,
+}
+
+impl Control {
+    /// every hex holding or next to an enemy unit
+    pub fn new(context: &Context, units: Vec<&Unit>, player: &PlayerId, teams: &Teams) -> Self {
+        let occupied: HashSet<Point> = units
+            .into_iter()
+            .filter(|u| teams.is_enemy(u.player_id(), player))
+            .map(|u| *u.position())
+            .collect();
+
+        let zones = occupied
+            .iter()
+            .map(|p| {
+                let mut n = p.neighbors(context);
+                n.push(*p);
+                n
+            })
+            .flatten()
+            .collect();
+
+        Self { zones, occupied }
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        self.zones.contains(point)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    /// raise the impedance of controlled hexes so that
+    /// the pathfinder avoids them where it can
+    pub fn weigh(&self, impedance: &mut HashMap<Point, Cost>) {
+        for point in self.zones.iter() {
+            if let Some(cost) = impedance.get_mut(point) {
+                *cost += ZONE_COST;
+            }
+        }
+    }
+
+    /// walk a path until actions run out or an enemy zone is
+    /// entered, returning each point reached with the actions
+    /// left over. Entering a zone uses all remaining actions.
+    pub fn walk(&self, start: &Point, path: Vec<(Point, Cost)>, actions: u8) -> Vec<(Point, u8)> {
+        let mut result = vec![];
+        let mut remaining = actions;
+        let mut previous = *start;
+
+        for (point, cost) in path.into_iter() {
+            // enemy positions can only be attacked
+            if self.occupied.contains(&point) {
+                break;
+            }
+
+            let mut cost = cost.max(0.).min(100.) as u8;
+
+            // retreating out of contact is slow
+            if point != *start && self.contains(&previous) {
+                cost = cost.saturating_add(DISENGAGE);
+            }
+
+            remaining = remaining.saturating_sub(cost);
+            if remaining == 0 {
+                break;
+            }
+
+            // moving into contact ends movement
+            if point != *start && self.contains(&point) {
+                result.push((point, 0));
+                break;
+            }
+
+            result.push((point, remaining));
+            previous = point;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (PlayerId, Control) {
+        let player = PlayerId::new();
+        let enemy = Unit::new(PlayerId::new()).with_position(Point::new(5, 0));
+        let control = Control::new(&Context::default(), vec![&enemy], &player, &Teams::default());
+        (player, control)
+    }
+
+    #[test]
+    fn test_enemy_controls_neighbors() {
+        let (_, control) = setup();
+        assert!(control.contains(&Point::new(5, 0)));
+        assert!(control.contains(&Point::new(4, 0)));
+        assert!(!control.contains(&Point::new(2, 0)));
+    }
+
+    #[test]
+    fn test_friendly_units_have_no_zone() {
+        let (player, _) = setup();
+        let friend = Unit::new(player).with_position(Point::new(5, 0));
+        let control = Control::new(&Context::default(), vec![&friend], &player, &Teams::default());
+        assert!(control.is_empty());
+    }
+
+    #[test]
+    fn test_allies_have_no_zone() {
+        let (player, ally) = (PlayerId::new(), PlayerId::new());
+        let teams = Teams::new(vec![(player, 1), (ally, 1)].into_iter().collect());
+        let unit = Unit::new(ally).with_position(Point::new(5, 0));
+        let control = Control::new(&Context::default(), vec![&unit], &player, &teams);
+        assert!(control.is_empty());
+    }
+
+    #[test]
+    fn test_walk_stops_in_zone() {
+        let (_, control) = setup();
+        let start = Point::new(1, 0);
+        let path = (2..6)
+            .map(|x| (Point::new(x, 0), 10.))
+            .collect();
+
+        let walked = control.walk(&start, path, 100);
+        assert_eq!(walked.last(), Some(&(Point::new(4, 0), 0)));
+    }
+
+    #[test]
+    fn test_disengage_costs_actions() {
+        let (_, control) = setup();
+        let start = Point::new(4, 0);
+        let path = vec![(Point::new(3, 0), 10.)];
+
+        let walked = control.walk(&start, path, 100);
+        assert_eq!(walked, vec![(Point::new(3, 0), 100 - 10 - DISENGAGE)]);
+    }
+
+    #[test]
+    fn test_walk_never_enters_enemy() {
+        let (_, control) = setup();
+        let start = Point::new(4, 0);
+        let path = vec![(Point::new(5, 0), 10.)];
+
+        assert!(control.walk(&start, path, 100).is_empty());
+    }
+}
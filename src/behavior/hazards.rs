@@ -0,0 +1,148 @@
+use crate::generation::{Area, Biome, Condition, Specialty, Time};
+use crate::state::Season;
+use rand::Rng;
+
+/// temperature drop (celsius) during the night stage
+const NIGHT_DROP: f32 = 8.0;
+
+/// below this temperature soldiers risk frostbite
+const COLD: f32 = -5.0;
+
+/// above this temperature soldiers risk heatstroke
+const HEAT: f32 = 32.0;
+
+/// all conditions in the order they are rolled
+const CONDITIONS: [Condition; 5] = [
+    Condition::Injury,
+    Condition::Frostbite,
+    Condition::Heatstroke,
+    Condition::Illness,
+    Condition::Breakdown,
+];
+
+/// Environmental hazards for a single area during
+/// one stage (day or night) of a turn.
+pub struct Hazards<'a> {
+    area: &'a Area,
+    season: Season,
+    time: Time,
+}
+
+impl<'a> Hazards<'a> {
+    pub fn new(area: &'a Area, season: Season, time: Time) -> Self {
+        Self { area, season, time }
+    }
+
+    /// temperature adjusted for the time of day
+    pub fn temperature(&self) -> f32 {
+        match self.time {
+            Time::Day => self.area.temperature(),
+            Time::Night => self.area.temperature() - NIGHT_DROP,
+        }
+    }
+
+    /// chance (0-1) that a soldier suffers the condition
+    pub fn chance(&self, condition: Condition, specialty: &Specialty) -> f32 {
+        let rocks = self.area.rocks() as f32 / 100.;
+        let moisture = self.area.moisture() as f32 / 100.;
+        let temperature = self.temperature();
+        let night = self.time == Time::Night;
+
+        let v = match condition {
+            // rough ground and darkness cause falls
+            Condition::Injury => {
+                let v = 0.0005 + rocks * 0.003;
+                if night { v * 1.5 } else { v }
+            }
+            // scales with how far below freezing it is
+            Condition::Frostbite if temperature < COLD => {
+                let mut v = (COLD - temperature) / 35. * 0.01;
+                if self.area.biome() == Biome::Tundra { v *= 1.5; }
+                if self.season == Season::Winter { v *= 1.5; }
+                if night { v * 2.0 } else { v }
+            }
+            // scales with how far above the limit it is
+            Condition::Heatstroke if temperature > HEAT => {
+                let mut v = (temperature - HEAT) / 18. * 0.01;
+                if self.area.biome() == Biome::Desert { v *= 1.5; }
+                if self.season == Season::Summer { v *= 1.5; }
+                if night { v * 0.25 } else { v }
+            }
+            // damp and cold seasons spread disease
+            Condition::Illness => {
+                let v = 0.0002 + moisture * 0.002;
+                match self.season {
+                    Season::Autumn | Season::Winter => v * 1.5,
+                    _ => v,
+                }
+            }
+            // rocks and mud wear out vehicles
+            Condition::Breakdown if specialty.has_vehicles() => {
+                let v = 0.001 + rocks * 0.004 + moisture * 0.002;
+                if night { v * 1.5 } else { v }
+            }
+            _ => 0.0,
+        };
+
+        v.max(0.0).min(1.0)
+    }
+
+    /// roll for a single soldier, returning the first condition hit
+    pub fn roll<R: Rng>(&self, rng: &mut R, specialty: &Specialty) -> Option<Condition> {
+        CONDITIONS
+            .iter()
+            .cloned()
+            .find(|c| rng.gen::<f32>() < self.chance(*c, specialty))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(temperature: f32, rocks: u8, moisture: u8) -> Area {
+        Area::create()
+            .with_temperature(temperature)
+            .with_rocks(rocks)
+            .with_moisture(moisture)
+            .with_textures(vec![0])
+            .build()
+    }
+
+    #[test]
+    fn test_temperate_has_no_exposure() {
+        let a = area(20.0, 0, 0);
+        let h = Hazards::new(&a, Season::Spring, Time::Day);
+        assert_eq!(h.chance(Condition::Frostbite, &Specialty::Infantry), 0.0);
+        assert_eq!(h.chance(Condition::Heatstroke, &Specialty::Infantry), 0.0);
+    }
+
+    #[test]
+    fn test_night_increases_frostbite() {
+        let a = area(-20.0, 0, 0);
+        let day = Hazards::new(&a, Season::Winter, Time::Day);
+        let night = Hazards::new(&a, Season::Winter, Time::Night);
+        let d = day.chance(Condition::Frostbite, &Specialty::Infantry);
+        let n = night.chance(Condition::Frostbite, &Specialty::Infantry);
+        assert!(d > 0.0);
+        assert!(n > d);
+    }
+
+    #[test]
+    fn test_rocks_increase_injury() {
+        let flat = area(20.0, 0, 0);
+        let rocky = area(20.0, 100, 0);
+        let f = Hazards::new(&flat, Season::Summer, Time::Day);
+        let r = Hazards::new(&rocky, Season::Summer, Time::Day);
+        assert!(r.chance(Condition::Injury, &Specialty::Infantry) >
+                f.chance(Condition::Injury, &Specialty::Infantry));
+    }
+
+    #[test]
+    fn test_breakdown_requires_vehicles() {
+        let a = area(20.0, 50, 50);
+        let h = Hazards::new(&a, Season::Spring, Time::Day);
+        assert_eq!(h.chance(Condition::Breakdown, &Specialty::Infantry), 0.0);
+        assert!(h.chance(Condition::Breakdown, &Specialty::Armor) > 0.0);
+    }
+}
@@ -1,3 +1,11 @@
+pub mod control;
+pub mod hazards;
+pub mod opponent;
 pub mod pathfinding;
+pub mod sight;
 
+pub use control::Control;
+pub use hazards::Hazards;
+pub use opponent::{Difficulty, Opponent, Plan};
 pub use pathfinding::Pathfinder;
+pub use sight::Sight;
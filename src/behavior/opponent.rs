@@ -0,0 +1,373 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result};
+use rand::Rng;
+
+use crate::behavior::{Pathfinder, Sight};
+use crate::generation::{Id, Order, PlayerId, Unit};
+use crate::objects::Point;
+use crate::state::traits::*;
+use crate::state::{Attack, Fire, State};
+
+/// How well a computer player plays
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+/// A computer player that fills a player slot
+#[derive(Debug, Clone)]
+pub struct Opponent {
+    id: PlayerId,
+    name: String,
+    difficulty: Difficulty,
+}
+
+/// Everything a computer player decided to do this turn
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    pub orders: Vec<(Id, Vec<Order>)>,
+    pub attacks: Vec<Attack>,
+}
+
+impl Difficulty {
+    /// strength ratio needed before attacking
+    pub fn odds(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.8,
+            Difficulty::Normal => 1.2,
+            Difficulty::Hard => 1.5,
+        }
+    }
+
+    /// distance searched for better ground
+    pub fn search(&self) -> i32 {
+        match self {
+            Difficulty::Easy => 0,
+            Difficulty::Normal => 2,
+            Difficulty::Hard => 4,
+        }
+    }
+
+    /// chance that a unit is left without orders
+    pub fn hesitation(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.3,
+            Difficulty::Normal => 0.1,
+            Difficulty::Hard => 0.0,
+        }
+    }
+}
+
+impl Opponent {
+    pub fn new(name: String, difficulty: Difficulty) -> Self {
+        Self {
+            id: PlayerId::new(),
+            name,
+            difficulty,
+        }
+    }
+
+    pub fn id(&self) -> PlayerId {
+        self.id
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// decide what every idle unit should do this turn
+    pub fn plan(&self, state: &State) -> Plan {
+        let mut rng = rand::thread_rng();
+        let mut plan = Plan::default();
+
+        let sight = Sight::new(&state.areas);
+        let impedance = state.impedance_map();
+
+        let (friends, others): (Vec<&Unit>, Vec<&Unit>) = state.units
+            .units()
+            .into_iter()
+            .partition(|u| u.player_id() == &self.id);
+
+        let enemies: Vec<&Unit> = others
+            .into_iter()
+            .filter(|u| state.teams.is_enemy(u.player_id(), &self.id))
+            .collect();
+
+        if friends.is_empty() || enemies.is_empty() {
+            return plan;
+        }
+
+        for unit in friends.iter() {
+            if !unit.orders().is_empty() {
+                continue;
+            }
+
+            if rng.gen::<f32>() < self.difficulty.hesitation() {
+                continue;
+            }
+
+            let start = *unit.position();
+
+            // the enemy position with the best odds that can be reached
+            let objective = self.objective(state, &impedance, unit, &enemies);
+
+            let target = match objective {
+                Some(t) => t,
+                None => continue,
+            };
+
+            let defenders = state.units.targeted_units(&target, &self.id, &state.teams);
+            let odds = self.odds(state, &friends, &target, &defenders);
+            let range = unit.specialty().range();
+            let distance = start.distance(target);
+
+            if unit.specialty().is_indirect() {
+                if distance <= range && sight.spotted(&friends, &target) {
+                    plan.attacks.push(self.attack(unit, target, Fire::Indirect));
+                }
+                continue;
+            }
+
+            if odds >= self.difficulty.odds() {
+                if distance == 1 {
+                    plan.attacks.push(self.attack(unit, target, Fire::Assault));
+                }
+                else if distance <= range && sight.visible(&start, &target) {
+                    plan.attacks.push(self.attack(unit, target, Fire::Direct));
+                }
+                else {
+                    plan.orders.push((*unit.id(), vec![Order::AttackMove(target)]));
+                }
+            }
+            else {
+                // hold the best nearby ground until the odds improve
+                let ground = self.ground(state, &start);
+                let orders = match ground == start {
+                    true => vec![Order::DigIn],
+                    false => vec![Order::Move(ground), Order::DigIn],
+                };
+                plan.orders.push((*unit.id(), orders));
+            }
+        }
+
+        plan
+    }
+
+    fn attack(&self, unit: &Unit, target: Point, fire: Fire) -> Attack {
+        Attack::new(self.id, vec![*unit.id()], target).with_fire(fire)
+    }
+
+    /// pick the enemy position that is cheapest to reach,
+    /// weighted by how weakly it is held, ignoring any that
+    /// can't be reached at all
+    fn objective(
+        &self,
+        state: &State,
+        impedance: &HashMap<Point, f32>,
+        unit: &Unit,
+        enemies: &Vec<&Unit>,
+    ) -> Option<Point> {
+        let start = *unit.position();
+
+        let mut targets: Vec<Point> = enemies
+            .iter()
+            .map(|u| *u.position())
+            .collect();
+        targets.sort_by_key(|p| p.integers());
+        targets.dedup();
+
+        targets
+            .into_iter()
+            .filter_map(|target| {
+                let path = Pathfinder::new(state.context(), impedance, start, target).find_weighted();
+                if path.is_empty() {
+                    return None;
+                }
+
+                let cost: f32 = path
+                    .into_iter()
+                    .skip(1)
+                    .map(|(_, c)| c)
+                    .sum();
+
+                let held = strength(&state.units.targeted_units(&target, &self.id, &state.teams));
+                Some((target, cost + held))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(t, _)| t)
+    }
+
+    /// strength of nearby friendly units against a target
+    fn odds(&self, state: &State, friends: &Vec<&Unit>, target: &Point, defenders: &Vec<&Unit>) -> f32 {
+        let attackers: Vec<&Unit> = friends
+            .iter()
+            .filter(|u| u.position().distance(*target) <= u.specialty().range().max(2))
+            .cloned()
+            .collect();
+
+        let cover = state.fortifications.cover(&target.integers());
+        let defense = strength(defenders) * (1.0 + cover);
+
+        match defense > 0.0 {
+            true => strength(&attackers) / defense,
+            false => f32::MAX,
+        }
+    }
+
+    /// the most defensible free position near a point
+    fn ground(&self, state: &State, start: &Point) -> Point {
+        let search = self.difficulty.search();
+        let mut best = (*start, self.defensibility(state, start));
+
+        for location in state.areas.keys() {
+            let point = Point::from(*location);
+            if point.distance(*start) > search || state.units.space(&point) == 0 {
+                continue;
+            }
+
+            if state.units.has_enemy(&point, &self.id, &state.teams) {
+                continue;
+            }
+
+            let score = self.defensibility(state, &point);
+            if score > best.1 {
+                best = (point, score);
+            }
+        }
+
+        best.0
+    }
+
+    /// high, open ground that is easy to fortify scores best
+    fn defensibility(&self, state: &State, point: &Point) -> f32 {
+        let location = point.integers();
+        let area = match state.areas.get(&location) {
+            Some(a) => a,
+            None => return f32::MIN,
+        };
+
+        area.elevation() - area.impedance() as f32 +
+            state.fortifications.cover(&location) * 100.
+    }
+}
+
+/// combined fighting strength of a group of units
+pub fn strength(units: &Vec<&Unit>) -> f32 {
+    units
+        .iter()
+        .map(|u| u.accuracy() as f32 * u.health() as f32 / 100.)
+        .sum()
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Normal => write!(f, "Normal"),
+            Difficulty::Hard => write!(f, "Hard"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::{Area, Condition, Soldier, Specialty};
+    use crate::objects::Map;
+    use crate::state::Context;
+
+    /// a board where every tile in the area is open ground
+    fn state(areas: std::ops::Range<i32>) -> State {
+        let mut state = State::default();
+        state.units = Map::new(&Context::default());
+        for x in areas.clone() {
+            for y in areas.clone() {
+                state.add(Area::create()
+                    .with_location((x, y))
+                    .with_impedance(1u8));
+            }
+        }
+        state
+    }
+
+    /// place a unit, sick enough that it can't defend itself
+    fn place(state: &mut State, player: PlayerId, point: Point, sick: bool) -> Id {
+        let mut unit = Unit::new(player).with_position(point);
+        unit.attach(vec![Soldier::new(&Specialty::Infantry); 3]);
+        if sick {
+            for i in 0..3 {
+                unit.afflict(i, Condition::Illness);
+            }
+        }
+        let id = *unit.id();
+        state.units.add(point, unit);
+        id
+    }
+
+    #[test]
+    fn test_harder_opponents_are_careful() {
+        assert!(Difficulty::Hard.odds() > Difficulty::Easy.odds());
+        assert!(Difficulty::Hard.hesitation() < Difficulty::Easy.hesitation());
+    }
+
+    #[test]
+    fn test_strength_of_nothing() {
+        assert_eq!(strength(&vec![]), 0.0);
+    }
+
+    #[test]
+    fn test_plan_targets_the_nearest_enemy() {
+        let opponent = Opponent::new("AI".into(), Difficulty::Hard);
+        let enemy = PlayerId::new();
+
+        let mut state = state(-15..15);
+        let id = place(&mut state, opponent.id(), Point::new(0, 0), false);
+        place(&mut state, enemy, Point::new(-8, 0), true);
+        place(&mut state, enemy, Point::new(4, 0), true);
+
+        let plan = opponent.plan(&state);
+        assert_eq!(plan.orders, vec![(id, vec![Order::AttackMove(Point::new(4, 0))])]);
+        assert!(plan.attacks.is_empty());
+    }
+
+    #[test]
+    fn test_plan_ignores_own_units() {
+        let opponent = Opponent::new("AI".into(), Difficulty::Hard);
+        let enemy = PlayerId::new();
+
+        let mut state = state(-15..15);
+        let id = place(&mut state, opponent.id(), Point::new(0, 0), false);
+        place(&mut state, opponent.id(), Point::new(3, 0), true);
+        place(&mut state, enemy, Point::new(-6, 0), true);
+
+        let plan = opponent.plan(&state);
+        let targets: Vec<Point> = plan.orders
+            .iter()
+            .filter(|(i, _)| *i == id)
+            .flat_map(|(_, o)| o.iter().filter_map(|o| o.target()))
+            .collect();
+        assert_eq!(targets, vec![Point::new(-6, 0)]);
+    }
+
+    #[test]
+    fn test_plan_without_reachable_enemies() {
+        let opponent = Opponent::new("AI".into(), Difficulty::Hard);
+        let enemy = PlayerId::new();
+
+        // the enemy stands beyond the edge of the known ground
+        let mut state = state(-3..3);
+        place(&mut state, opponent.id(), Point::new(0, 0), false);
+        place(&mut state, enemy, Point::new(10, 10), true);
+
+        let plan = opponent.plan(&state);
+        assert!(plan.orders.is_empty());
+        assert!(plan.attacks.is_empty());
+    }
+}
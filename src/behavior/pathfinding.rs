@@ -7,35 +7,37 @@ pub type Score = f32;
 pub type Cost = f32;
 
 pub struct Pathfinder<'a> {
+    context: &'a Context,
     nodes: &'a HashMap<Point, Cost>,
     start: Point,
     end: Point,
 }
 
 impl<'a> Pathfinder<'a> {
-    pub fn new(nodes: &'a HashMap<Point, Cost>, start: Point, end: Point) -> Self {
-        Self { nodes, start, end }
+    pub fn new(context: &'a Context, nodes: &'a HashMap<Point, Cost>, start: Point, end: Point) -> Self {
+        Self { context, nodes, start, end }
     }
 
     pub fn limit(&self) -> u32 {
-        let w = Context::width();
-        let h = Context::height();
-        w * h
+        self.context.total() as u32
     }
 
+    /// the cheapest path from start to end, including both, or
+    /// nothing if either is off the map or there is no path
     pub fn find(&self) -> Vec<Point> {
         let max = self.limit();
 
+        if !self.nodes.contains_key(&self.start) || !self.nodes.contains_key(&self.end) {
+            return vec![];
+        }
+
         let weights: HashMap<Point, (Cost, Weight)> = self
             .nodes
             .iter()
             .map(|(p, c)| (*p, (*c, p.distance(self.end) as f32)))
             .collect();
 
-        let (_, w): (Cost, Weight) = weights
-            .get(&self.start)
-            .expect("Starting point is not in map")
-            .to_owned();
+        let (_, w): (Cost, Weight) = weights[&self.start];
 
         let mut scores: HashMap<Point, Score> = HashMap::new();
 
@@ -48,9 +50,12 @@ impl<'a> Pathfinder<'a> {
         let mut count = 0;
         while queue[0].0 != self.end {
             let (point, _, path, previous) = queue.swap_remove(0);
-            for node in point.neighbors().iter() {
-                let (current, _) = weights.get(&point).expect("Cannot find current weights");
-                let (next, weight) = weights.get(&node).expect("Cannot find next weights");
+            for node in point.neighbors(self.context).iter() {
+                let (current, _) = weights[&point];
+                let (next, weight) = match weights.get(node) {
+                    Some(w) => *w,
+                    None => continue,
+                };
 
                 let cost = previous + (current * 0.5) + (next * 0.5);
                 let score = weight + cost;
@@ -84,11 +89,11 @@ impl<'a> Pathfinder<'a> {
                 }
             }
 
-            queue.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            queue.sort_by(|a, b| a.1.total_cmp(&b.1));
 
-            // break when no path found
-            if count > max {
-                break;
+            // give up when no path found
+            if queue.is_empty() || count > max {
+                return vec![];
             }
             count += 1;
         }
@@ -101,7 +106,7 @@ impl<'a> Pathfinder<'a> {
     pub fn find_weighted(&self) -> Vec<(Point, Cost)> {
         self.find()
             .into_iter()
-            .map(|p| (p, self.nodes.get(&p).unwrap().clone()))
+            .filter_map(|p| self.nodes.get(&p).map(|c| (p, *c)))
             .collect()
     }
 }
@@ -112,11 +117,10 @@ mod tests {
 
     macro_rules! initialize {
         ( $w:expr, $h:expr ) => {
-            initialize!($w, $h, 175, 200);
+            initialize!($w, $h, 175, 200)
         };
         ( $w:expr, $h:expr, $tw:expr, $th:expr ) => {
-            crate::state::Context::set_size($w, $h);
-            crate::state::Context::set_tile_size($tw, $th);
+            Context::new($w, $h, $tw, $th)
         };
     }
 
@@ -130,7 +134,7 @@ mod tests {
 
     #[test]
     fn pathfinder_simple_test() {
-        initialize!(30, 30);
+        let context = initialize!(30, 30);
 
         let tilemap = map![
             (Point::new(-15, -15), 8.),
@@ -1035,9 +1039,27 @@ mod tests {
             (Point::new(14, 14), 12.)
         ];
 
-        let finder = Pathfinder::new(&tilemap, (0, 0).into(), (2, 2).into());
+        let finder = Pathfinder::new(&context, &tilemap, (0, 0).into(), (2, 2).into());
         let path = finder.find();
 
         assert_eq!(path.len(), 4);
     }
+
+    #[test]
+    fn pathfinder_no_path_test() {
+        let context = initialize!(10, 10);
+
+        // the two points aren't connected by any tiles
+        let tilemap = map![
+            (Point::new(0, 0), 1.),
+            (Point::new(3, 3), 1.)
+        ];
+
+        let finder = Pathfinder::new(&context, &tilemap, (0, 0).into(), (3, 3).into());
+        assert!(finder.find().is_empty());
+
+        let finder = Pathfinder::new(&context, &tilemap, (0, 0).into(), (40, 40).into());
+        assert!(finder.find().is_empty());
+        assert!(finder.find_weighted().is_empty());
+    }
 }
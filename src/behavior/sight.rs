@@ -0,0 +1,89 @@
+use crate::generation::{Area, Unit};
+use crate::objects::{Location, Point};
+use crate::state::traits::HasPosition;
+use std::collections::HashMap;
+
+/// height (meters) of an observer above the ground
+const EYE: f32 = 2.0;
+
+/// maximum distance (hexes) a unit can spot targets
+pub const SIGHT: i32 = 6;
+
+/// Line of sight over the elevation of the map
+pub struct Sight<'a> {
+    areas: &'a HashMap<Location, Area>,
+}
+
+impl<'a> Sight<'a> {
+    pub fn new(areas: &'a HashMap<Location, Area>) -> Self {
+        Self { areas }
+    }
+
+    fn elevation(&self, point: &Point) -> f32 {
+        self.areas
+            .get(&point.integers())
+            .map(|a| a.elevation())
+            .unwrap_or(0.0)
+    }
+
+    /// true if no hex between the two points rises
+    /// above the line between the observer and target
+    pub fn visible(&self, from: &Point, to: &Point) -> bool {
+        let line = from.line(*to);
+        let n = line.len().saturating_sub(1);
+
+        if n < 2 {
+            return true;
+        }
+
+        let start = self.elevation(from) + EYE;
+        let end = self.elevation(to) + EYE;
+
+        line.iter()
+            .enumerate()
+            .skip(1)
+            .take(n - 1)
+            .all(|(i, p)| {
+                let t = i as f32 / n as f32;
+                self.elevation(p) <= start + (end - start) * t
+            })
+    }
+
+    /// true if any of the units is close enough to see the target
+    pub fn spotted(&self, spotters: &Vec<&Unit>, target: &Point) -> bool {
+        spotters.iter().any(|u| {
+            let p = u.position();
+            p.distance(*target) <= SIGHT && self.visible(p, target)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn areas(heights: Vec<(i32, f32)>) -> HashMap<Location, Area> {
+        heights
+            .into_iter()
+            .map(|(x, e)| ((x, 0), Area::create()
+                .with_location((x, 0))
+                .with_elevation(e)
+                .with_textures(vec![0])
+                .build()))
+            .collect()
+    }
+
+    #[test]
+    fn test_flat_ground_is_visible() {
+        let map = areas(vec![(0, 10.), (1, 10.), (2, 10.), (3, 10.)]);
+        let sight = Sight::new(&map);
+        assert!(sight.visible(&Point::new(0, 0), &Point::new(3, 0)));
+    }
+
+    #[test]
+    fn test_ridge_blocks_sight() {
+        let map = areas(vec![(0, 10.), (1, 10.), (2, 200.), (3, 10.)]);
+        let sight = Sight::new(&map);
+        assert!(!sight.visible(&Point::new(0, 0), &Point::new(3, 0)));
+    }
+}
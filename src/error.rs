@@ -18,6 +18,42 @@ pub enum Error {
     #[error("The target position is too small to hold the selection")]
     TargetTooSmall,
 
+    #[error("Could not read the file")]
+    Io(#[from] std::io::Error),
+
+    #[error("Unknown file format: {0}")]
+    UnknownFormat(String),
+
+    #[error("Could not parse the scenario: {0}")]
+    ScenarioInvalid(String),
+
+    #[error("Could not parse the replay: {0}")]
+    ReplayInvalid(String),
+
+    #[error("Could not export the log: {0}")]
+    ExportFailed(String),
+
+    #[error("Could not pack the message: {0}")]
+    PackFailed(String),
+
+    #[error("Could not parse the turn file: {0}")]
+    MailInvalid(String),
+
+    #[error("The turn file's signature doesn't match the game password")]
+    MailSignature,
+
+    #[error("Turn files can't be signed without a game password")]
+    MailPassword,
+
+    #[error("Protocol version {0} does not match the server's ({1})")]
+    ProtocolMismatch(u32, u32),
+
+    #[error("Game version {0} does not match the server's ({1})")]
+    GameMismatch(String, String),
+
+    #[error("Game data does not match the server's")]
+    DataMismatch,
+
     #[error("Tilemap operation failed")]
     TilemapError(#[from] bevy_tilemap::tilemap::TilemapError),
 }
@@ -156,6 +156,10 @@ impl Area {
         self.id.clone()
     }
 
+    pub fn textures(&self) -> &Vec<usize> {
+        &self.textures
+    }
+
     pub fn texture(&self) -> Option<usize> {
         self.textures.get(0).cloned()
     }
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Display, Formatter, Result};
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Condition {
+    Injury,     // sprains, fractures, falls on rough ground
+    Frostbite,  // cold exposure (tundra, winter nights)
+    Heatstroke, // heat exposure (desert, summer days)
+    Illness,    // disease from damp and exposure
+    Breakdown,  // the soldier's vehicle is out of action
+}
+
+/// a condition currently affecting a soldier
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Affliction {
+    /// the type of condition
+    pub condition: Condition,
+
+    /// turns remaining until recovery
+    pub turns: u8,
+}
+
+impl Condition {
+    /// number of turns before the soldier recovers
+    pub fn duration(&self) -> u8 {
+        match self {
+            Condition::Injury => 7,
+            Condition::Frostbite => 10,
+            Condition::Heatstroke => 3,
+            Condition::Illness => 5,
+            Condition::Breakdown => 2,
+        }
+    }
+
+    /// immediate health lost when the condition occurs
+    pub fn severity(&self) -> u8 {
+        match self {
+            Condition::Injury => 30,
+            Condition::Frostbite => 25,
+            Condition::Heatstroke => 15,
+            Condition::Illness => 10,
+            Condition::Breakdown => 0,
+        }
+    }
+}
+
+impl Affliction {
+    pub fn new(condition: Condition) -> Self {
+        Self {
+            condition,
+            turns: condition.duration(),
+        }
+    }
+
+    /// advance one turn, return true if recovered
+    pub fn tick(&mut self) -> bool {
+        self.turns = self.turns.saturating_sub(1);
+        self.turns == 0
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_affliction_recovers() {
+        let mut affliction = Affliction::new(Condition::Heatstroke);
+        assert!(!affliction.tick());
+        assert!(!affliction.tick());
+        assert!(affliction.tick());
+        assert!(affliction.tick());
+    }
+}
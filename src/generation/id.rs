@@ -94,6 +94,11 @@ macro_rules! identifier_ex {
                 $name(uuid::Uuid::new_v4())
             }
 
+            /// read an id back from its displayed form
+            pub fn parse(text: &str) -> Option<Self> {
+                uuid::Uuid::parse_str(text).ok().map($name)
+            }
+
         }
 
         impl fmt::Display for $name {
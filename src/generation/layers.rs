@@ -5,6 +5,7 @@ pub enum LayerUse {
     Tilemap,
     Selection,
     Overlay,
+    Structures,
     Units,
 }
 
@@ -21,6 +22,7 @@ impl Default for Layers {
                 (LayerKind::Dense, LayerUse::Tilemap),
                 (LayerKind::Dense, LayerUse::Tilemap),
                 (LayerKind::Dense, LayerUse::Overlay),
+                (LayerKind::Sparse, LayerUse::Structures),
                 (LayerKind::Sparse, LayerUse::Units),
                 (LayerKind::Sparse, LayerUse::Selection),
             ],
@@ -72,7 +74,8 @@ mod tests {
         assert_eq!(layers.nth(1, &LayerUse::Tilemap), Some(1));
         assert_eq!(layers.nth(2, &LayerUse::Tilemap), Some(2));
         assert_eq!(layers.nth(0, &LayerUse::Overlay), Some(3));
-        assert_eq!(layers.nth(0, &LayerUse::Selection), Some(5));
+        assert_eq!(layers.nth(0, &LayerUse::Structures), Some(4));
+        assert_eq!(layers.nth(0, &LayerUse::Selection), Some(6));
 
         // negative cases
         assert_eq!(layers.nth(1, &LayerUse::Selection), None);
@@ -87,6 +90,6 @@ mod tests {
 
         assert_eq!(layers.max(&LayerUse::Tilemap), Some(2));
         assert_eq!(layers.max(&LayerUse::Overlay), Some(3));
-        assert_eq!(layers.max(&LayerUse::Selection), Some(5));
+        assert_eq!(layers.max(&LayerUse::Selection), Some(6));
     }
 }
@@ -5,17 +5,20 @@ mod terrain;
 mod weather;
 
 mod area;
+mod condition;
 mod marker;
+mod order;
 
 pub mod id;
 pub mod unit;
+pub mod veterancy;
 
 pub use factors::Factors;
 pub use generate::Generator;
 
 pub use weather::{Weather, WeatherType};
 
-pub use terrain::{Biome, Foliage, Soil, Structure};
+pub use terrain::{Biome, Foliage, Soil, Structure, Time};
 
 pub use layers::{LayerUse, Layers};
 
@@ -23,6 +26,12 @@ pub use area::{bounds, Area, Attribute};
 
 pub use marker::{Cursor, Marker};
 
-pub use unit::{Specialty, Unit, Units, Change, ChangeType};
+pub use condition::{Affliction, Condition};
+
+pub use veterancy::Veterancy;
+
+pub use order::{Order, Orders};
+
+pub use unit::{Specialty, Soldier, Unit, Units, Change, ChangeType};
 
 pub use id::{PlayerId,Id};
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result};
+use crate::objects::Point;
+
+/// A standing order that is carried out at the start of each turn
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq)]
+pub enum Order {
+    Move(Point),       // move to a position, stopping before enemies
+    Hold(u8),          // stay in place for a number of turns
+    AttackMove(Point), // move to a position, attacking enemies on the way
+    DigIn,             // fortify the current position
+}
+
+/// Orders waiting to be carried out by a unit
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Orders {
+    queue: Vec<Order>,
+}
+
+impl Order {
+    /// the destination of the order, if it moves the unit
+    pub fn target(&self) -> Option<Point> {
+        match self {
+            Order::Move(p) => Some(*p),
+            Order::AttackMove(p) => Some(*p),
+            _ => None,
+        }
+    }
+
+    /// true if the unit should engage enemies on the way
+    pub fn is_aggressive(&self) -> bool {
+        matches!(self, Order::AttackMove(_))
+    }
+}
+
+impl Orders {
+    pub fn new() -> Self {
+        Self { queue: vec![] }
+    }
+
+    pub fn list(&self) -> &Vec<Order> {
+        &self.queue
+    }
+
+    pub fn current(&self) -> Option<&Order> {
+        self.queue.first()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// add an order to the end of the queue
+    pub fn push(&mut self, order: Order) {
+        self.queue.push(order);
+    }
+
+    /// replace all orders in the queue
+    pub fn set(&mut self, orders: Vec<Order>) {
+        self.queue = orders;
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    /// waypoints for all orders that move the unit
+    pub fn waypoints(&self) -> Vec<Point> {
+        self.queue
+            .iter()
+            .filter_map(Order::target)
+            .collect()
+    }
+
+    /// update the current order after the unit acted on it,
+    /// dropping it once it's complete
+    pub fn progress(&mut self, position: &Point) {
+        let complete = match self.queue.first_mut() {
+            Some(Order::Move(p)) => p == position,
+            Some(Order::AttackMove(p)) => p == position,
+            Some(Order::Hold(n)) => {
+                *n = n.saturating_sub(1);
+                *n == 0
+            }
+            Some(Order::DigIn) => true,
+            None => false,
+        };
+
+        if complete {
+            self.queue.remove(0);
+        }
+    }
+}
+
+impl Display for Order {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            Order::Move(p) => write!(f, "Move to {:?}", p.integers()),
+            Order::Hold(n) => write!(f, "Hold for {} turns", n),
+            Order::AttackMove(p) => write!(f, "Attack-move to {:?}", p.integers()),
+            Order::DigIn => write!(f, "Dig in"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_completes_at_target() {
+        let target = Point::new(3, 4);
+        let mut orders = Orders::new();
+        orders.push(Order::Move(target));
+        orders.push(Order::DigIn);
+
+        orders.progress(&Point::new(2, 4));
+        assert_eq!(orders.current(), Some(&Order::Move(target)));
+
+        orders.progress(&target);
+        assert_eq!(orders.current(), Some(&Order::DigIn));
+    }
+
+    #[test]
+    fn test_hold_counts_down() {
+        let here = Point::new(0, 0);
+        let mut orders = Orders::new();
+        orders.push(Order::Hold(2));
+
+        orders.progress(&here);
+        assert_eq!(orders.current(), Some(&Order::Hold(1)));
+
+        orders.progress(&here);
+        assert!(orders.is_empty());
+    }
+}
@@ -28,7 +28,7 @@ pub enum Soil {
     Loam,  // high fert, med moisture
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Foliage {
     Grass,
     Trees,
@@ -37,7 +37,7 @@ pub enum Foliage {
     Rocks,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Structure {
     None,
     Trenches,
@@ -80,6 +80,18 @@ impl Display for Soil {
     }
 }
 
+impl Display for Foliage {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl Display for Structure {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        Debug::fmt(self, f)
+    }
+}
+
 impl From<u8> for Biome {
     fn from(v: u8) -> Self {
         match v {
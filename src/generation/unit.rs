@@ -1,12 +1,14 @@
 use serde::{Deserialize, Serialize};
-use crate::generation::{Id, PlayerId, LayerUse, Marker};
+use crate::generation::{Id, PlayerId, LayerUse, Marker, Affliction, Condition, Veterancy, Orders};
+use crate::generation::veterancy::*;
 use crate::objects::{Name,Point,Property};
 use crate::state::demographics::{Demographics, Sex};
 use crate::state::traits::*;
 use crate::state::State;
-use crate::resources::Label;
+use crate::resources::{Label,Textures};
 use rand_pcg::Pcg64;
 use crate::networking::messages::PlayerData;
+use crate::behavior::Hazards;
 use rand::{Rng,rngs::ThreadRng};
 
 #[derive(Deserialize, Serialize, Debug, Clone, Copy)]
@@ -17,9 +19,55 @@ pub enum Specialty {
     Medical,
     Logistics,    
     Mechanic,
+    Artillery,
     // ... etc
 }
 
+impl Specialty {
+    /// every specialty a unit can have
+    pub fn all() -> Vec<Specialty> {
+        vec![
+            Specialty::Infantry,
+            Specialty::Armor,
+            Specialty::Militia,
+            Specialty::Medical,
+            Specialty::Logistics,
+            Specialty::Mechanic,
+            Specialty::Artillery,
+        ]
+    }
+
+    /// true if the specialty operates vehicles
+    pub fn has_vehicles(&self) -> bool {
+        match self {
+            Specialty::Armor => true,
+            Specialty::Logistics => true,
+            Specialty::Mechanic => true,
+            Specialty::Artillery => true,
+            _ => false,
+        }
+    }
+
+    /// maximum firing distance in hexes (0 can't fire)
+    pub fn range(&self) -> i32 {
+        match self {
+            Specialty::Infantry => 2,
+            Specialty::Armor => 3,
+            Specialty::Militia => 1,
+            Specialty::Artillery => 8,
+            _ => 0,
+        }
+    }
+
+    /// true if the specialty fires over obstacles at unseen targets
+    pub fn is_indirect(&self) -> bool {
+        match self {
+            Specialty::Artillery => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Soldier {
     skill: Specialty,  // occupation
@@ -34,6 +82,7 @@ pub struct Soldier {
     morale: Property,
     defense: Property,
     attack: Property,
+    conditions: Vec<Affliction>,
 }
 
 impl Soldier {
@@ -54,6 +103,7 @@ impl Soldier {
             morale: Property::new(100, 0,100),
             defense: Property::new(100, 0, 100),
             attack: Property::new(100, 0, 100),
+            conditions: vec![],
         }
     }
 
@@ -129,6 +179,67 @@ impl Soldier {
         &mut self.attack
     }
 
+    pub fn conditions(&self) -> &Vec<Affliction> {
+        &self.conditions
+    }
+
+    /// apply a new condition to the soldier
+    pub fn afflict(&mut self, condition: Condition) {
+        self.health -= condition.severity();
+        self.conditions.push(Affliction::new(condition));
+    }
+
+    /// advance all conditions by a turn, dropping recovered ones
+    pub fn recover(&mut self) {
+        for condition in self.conditions.iter_mut() {
+            condition.tick();
+        }
+        self.conditions.retain(|c| c.turns > 0);
+    }
+
+    /// actions available each turn, with none while the soldier's
+    /// vehicle is broken down and half while otherwise unwell
+    pub fn max_actions(&self) -> u8 {
+        let max = self.actions.max();
+        if self.conditions.iter().any(|c| c.condition == Condition::Breakdown) {
+            0
+        } else if self.conditions.is_empty() {
+            max
+        } else {
+            max / 2
+        }
+    }
+
+    /// true if the soldier is healthy and fit for duty
+    pub fn is_ready(&self) -> bool {
+        self.health.val() > 0 && self.conditions.is_empty()
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.health.val() > 0
+    }
+
+    pub fn veterancy(&self) -> Veterancy {
+        Veterancy::from(self.veteran.val())
+    }
+
+    /// attack modified by veterancy
+    pub fn accuracy(&self) -> u8 {
+        let v = self.attack.val() as f32 * self.veterancy().accuracy();
+        v.round() as u8
+    }
+
+    /// add experience to the soldier
+    pub fn gain(&mut self, experience: u8) {
+        self.veteran += experience;
+    }
+
+    /// lose morale, reduced by veterancy
+    pub fn demoralize(&mut self, loss: u8) {
+        let v = loss as f32 * (1.0 - self.veterancy().resolve());
+        self.morale -= v.round() as u8;
+    }
+
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -156,6 +267,9 @@ pub struct Unit {
 
     /// soldiers in this unit
     soldiers: Vec<Soldier>,
+
+    /// standing orders carried out each turn
+    orders: Orders,
 }
 
 /// Combined units for unit-to-unit interactions:
@@ -184,9 +298,15 @@ pub struct Change {
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum ChangeType {
+    /// health added to every soldier, negative for damage
     Health(i16),
     Morale(i16),
     Attack(i16),
+    Experience(i16),
+    Afflict(usize, Condition),
+    Reassign(Id, Vec<usize>),
+    Order(Point, u8),
+    Dig(u8),
 }
 
 impl Unit {
@@ -206,6 +326,7 @@ impl Unit {
             },
             specialty: Specialty::Infantry,
             soldiers: vec![],
+            orders: Orders::new(),
         }
     }
 
@@ -243,13 +364,12 @@ impl Unit {
             .get(&LayerUse::Units)
             .expect("Must have unit layer");
 
-        self.marker.texture = state.textures.unit(&self.specialty,self.player_order);
-
         for _ in 0..self.soldiers.capacity() {
             self.soldiers.push(
                 Soldier::new(&self.specialty));
         }
 
+        self.refresh(&state.textures);
         self
     }
 
@@ -259,13 +379,19 @@ impl Unit {
             .get(&LayerUse::Units)
             .expect("Must have unit layer");
 
-        self.marker.texture = state.textures.unit(&self.specialty,self.player_order);
+        self.refresh(&state.textures);
         self
     }
 
+    /// update the texture to match the current veterancy
+    pub fn refresh(&mut self, textures: &Textures) {
+        self.marker.texture = textures.unit(&self.specialty,self.veterancy().tier());
+    }
+
     pub fn reset_actions(&mut self) {
         for soldier in self.soldiers.iter_mut() {
-            soldier.actions_mut().reset();
+            let max = soldier.max_actions();
+            soldier.actions_mut().set(max);
         }
     }
 
@@ -275,12 +401,109 @@ impl Unit {
         }
     }
 
+    /// advance the conditions of every soldier by a turn
+    pub fn recover(&mut self) {
+        for soldier in self.soldiers.iter_mut() {
+            soldier.recover();
+        }
+    }
+
+    /// roll environmental hazards for every living soldier and
+    /// return the index of the afflicted with their condition
+    pub fn expose<R: Rng>(&self, hazards: &Hazards, rng: &mut R) -> Vec<(usize, Condition)> {
+        self.soldiers
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.is_alive())
+            .filter_map(|(i, _)| hazards
+                .roll(rng, &self.specialty)
+                .map(|c| (i, c)))
+            .collect()
+    }
+
+    /// apply a condition to a single soldier
+    pub fn afflict(&mut self, soldier: usize, condition: Condition) {
+        if let Some(s) = self.soldiers.get_mut(soldier) {
+            s.afflict(condition);
+        }
+    }
+
+    /// award experience for surviving the turn and
+    /// for any soldiers that acted during it
+    pub fn train(&mut self) {
+        for soldier in self.soldiers.iter_mut().filter(|s| s.is_alive()) {
+            let (a, _, m) = soldier.actions().inner();
+            soldier.gain(EXP_TURN);
+            if a < m {
+                soldier.gain(EXP_ACTION);
+            }
+        }
+    }
+
+    pub fn gain_experience(&mut self, v: i16) {
+        let v = v.max(0).min(255) as u8;
+        for soldier in self.soldiers.iter_mut().filter(|s| s.is_alive()) {
+            soldier.gain(v);
+        }
+    }
+
+    pub fn demoralize(&mut self, v: i16) {
+        let v = v.abs().min(255) as u8;
+        for soldier in self.soldiers.iter_mut().filter(|s| s.is_alive()) {
+            soldier.demoralize(v);
+        }
+    }
+
+    /// remove the soldiers at the given indices, always
+    /// leaving at least one soldier in the unit
+    pub fn detach(&mut self, indices: &Vec<usize>) -> Vec<Soldier> {
+        let mut indices = indices.clone();
+        indices.sort();
+        indices.dedup();
+        indices.retain(|&i| i < self.soldiers.len());
+
+        if indices.len() >= self.soldiers.len() {
+            return vec![];
+        }
+
+        indices
+            .into_iter()
+            .rev()
+            .map(|i| self.soldiers.remove(i))
+            .collect()
+    }
+
+    /// add soldiers (with their experience) to the unit
+    pub fn attach(&mut self, soldiers: Vec<Soldier>) {
+        self.soldiers.extend(soldiers);
+    }
+
+    /// set the health of every soldier outright, for restoring
+    /// a unit rather than applying a change to it
     pub fn set_health(&mut self, v: i16) {
         for soldier in self.soldiers.iter_mut() {
             soldier.health_mut().set(v);
         }
     }
 
+    /// add or remove health from every soldier
+    pub fn update_health(&mut self, v: i16) {
+        for soldier in self.soldiers.iter_mut() {
+            soldier.health_mut().update(v);
+        }
+    }
+
+    /// apply a change that only affects the unit itself
+    pub fn apply(&mut self, action: &ChangeType) {
+        match *action {
+            ChangeType::Health(v) => self.update_health(v),
+            ChangeType::Morale(v) => self.demoralize(v),
+            ChangeType::Experience(v) => self.gain_experience(v),
+            ChangeType::Afflict(i, c) => self.afflict(i, c),
+            _ => (),
+        }
+    }
+
     pub fn health(&self) -> u8 {
         let s = &self.soldiers;
         let v: usize = s
@@ -318,21 +541,83 @@ impl Unit {
         let s = &self.soldiers;
         let v: usize = s
             .iter()
-            .map(|s| s.actions().max())
+            .map(|s| s.max_actions())
+            .map(|v| v as usize)
+            .sum::<usize>()
+            / s.len();
+        v.min(255) as u8
+    }
+
+    /// the number of soldiers still alive
+    pub fn living(&self) -> usize {
+        self.soldiers
+            .iter()
+            .filter(|s| s.is_alive())
+            .count()
+    }
+
+    /// aggregate veterancy of living soldiers
+    pub fn veterancy(&self) -> Veterancy {
+        let s = self
+            .soldiers
+            .iter()
+            .filter(|s| s.is_alive())
+            .collect::<Vec<&Soldier>>();
+        let v: usize = s
+            .iter()
+            .map(|s| s.veteran().val())
+            .map(|v| v as usize)
+            .sum::<usize>()
+            / s.len().max(1);
+        Veterancy::from(v.min(100) as u8)
+    }
+
+    /// accuracy of the soldiers fit for duty, spread over
+    /// the whole unit so that it falls with readiness
+    pub fn accuracy(&self) -> u8 {
+        let s = &self.soldiers;
+        let v: usize = s
+            .iter()
+            .filter(|s| s.is_ready())
+            .map(|s| s.accuracy())
             .map(|v| v as usize)
             .sum::<usize>()
             / s.len();
         v.min(255) as u8
     }
 
+    /// percentage of soldiers that are fit for duty
+    pub fn readiness(&self) -> u8 {
+        let s = &self.soldiers;
+        let v: usize = s
+            .iter()
+            .filter(|s| s.is_ready())
+            .count()
+            * 100
+            / s.len().max(1);
+        v.min(100) as u8
+    }
+
     pub fn soldiers(&self) -> &Vec<Soldier> {
         &self.soldiers
     }
 
+    pub fn soldiers_mut(&mut self) -> &mut Vec<Soldier> {
+        &mut self.soldiers
+    }
+
     pub fn specialty(&self) -> &Specialty {
         &self.specialty
     }
 
+    pub fn orders(&self) -> &Orders {
+        &self.orders
+    }
+
+    pub fn orders_mut(&mut self) -> &mut Orders {
+        &mut self.orders
+    }
+
     pub fn player_id(&self) -> &PlayerId {
         &self.player_id
     }
@@ -362,56 +647,64 @@ impl<'a> Units<'a> {
 
         atk1 = atk1.saturating_add(var1);
         atk2 = atk2.saturating_add(var2);
-        
-        if atk1 > atk2 {
-            let mut changes1 = self
-                .units()
-                .iter()
-                .map(|u| Change::health(u,-50))
-                .collect::<Vec<Change>>();
-            let mut changes2 = other
-                .units()
-                .iter()
-                .map(|u| Change::health(u,-100))
-                .collect::<Vec<Change>>();
-            changes1.append(&mut changes2);
-            changes1
+
+        // (health, morale, experience) for each side
+        let (r1, r2) = if atk1 > atk2 {
+            ((-50, 0, EXP_VICTORY), (-100, -20, EXP_COMBAT))
         }
         else if atk1 < atk2 {
-            let mut changes1 = self
-                .units()
-                .iter()
-                .map(|u| Change::health(u,-100))
-                .collect::<Vec<Change>>();
-            let mut changes2 = other
-                .units()
-                .iter()
-                .map(|u| Change::health(u,-50))
-                .collect::<Vec<Change>>();
-            changes1.append(&mut changes2);
-            changes1
+            ((-100, -20, EXP_COMBAT), (-50, 0, EXP_VICTORY))
         }
         else {
-            let mut changes1 = self
-                .units()
-                .iter()
-                .map(|u| Change::health(u,-50))
-                .collect::<Vec<Change>>();
-            let mut changes2 = other
-                .units()
-                .iter()
-                .map(|u| Change::health(u,-50))
-                .collect::<Vec<Change>>();
-            changes1.append(&mut changes2);
-            changes1
+            ((-50, 0, EXP_COMBAT), (-50, 0, EXP_COMBAT))
+        };
+
+        let mut changes1 = self.changes(r1);
+        let mut changes2 = other.changes(r2);
+        changes1.append(&mut changes2);
+        changes1
+    }
+
+    /// ranged fire that only harms the target, scaled
+    /// down for hexes away from the point of impact
+    pub fn fire(&self, other: &Units<'_>, scale: f32) -> Vec<Change> {
+        let attack = self.current_attack() as f32 * scale;
+        let health = -(attack / 4.).round() as i16;
+        let morale = -(attack / 10.).round() as i16;
+
+        let mut changes = vec![];
+        for unit in other.units().into_iter() {
+            changes.push(Change::health(unit, health));
+            changes.push(Change::morale(unit, morale));
         }
+        changes
+    }
+
+    /// experience for the units that fired
+    pub fn fired(&self) -> Vec<Change> {
+        self.units()
+            .into_iter()
+            .map(|u| Change::experience(u, EXP_COMBAT as i16))
+            .collect()
+    }
+
+    fn changes(&self, (health, morale, experience): (i16, i16, u8)) -> Vec<Change> {
+        let mut changes = vec![];
+        for unit in self.units().into_iter() {
+            changes.push(Change::health(unit, health));
+            if morale != 0 {
+                changes.push(Change::morale(unit, morale));
+            }
+            changes.push(Change::experience(unit, experience as i16));
+        }
+        changes
     }
 
     pub fn current_attack(&self) -> u8 {
         let s = &self.units;
         let v: usize = s
             .iter()
-            .map(|u| u.attack())
+            .map(|u| u.accuracy())
             .map(|v| v as usize)
             .sum::<usize>()
             / s.len();
@@ -431,6 +724,57 @@ impl Change {
             action: ChangeType::Health(change),
         }
     }
+
+    pub fn morale(unit: &Unit, change: i16) -> Self {
+        Self {
+            id: *unit.id(),
+            point: *unit.position(),
+            action: ChangeType::Morale(change),
+        }
+    }
+
+    pub fn experience(unit: &Unit, change: i16) -> Self {
+        Self {
+            id: *unit.id(),
+            point: *unit.position(),
+            action: ChangeType::Experience(change),
+        }
+    }
+
+    pub fn afflict(unit: &Unit, soldier: usize, condition: Condition) -> Self {
+        Self {
+            id: *unit.id(),
+            point: *unit.position(),
+            action: ChangeType::Afflict(soldier, condition),
+        }
+    }
+
+    pub fn reassign(unit: &Unit, target: &Unit, soldiers: Vec<usize>) -> Self {
+        Self {
+            id: *unit.id(),
+            point: *unit.position(),
+            action: ChangeType::Reassign(*target.id(), soldiers),
+        }
+    }
+
+    /// the unit carried out its current order, ending at
+    /// the given position and spending actions
+    pub fn order(unit: &Unit, point: Point, cost: u8) -> Self {
+        Self {
+            id: *unit.id(),
+            point: *unit.position(),
+            action: ChangeType::Order(point, cost),
+        }
+    }
+
+    /// the unit spent the turn fortifying its position
+    pub fn dig(unit: &Unit, amount: u8) -> Self {
+        Self {
+            id: *unit.id(),
+            point: *unit.position(),
+            action: ChangeType::Dig(amount),
+        }
+    }
 }
 
 impl HasMarker for Unit {
@@ -448,3 +792,111 @@ impl HasId for Unit {
         &self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(count: usize) -> Unit {
+        let mut unit = Unit::new(PlayerId::new());
+        for _ in 0..count {
+            unit.soldiers.push(Soldier::new(&Specialty::Infantry));
+        }
+        unit
+    }
+
+    #[test]
+    fn test_transfer_keeps_experience() {
+        let mut unit1 = unit(3);
+        let mut unit2 = unit(3);
+
+        unit1.soldiers[1].gain(85);
+        let name = unit1.soldiers[1].name();
+
+        let moved = unit1.detach(&vec![1]);
+        unit2.attach(moved);
+
+        assert_eq!(unit1.soldiers().len(), 2);
+        assert_eq!(unit2.soldiers().len(), 4);
+
+        let soldier = unit2.soldiers().last().unwrap();
+        assert_eq!(soldier.name(), name);
+        assert_eq!(soldier.veterancy(), Veterancy::Elite);
+    }
+
+    #[test]
+    fn test_detach_leaves_one_soldier() {
+        let mut unit = unit(2);
+        assert!(unit.detach(&vec![0, 1]).is_empty());
+        assert_eq!(unit.soldiers().len(), 2);
+    }
+
+    #[test]
+    fn test_veterancy_improves_accuracy() {
+        let mut unit = unit(2);
+        let before = unit.accuracy();
+        unit.gain_experience(100);
+        assert!(unit.accuracy() > before);
+        assert_eq!(unit.veterancy(), Veterancy::Elite);
+    }
+
+    #[test]
+    fn test_afflicted_units_fight_and_move_less() {
+        let healthy = unit(2);
+        let mut sick = unit(2);
+        sick.afflict(0, Condition::Illness);
+
+        assert!(sick.accuracy() < healthy.accuracy());
+        assert!(sick.max_actions() < healthy.max_actions());
+
+        let target = unit(2);
+        let damage = |u: &Unit| Units::aggregate(vec![u])
+            .fire(&Units::aggregate(vec![&target]), 1.0)
+            .into_iter()
+            .filter_map(|c| match c.action {
+                ChangeType::Health(h) => Some(h),
+                _ => None,
+            })
+            .sum::<i16>();
+        assert!(damage(&sick).abs() < damage(&healthy).abs());
+
+        // a broken down vehicle goes nowhere
+        sick.afflict(1, Condition::Breakdown);
+        sick.reset_actions();
+        assert_eq!(sick.soldiers()[1].actions().val(), 0);
+    }
+
+    #[test]
+    fn test_fire_only_harms_target() {
+        let shooter = unit(2);
+        let target = unit(2);
+
+        let changes = Units::aggregate(vec![&shooter])
+            .fire(&Units::aggregate(vec![&target]), 1.0);
+
+        assert!(!changes.is_empty());
+        assert!(changes.iter().all(|c| &c.id == target.id()));
+    }
+
+    #[test]
+    fn test_health_changes_are_relative() {
+        let mut unit = unit(2);
+        unit.update_health(-30);
+        assert_eq!(unit.health(), 70);
+        unit.update_health(-30);
+        assert_eq!(unit.health(), 40);
+    }
+
+    #[test]
+    fn test_health_change_is_a_delta() {
+        let mut unit = unit(2);
+
+        let damage = Change::health(&unit, -30);
+        unit.apply(&damage.action);
+        unit.apply(&damage.action);
+        assert_eq!(unit.health(), 40);
+
+        unit.apply(&Change::health(&unit, 20).action);
+        assert_eq!(unit.health(), 60);
+    }
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Display, Formatter, Result};
+
+/// experience gained for each turn survived
+pub const EXP_TURN: u8 = 1;
+
+/// experience gained for acting during a turn
+pub const EXP_ACTION: u8 = 1;
+
+/// experience gained for winning an engagement
+pub const EXP_VICTORY: u8 = 10;
+
+/// experience gained for surviving an engagement
+pub const EXP_COMBAT: u8 = 5;
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Veterancy {
+    Recruit,
+    Regular,
+    Seasoned,
+    Veteran,
+    Elite,
+}
+
+impl Veterancy {
+    /// get the tier for an experience value (0-100)
+    pub fn from(experience: u8) -> Self {
+        match experience {
+            v if v < 20 => Self::Recruit,
+            v if v < 40 => Self::Regular,
+            v if v < 60 => Self::Seasoned,
+            v if v < 80 => Self::Veteran,
+            _ => Self::Elite,
+        }
+    }
+
+    /// tier as a texture variant (0-4)
+    pub fn tier(&self) -> u8 {
+        *self as u8
+    }
+
+    /// fraction of attack that lands on target
+    pub fn accuracy(&self) -> f32 {
+        match self {
+            Self::Recruit => 0.6,
+            Self::Regular => 0.7,
+            Self::Seasoned => 0.8,
+            Self::Veteran => 0.9,
+            Self::Elite => 1.0,
+        }
+    }
+
+    /// fraction of morale loss that is ignored
+    pub fn resolve(&self) -> f32 {
+        match self {
+            Self::Recruit => 0.0,
+            Self::Regular => 0.1,
+            Self::Seasoned => 0.2,
+            Self::Veteran => 0.3,
+            Self::Elite => 0.4,
+        }
+    }
+}
+
+impl Default for Veterancy {
+    fn default() -> Self {
+        Self::Recruit
+    }
+}
+
+impl Display for Veterancy {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiers_from_experience() {
+        assert_eq!(Veterancy::from(0), Veterancy::Recruit);
+        assert_eq!(Veterancy::from(19), Veterancy::Recruit);
+        assert_eq!(Veterancy::from(20), Veterancy::Regular);
+        assert_eq!(Veterancy::from(59), Veterancy::Seasoned);
+        assert_eq!(Veterancy::from(79), Veterancy::Veteran);
+        assert_eq!(Veterancy::from(100), Veterancy::Elite);
+    }
+
+    #[test]
+    fn test_tiers_match_textures() {
+        assert_eq!(Veterancy::Recruit.tier(), 0);
+        assert_eq!(Veterancy::Elite.tier(), 4);
+    }
+}
@@ -22,7 +22,7 @@ mod state;
 mod systems;
 mod networking;
 
-use crate::state::{Action, State};
+use crate::state::{Action, Context, State};
 
 fn main() {
     App::build()
@@ -43,6 +43,7 @@ fn main() {
             mode: WindowMode::Windowed,
             ..Default::default()
         })
+        .init_resource::<Context>()
         .init_resource::<State>()
         .init_resource::<systems::gui::GuiState>()
         .init_resource::<systems::network::NetworkState>()
@@ -62,10 +63,18 @@ fn main() {
         // set up selection plugin/system
         .add_plugin(systems::selection::SelectionPlugin)
         .add_startup_system(systems::selection::setup.system())
+        .add_plugin(systems::editor::EditorPlugin)
 
         .add_plugin(systems::gui::GuiPlugin)
         .add_plugin(systems::network::NetworkPlugin)
+        .add_plugin(systems::turn::TurnPlugin)
+        .add_plugin(systems::lobby::LobbyPlugin)
+        .add_plugin(systems::replay::ReplayPlugin)
+        .add_plugin(systems::mail::MailPlugin)
+        .add_plugin(systems::opponent::OpponentPlugin)
+        .add_plugin(systems::scenario::ScenarioPlugin)
         .add_plugin(systems::overlay::OverlayPlugin)
+        .add_plugin(systems::structures::StructurePlugin)
         .add_plugin(systems::icon::IconPlugin)
         .add_plugin(systems::generate::GeneratorPlugin)
         .add_startup_system(setup.system())
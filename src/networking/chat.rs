@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+use crate::generation::Id;
+use crate::objects::Location;
+
+/// Who a chat message is meant for
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// every player in the game
+    All,
+
+    /// only players on the given team
+    Team(u8),
+
+    /// announcements about the game itself
+    System,
+}
+
+/// A piece of a chat message, either plain text or a link
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Text(String),
+    Location(Location),
+    Unit(Id),
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Channel::All
+    }
+}
+
+/// markup for a link to a hex, like "[4,-2]"
+pub fn location_link(location: &Location) -> String {
+    format!("[{},{}]", location.0, location.1)
+}
+
+/// markup for a link to a unit, like "[unit:<id>]"
+pub fn unit_link(id: &Id) -> String {
+    format!("[unit:{}]", id)
+}
+
+/// split a message into text and links, leaving any
+/// brackets that aren't valid links as text
+pub fn parse(content: &str) -> Vec<Segment> {
+    let mut segments = vec![];
+    let mut text = String::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find('[') {
+        let (before, after) = rest.split_at(start);
+        text.push_str(before);
+
+        let found = after
+            .find(']')
+            .map(|end| (link(&after[1..end]), end));
+
+        match found {
+            Some((Some(segment), end)) => {
+                if !text.is_empty() {
+                    segments.push(Segment::Text(text.split_off(0)));
+                }
+                segments.push(segment);
+                rest = &after[end + 1..];
+            }
+            _ => {
+                text.push('[');
+                rest = &after[1..];
+            }
+        }
+    }
+
+    text.push_str(rest);
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+
+    segments
+}
+
+fn link(inner: &str) -> Option<Segment> {
+    if let Some(id) = inner.strip_prefix("unit:") {
+        return Id::parse(id.trim()).map(Segment::Unit);
+    }
+
+    let mut parts = inner
+        .split(',')
+        .map(|p| p.trim().parse::<i32>().ok());
+
+    match (parts.next()??, parts.next()??, parts.next()) {
+        (x, y, None) => Some(Segment::Location((x, y))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_links() {
+        let id = Id::new();
+        let content = format!("hold {} with {}", location_link(&(4, -2)), unit_link(&id));
+
+        assert_eq!(parse(&content), vec![
+            Segment::Text("hold ".into()),
+            Segment::Location((4, -2)),
+            Segment::Text(" with ".into()),
+            Segment::Unit(id),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_leaves_plain_brackets() {
+        assert_eq!(parse("see [here] [1,2"), vec![
+            Segment::Text("see [here] [1,2".into()),
+        ]);
+    }
+}
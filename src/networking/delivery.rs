@@ -0,0 +1,281 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+use bevy_spicy_networking::ConnectionId;
+
+use crate::generation::PlayerId;
+
+/// how many message ids or sequence numbers are remembered
+/// before the oldest are forgotten
+pub const WINDOW: usize = 4096;
+
+/// A set that only remembers the most recent values
+pub struct Window<T> {
+    values: HashSet<T>,
+    order: VecDeque<T>,
+    size: usize,
+}
+
+impl<T: Hash + Eq + Copy> Window<T> {
+    pub fn new(size: usize) -> Self {
+        Self {
+            values: HashSet::new(),
+            order: VecDeque::new(),
+            size,
+        }
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.values.contains(value)
+    }
+
+    /// add a value, forgetting the oldest if full. Returns
+    /// false if the value was already in the window
+    pub fn insert(&mut self, value: T) -> bool {
+        if !self.values.insert(value) {
+            return false;
+        }
+
+        self.order.push_back(value);
+        if self.order.len() > self.size {
+            if let Some(old) = self.order.pop_front() {
+                self.values.remove(&old);
+            }
+        }
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.order.clear();
+    }
+}
+
+/// Where a sequence number came from. Everything the server
+/// sends shares one sequence, clients each have their own. A
+/// client's stream is found from its connection, never its
+/// messages, and follows its player across reconnects
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Debug)]
+pub enum Stream {
+    Server,
+    Player(PlayerId),
+    Connection(ConnectionId),
+}
+
+/// how many frames a stream waits for a missing message
+/// before giving up on it and moving on
+pub const PATIENCE: u32 = 120;
+
+/// Messages from one stream waiting for the next in sequence
+struct Pending<T> {
+    next: u64,
+    held: BTreeMap<u64, T>,
+    waited: u32,
+}
+
+impl<T> Pending<T> {
+    fn new(next: u64) -> Self {
+        Self {
+            next,
+            held: BTreeMap::new(),
+            waited: 0,
+        }
+    }
+
+    /// true if the missing message should be given up on
+    fn is_stalled(&self) -> bool {
+        self.waited > PATIENCE || self.held.len() > WINDOW
+    }
+}
+
+/// Messages from each stream, released in the order they were
+/// sent. Later messages are held until the gap before them fills
+pub struct Inbox<T> {
+    streams: HashMap<Stream, Pending<T>>,
+    unsequenced: Vec<T>,
+}
+
+impl<T> Inbox<T> {
+    pub fn new() -> Self {
+        Self {
+            streams: HashMap::new(),
+            unsequenced: vec![],
+        }
+    }
+
+    /// hold a message until it's next in its stream. Returns
+    /// false if it was seen before. Zero is never assigned, so
+    /// those messages are always accepted and released first
+    pub fn push(&mut self, stream: Stream, seq: u64, message: T) -> bool {
+        if seq == 0 {
+            self.unsequenced.push(message);
+            return true;
+        }
+
+        // a stream starts wherever it was first heard
+        let pending = self.streams
+            .entry(stream)
+            .or_insert_with(|| Pending::new(seq));
+
+        if seq < pending.next || pending.held.contains_key(&seq) {
+            return false;
+        }
+
+        pending.held.insert(seq, message);
+        true
+    }
+
+    /// the messages that are next in their streams, and the
+    /// streams that skipped a gap after waiting too long for it
+    pub fn release(&mut self) -> (Vec<T>, Vec<Stream>) {
+        let mut ready = std::mem::take(&mut self.unsequenced);
+        let mut gaps = vec![];
+
+        for (stream, pending) in self.streams.iter_mut() {
+            if pending.is_stalled() {
+                if let Some(first) = pending.held.keys().next() {
+                    pending.next = *first;
+                    gaps.push(*stream);
+                }
+            }
+
+            while let Some(message) = pending.held.remove(&pending.next) {
+                ready.push(message);
+                pending.next += 1;
+            }
+
+            pending.waited = match pending.held.is_empty() {
+                true => 0,
+                false => pending.waited + 1,
+            };
+        }
+
+        (ready, gaps)
+    }
+
+    pub fn clear(&mut self) {
+        self.streams.clear();
+        self.unsequenced.clear();
+    }
+}
+
+/// Messages sent to the server that it hasn't acknowledged,
+/// kept so they can be sent again after a reconnect
+pub struct Outbox<T> {
+    next: u64,
+    unacked: BTreeMap<u64, T>,
+}
+
+impl<T: Clone> Outbox<T> {
+    pub fn new() -> Self {
+        Self {
+            next: 0,
+            unacked: BTreeMap::new(),
+        }
+    }
+
+    /// the next sequence number to send with
+    pub fn stamp(&mut self) -> u64 {
+        self.next += 1;
+        self.next
+    }
+
+    /// keep a message until it's acknowledged, dropping
+    /// the oldest if too many are waiting
+    pub fn retain(&mut self, seq: u64, message: T) {
+        self.unacked.insert(seq, message);
+        while self.unacked.len() > WINDOW {
+            let first = *self.unacked.keys().next().unwrap();
+            self.unacked.remove(&first);
+        }
+    }
+
+    /// forget everything up to and including the sequence
+    pub fn ack(&mut self, seq: u64) {
+        self.unacked = self.unacked.split_off(&(seq + 1));
+    }
+
+    /// the unacknowledged messages in the order they were sent
+    pub fn pending(&self) -> Vec<T> {
+        self.unacked.values().cloned().collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.unacked.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_forgets_oldest() {
+        let mut window = Window::new(2);
+        assert!(window.insert(1));
+        assert!(window.insert(2));
+        assert!(!window.insert(2));
+        assert!(window.insert(3));
+
+        assert!(!window.contains(&1));
+        assert!(window.contains(&3));
+        assert_eq!(window.len(), 2);
+    }
+
+    #[test]
+    fn test_inbox_rejects_repeats() {
+        let mut inbox = Inbox::new();
+        let player = PlayerId::new();
+
+        assert!(inbox.push(Stream::Player(player), 1, 1));
+        assert!(!inbox.push(Stream::Player(player), 1, 1));
+        assert!(inbox.push(Stream::Server, 1, 1));
+        assert!(inbox.push(Stream::Server, 0, 0));
+        assert!(inbox.push(Stream::Server, 0, 0));
+
+        inbox.release();
+        assert!(!inbox.push(Stream::Player(player), 1, 1));
+    }
+
+    #[test]
+    fn test_inbox_holds_until_gap_fills() {
+        let mut inbox = Inbox::new();
+
+        inbox.push(Stream::Server, 1, 1);
+        inbox.push(Stream::Server, 3, 3);
+        assert_eq!(inbox.release(), (vec![1], vec![]));
+
+        inbox.push(Stream::Server, 2, 2);
+        assert_eq!(inbox.release(), (vec![2, 3], vec![]));
+    }
+
+    #[test]
+    fn test_inbox_skips_lasting_gap() {
+        let mut inbox = Inbox::new();
+
+        inbox.push(Stream::Server, 1, 1);
+        inbox.push(Stream::Server, 3, 3);
+        for _ in 0..=PATIENCE {
+            inbox.release();
+        }
+
+        assert_eq!(inbox.release(), (vec![3], vec![Stream::Server]));
+        assert!(!inbox.push(Stream::Server, 2, 2));
+    }
+
+    #[test]
+    fn test_outbox_drops_acknowledged() {
+        let mut outbox = Outbox::new();
+        for _ in 0..3 {
+            let seq = outbox.stamp();
+            outbox.retain(seq, seq);
+        }
+
+        outbox.ack(2);
+        assert_eq!(outbox.pending(), vec![3]);
+    }
+}
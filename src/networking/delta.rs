@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::generation::{Id, Unit};
+use crate::objects::Point;
+use crate::state::traits::*;
+
+/// The parts of a unit that change from turn to turn, sent by
+/// a peer so the server can tell which of its units have drifted.
+///
+/// Deltas are only used for these resyncs. Change and move messages
+/// already carry just the unit id and what changed, and a created
+/// unit is new to every peer, so it's sent whole and packed instead.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Summary {
+    pub id: Id,
+    pub position: Point,
+    pub soldiers: usize,
+    pub health: u8,
+    pub actions: u8,
+}
+
+/// How to bring one of a peer's units back in line
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum UnitDelta {
+    /// a unit the peer is missing or has different soldiers in
+    Full(Unit),
+
+    /// only the fields that differ, with health and
+    /// actions given for every soldier
+    Changed {
+        id: Id,
+        position: Option<Point>,
+        health: Option<Vec<u8>>,
+        actions: Option<Vec<u8>>,
+    },
+
+    /// a unit the peer has that no longer exists
+    Removed(Id),
+}
+
+impl Summary {
+    pub fn of(unit: &Unit) -> Self {
+        Self {
+            id: *unit.id(),
+            position: *unit.position(),
+            soldiers: unit.soldiers().len(),
+            health: unit.health(),
+            actions: unit.actions(),
+        }
+    }
+}
+
+impl UnitDelta {
+    pub fn id(&self) -> Id {
+        match self {
+            UnitDelta::Full(u) => *u.id(),
+            UnitDelta::Changed { id, .. } => *id,
+            UnitDelta::Removed(id) => *id,
+        }
+    }
+}
+
+/// find the smallest set of deltas that turn the summarized
+/// units into the given ones
+pub fn diff(units: &[&Unit], summaries: &[Summary]) -> Vec<UnitDelta> {
+    let known: HashMap<Id, &Summary> = summaries
+        .iter()
+        .map(|s| (s.id, s))
+        .collect();
+
+    let mut deltas: Vec<UnitDelta> = units
+        .iter()
+        .filter_map(|unit| {
+            let theirs = match known.get(unit.id()) {
+                Some(s) => s,
+                None => return Some(UnitDelta::Full((*unit).clone())),
+            };
+
+            let ours = Summary::of(unit);
+            if ours == **theirs {
+                return None;
+            }
+
+            if ours.soldiers != theirs.soldiers {
+                return Some(UnitDelta::Full((*unit).clone()));
+            }
+
+            Some(UnitDelta::Changed {
+                id: ours.id,
+                position: match ours.position != theirs.position {
+                    true => Some(ours.position),
+                    false => None,
+                },
+                health: match ours.health != theirs.health {
+                    true => Some(unit.soldiers().iter().map(|s| s.health().val()).collect()),
+                    false => None,
+                },
+                actions: match ours.actions != theirs.actions {
+                    true => Some(unit.soldiers().iter().map(|s| s.actions().val()).collect()),
+                    false => None,
+                },
+            })
+        })
+        .collect();
+
+    let ids: Vec<&Id> = units
+        .iter()
+        .map(|u| u.id())
+        .collect();
+
+    deltas.extend(summaries
+        .iter()
+        .filter(|s| !ids.contains(&&s.id))
+        .map(|s| UnitDelta::Removed(s.id)));
+
+    deltas
+}
+
+/// apply deltas to a list of units
+pub fn patch(units: Vec<Unit>, deltas: &[UnitDelta]) -> Vec<Unit> {
+    let mut changes: HashMap<Id, &UnitDelta> = deltas
+        .iter()
+        .map(|d| (d.id(), d))
+        .collect();
+
+    let mut result: Vec<Unit> = units
+        .into_iter()
+        .filter_map(|mut unit| match changes.remove(unit.id()) {
+            None => Some(unit),
+            Some(UnitDelta::Removed(_)) => None,
+            Some(UnitDelta::Full(u)) => Some(u.clone()),
+            Some(UnitDelta::Changed { position, health, actions, .. }) => {
+                if let Some(p) = position {
+                    unit.set_position(p);
+                }
+                for (i, soldier) in unit.soldiers_mut().iter_mut().enumerate() {
+                    if let Some(v) = health.as_ref().and_then(|h| h.get(i)) {
+                        soldier.health_mut().set(*v);
+                    }
+                    if let Some(v) = actions.as_ref().and_then(|a| a.get(i)) {
+                        soldier.actions_mut().set(*v);
+                    }
+                }
+                Some(unit)
+            },
+        })
+        .collect();
+
+    // units the peer didn't have at all, in the order they were sent
+    for delta in deltas.iter() {
+        if let UnitDelta::Full(unit) = delta {
+            if changes.contains_key(unit.id()) {
+                result.push(unit.clone());
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::{PlayerId, Soldier, Specialty};
+
+    fn unit(x: i32) -> Unit {
+        let mut unit = Unit::new(PlayerId::new())
+            .with_specialty(Specialty::Infantry)
+            .with_position((x, 0).into());
+        unit.attach(vec![Soldier::new(&Specialty::Infantry); 3]);
+        unit
+    }
+
+    #[test]
+    fn test_diff_sends_only_changes() {
+        let a = unit(1);
+        let mut b = unit(2);
+        let c = unit(3);
+
+        let summaries = vec![Summary::of(&a), Summary::of(&b), Summary::of(&c)];
+        b.set_position(&(5, 5).into());
+        b.update_health(-10);
+        let d = unit(4);
+
+        let deltas = diff(&[&a, &b, &d], &summaries);
+        assert_eq!(deltas.len(), 3);
+
+        match &deltas[0] {
+            UnitDelta::Changed { position, health, actions, .. } => {
+                assert_eq!(*position, Some((5, 5).into()));
+                assert_eq!(health.as_ref().map(|h| h.len()), Some(3));
+                assert!(actions.is_none());
+            },
+            _ => panic!("expected a change"),
+        }
+        assert!(matches!(deltas[1], UnitDelta::Full(_)));
+        assert!(matches!(deltas[2], UnitDelta::Removed(_)));
+
+        let patched = patch(vec![a.clone(), unit_from(&b, &summaries[1]), c], &deltas);
+        let ids: Vec<Id> = patched.iter().map(|u| *u.id()).collect();
+        assert_eq!(ids, vec![*a.id(), *b.id(), *d.id()]);
+        assert_eq!(*patched[1].position(), (5, 5).into());
+        assert_eq!(patched[1].health(), b.health());
+    }
+
+    /// the unit as the peer last saw it
+    fn unit_from(unit: &Unit, summary: &Summary) -> Unit {
+        let mut old = unit.clone();
+        old.set_position(&summary.position);
+        old.set_health(summary.health as i16);
+        old
+    }
+}
@@ -1,7 +1,6 @@
 use serde::{Deserialize, Serialize};
 use itertools::Itertools;
 use std::sync::Mutex;
-use std::collections::HashSet;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 
@@ -19,17 +18,23 @@ use bevy_spicy_networking::{
 };
 
 use crate::generation::Factors;
-use crate::generation::{Unit,id::*,Change};
+use crate::behavior::Difficulty;
+use crate::generation::{Unit,id::*,Change,Order};
 use crate::objects::Point;
+use crate::networking::chat::Channel;
+use crate::networking::delivery::{Window, WINDOW};
+use crate::networking::delta::{Summary, UnitDelta};
+use crate::objects::Fortifications;
+use crate::networking::version::{ClientHello, ServerHello};
 
-use crate::systems::network::NetworkState;
+use crate::systems::network::{NetworkFlag, NetworkState};
 use crate::systems::gui::GuiState;
-use crate::state::State;
+use crate::state::{Action, Attack, Context, Fire, Scenario, Side, Snapshot, State, TileOverride, Victory, SLOTS};
 use crate::resources::Label;
 use crate::state::traits::*;
 
-// messages that have been rebroadcasted
-static MESSAGES: Lazy<Mutex<HashSet<MessageId>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+// the most recent messages that have been applied
+static MESSAGES: Lazy<Mutex<Window<MessageId>>> = Lazy::new(|| Mutex::new(Window::new(WINDOW)));
 
 pub fn check_applied(id: MessageId) -> bool {
     MESSAGES.lock()
@@ -103,6 +108,14 @@ macro_rules! message {
                 self.0.header.id = Some(id);
             }
 
+            fn seq(&self) -> u64 {
+                self.0.header.seq
+            }
+
+            fn set_seq(&mut self, seq: u64) {
+                self.0.header.seq = seq;
+            }
+
             fn data(&self) -> MessageData {
                 MessageData::$d(self.0.clone())
             }
@@ -132,6 +145,10 @@ pub trait Message {
 
     fn set_id(&mut self, id: MessageId);
 
+    fn seq(&self) -> u64;
+
+    fn set_seq(&mut self, seq: u64);
+
     fn is_registered(&self) -> bool {
         self.id().is_some()
     }
@@ -149,9 +166,14 @@ pub trait Message {
 
 pub struct MessagePlugin;
 
+/// Secret handed to each player so they can reclaim
+/// their place after a dropped connection
+pub type Token = u64;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct HeaderData {
     pub id: Option<MessageId>,
+    pub seq: u64,
     pub sender: PlayerId,
     pub name: String,
 }
@@ -160,6 +182,7 @@ impl HeaderData {
     pub fn new(sender: PlayerId, name: String) -> Self {
         Self {
             id:     None,
+            seq:    0,
             sender: sender,
             name:   name,
         }
@@ -182,6 +205,9 @@ pub struct JoinData {
     pub header: HeaderData,
     pub name: String,
     pub code: usize,
+    pub token: Option<Token>,
+    pub password: String,
+    pub spectate: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -191,6 +217,55 @@ pub struct ConfirmData {
     pub code: usize,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RejectData {
+    pub header: HeaderData,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AckData {
+    pub header: HeaderData,
+    pub seq: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionData {
+    pub header: HeaderData,
+    pub player: PlayerId,
+    pub token: Token,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SnapshotData {
+    pub header: HeaderData,
+    pub snapshot: Snapshot,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChecksumData {
+    pub header: HeaderData,
+    pub turn: u32,
+    pub checksum: u64,
+    pub units: Vec<Summary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SyncData {
+    pub header: HeaderData,
+    pub turn: u32,
+    pub units: Vec<UnitDelta>,
+    pub fortifications: Fortifications,
+    pub pending: Vec<Attack>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PackedData {
+    pub header: HeaderData,
+    pub compressed: bool,
+    pub bytes: Vec<u8>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct UnitData {
     pub header: HeaderData,
@@ -207,6 +282,43 @@ pub struct MoveData {
 pub struct ChatData {
     pub header: HeaderData,
     pub message: String,
+    pub channel: Channel,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReadyData {
+    pub header: HeaderData,
+    pub turn: u32,
+    pub ready: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SeatData {
+    pub header: HeaderData,
+    pub slot: usize,
+    pub side: Side,
+    pub team: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AttackData {
+    pub header: HeaderData,
+    pub units: Vec<Id>,
+    pub target: Point,
+    pub fire: Fire,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TurnData {
+    pub header: HeaderData,
+    pub turn: u32,
+    pub changes: Vec<Change>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OrderData {
+    pub header: HeaderData,
+    pub orders: Vec<(Id,Vec<Order>)>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -214,6 +326,12 @@ pub struct PlayerData {
     pub id: PlayerId,
     pub name: String,
     pub order: usize,
+    pub side: Side,
+    pub team: u8,
+    pub ready: bool,
+    pub connected: bool,
+    pub spectator: bool,
+    pub computer: Option<Difficulty>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -221,8 +339,14 @@ pub struct UpdateData {
     pub header: HeaderData,
     pub seed: u32,
     pub turn: u32,
+    pub context: Context,
     pub factors: Factors,
+    pub timer: u32,
+    pub lobby: bool,
     pub players: Vec<PlayerData>,
+    pub victory: Victory,
+    pub scenario: Option<Scenario>,
+    pub tiles: Vec<TileOverride>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -230,32 +354,135 @@ pub enum MessageData {
     Join(JoinData),      // player joined
     Change(ChangeData),  // apply changes to units
     Confirm(ConfirmData),// confirm connection
+    Reject(RejectData),  // connection refused
+    Ack(AckData),        // messages received up to a sequence
+    Session(SessionData),// player id and token
+    Snapshot(SnapshotData),// full game state
+    Checksum(ChecksumData),// state hash after a turn
+    Sync(SyncData),      // units that drifted from the server
+    Packed(PackedData),  // another message, encoded and compressed
     Create(UnitData),    // unit created
     Move(MoveData),      // unit moved
     Chat(ChatData),      // chat message
     Update(UpdateData),  // update response
     Refresh(EmptyData),  // request update
+    Ready(ReadyData),    // player finished turn
+    Seat(SeatData),      // lobby slot changed
+    Attack(AttackData),  // attack queued for turn end
+    Turn(TurnData),      // turn resolved
+    Order(OrderData),    // standing orders changed
+}
+
+impl MessageData {
+    pub fn header(&self) -> &HeaderData {
+        match self {
+            MessageData::Join(v) => &v.header,
+            MessageData::Change(v) => &v.header,
+            MessageData::Confirm(v) => &v.header,
+            MessageData::Reject(v) => &v.header,
+            MessageData::Ack(v) => &v.header,
+            MessageData::Session(v) => &v.header,
+            MessageData::Snapshot(v) => &v.header,
+            MessageData::Checksum(v) => &v.header,
+            MessageData::Sync(v) => &v.header,
+            MessageData::Packed(v) => &v.header,
+            MessageData::Create(v) => &v.header,
+            MessageData::Move(v) => &v.header,
+            MessageData::Chat(v) => &v.header,
+            MessageData::Update(v) => &v.header,
+            MessageData::Refresh(v) => &v.header,
+            MessageData::Ready(v) => &v.header,
+            MessageData::Seat(v) => &v.header,
+            MessageData::Attack(v) => &v.header,
+            MessageData::Turn(v) => &v.header,
+            MessageData::Order(v) => &v.header,
+        }
+    }
+
+    pub fn header_mut(&mut self) -> &mut HeaderData {
+        match self {
+            MessageData::Join(v) => &mut v.header,
+            MessageData::Change(v) => &mut v.header,
+            MessageData::Confirm(v) => &mut v.header,
+            MessageData::Reject(v) => &mut v.header,
+            MessageData::Ack(v) => &mut v.header,
+            MessageData::Session(v) => &mut v.header,
+            MessageData::Snapshot(v) => &mut v.header,
+            MessageData::Checksum(v) => &mut v.header,
+            MessageData::Sync(v) => &mut v.header,
+            MessageData::Packed(v) => &mut v.header,
+            MessageData::Create(v) => &mut v.header,
+            MessageData::Move(v) => &mut v.header,
+            MessageData::Chat(v) => &mut v.header,
+            MessageData::Update(v) => &mut v.header,
+            MessageData::Refresh(v) => &mut v.header,
+            MessageData::Ready(v) => &mut v.header,
+            MessageData::Seat(v) => &mut v.header,
+            MessageData::Attack(v) => &mut v.header,
+            MessageData::Turn(v) => &mut v.header,
+            MessageData::Order(v) => &mut v.header,
+        }
+    }
+
+    /// true for messages applied in the order they were
+    /// sent and resent if a client drops before the ack
+    pub fn is_ordered(&self) -> bool {
+        matches!(self,
+            MessageData::Create(_) |
+            MessageData::Change(_) |
+            MessageData::Move(_) |
+            MessageData::Order(_) |
+            MessageData::Chat(_))
+    }
 }
 
 message!(Join,JoinMessage(JoinData));
 message!(Change,ChangeMessage(ChangeData));
 message!(Confirm,ConfirmMessage(ConfirmData));
+message!(Reject,RejectMessage(RejectData));
+message!(Ack,AckMessage(AckData));
+message!(Session,SessionMessage(SessionData));
+message!(Snapshot,SnapshotMessage(SnapshotData));
+message!(Checksum,ChecksumMessage(ChecksumData));
+message!(Sync,SyncMessage(SyncData));
+message!(Packed,PackedMessage(PackedData));
 message!(Create,CreateMessage(UnitData));
 message!(Move,MoveMessage(MoveData));
 message!(Chat,ChatMessage(ChatData));
 message!(Update,UpdateMessage(UpdateData));
 message!(Refresh,RefreshMessage(EmptyData));
+message!(Ready,ReadyMessage(ReadyData));
+message!(Seat,SeatMessage(SeatData));
+message!(Attack,AttackMessage(AttackData));
+message!(Turn,TurnMessage(TurnData));
+message!(Order,OrderMessage(OrderData));
 
 impl Plugin for MessagePlugin {
     fn build(&self, app: &mut AppBuilder) {
+        // the handshake only goes one way in each direction
+        app.listen_for_client_message::<ServerHello>();
+        app.listen_for_server_message::<ClientHello>();
+
         register!(app,JoinMessage);
         register!(app,ChangeMessage);
         register!(app,ConfirmMessage);
+        register!(app,RejectMessage);
+        register!(app,AckMessage);
+        register!(app,SessionMessage);
+        register!(app,SnapshotMessage);
+        register!(app,ChecksumMessage);
+        register!(app,SyncMessage);
+        register!(app,PackedMessage);
         register!(app,CreateMessage);
         register!(app,MoveMessage);
         register!(app,ChatMessage);
         register!(app,UpdateMessage);
         register!(app,RefreshMessage);
+        register!(app,ReadyMessage);
+        register!(app,SeatMessage);
+        register!(app,AttackMessage);
+        register!(app,TurnMessage);
+        register!(app,OrderMessage);
     }
 }
 
@@ -268,6 +495,12 @@ macro_rules! require {
             warn!($msg);
             return;
         } 
+    };
+    ( $check: expr, $msg: expr, $ret: expr ) => { 
+        if !$check { 
+            warn!($msg);
+            return $ret;
+        } 
     }
 }
 
@@ -284,38 +517,240 @@ macro_rules! require_other {
 }
 
 macro_rules! require_registered {
-    ( $message: ident ) => { require!(($message.is_registered()),"Must be registered") }
+    ( $message: ident ) => { require!(($message.is_registered()),"Must be registered") };
+    ( $message: ident, $ret: expr ) => { require!(($message.is_registered()),"Must be registered",$ret) }
 }
 
 macro_rules! require_unapplied {
-    ( $message: ident ) => { require!((!$message.is_applied()),"Must be unapplied") }
+    ( $message: ident ) => { require!((!$message.is_applied()),"Must be unapplied") };
+    ( $message: ident, $ret: expr ) => { require!((!$message.is_applied()),"Must be unapplied",$ret) }
 }
 
 impl PlayerData {
     pub fn new(id: PlayerId, name: String) -> Self {
-        Self { id, name, order: 0 }
+        Self {
+            id,
+            name,
+            order: 0,
+            side: Side::default(),
+            team: 1,
+            ready: false,
+            connected: true,
+            spectator: false,
+            computer: None,
+        }
     }
-    pub fn ordered(mut self, order: usize) -> Self {
-        self.order = order;
+    pub fn with_computer(mut self, difficulty: Difficulty) -> Self {
+        self.computer = Some(difficulty);
+        self
+    }
+    /// take a lobby slot with its default side and team
+    pub fn seated(mut self, slot: usize) -> Self {
+        self.order = slot;
+        self.side = Side::from_slot(slot);
+        self.team = slot as u8 + 1;
+        self
+    }
+    /// watch the game from outside the slots and teams
+    pub fn watching(mut self) -> Self {
+        self.order = SLOTS;
+        self.team = 0;
+        self.spectator = true;
         self
     }
 }
 
 impl JoinMessage {
-    pub fn apply(&self, network: &mut NetworkState, state: &State) {
+    pub fn apply(&self, network: &mut NetworkState, state: &mut State) {
         require_server!(network);
         require_unapplied!(self);
 
         debug!("applying join message");
 
-        if let Some(conn) = network.stop_waiting(self.value().code) {
-            let player = network.players.insert(
-                self.sender(),
-                conn,
-                self.value().name.clone(),
-            );
+        let data = self.value();
+        if let Some(conn) = network.stop_waiting(data.code) {
+            // incompatible or unwelcome clients are told why and never seated
+            if let Err(reason) = admit(network, data, &conn) {
+                info!("refused {}: {}", data.name, reason);
+                network.refuse(&conn, reason);
+                self.set_applied();
+                return;
+            }
+
+            // a known token takes back the slot it was given
+            let resumed = data.token
+                .map(|t| network.players.resume(t, conn))
+                .flatten();
+
+            // clients make up their own ids, so one that's taken
+            // can only be had again with its token
+            let claimed = match network.players.get(&self.sender()) {
+                Some(_) => PlayerId::new(),
+                None => self.sender(),
+            };
+
+            let player = match resumed {
+                Some(id) => {
+                    info!("player {} resumed as {}", data.name, id);
+                    network.players.set_name(&id, data.name.clone());
+                    network.send_system_event(format!("{} rejoined the game", data.name));
+                    id
+                },
+                // extra connections watch if the host allows it
+                None if data.spectate || network.players.count() >= network.max_players() => {
+                    network.players.insert_spectator(
+                        claimed,
+                        conn,
+                        data.name.clone(),
+                    );
+                    network.send_system_event(format!("{} is spectating", data.name));
+                    claimed
+                },
+                None => {
+                    network.players.insert(
+                        claimed,
+                        conn,
+                        data.name.clone(),
+                    );
+
+                    // starting forces are deployed by the scenario system
+                    if let Some(scenario) = state.scenario.as_mut() {
+                        scenario.claim(claimed);
+                    }
+                    network.send_system_event(format!("{} joined the game", data.name));
+                    claimed
+                },
+            };
+
+            let token = network.players.token(&player);
+
             network.send_update_event(state);
+            network.send_session_event(&conn, player, token);
+            network.send_snapshot_event(&conn, state.snapshot());
+        }
+        self.set_applied();
+    }
+}
+
+/// check a joining client against the host's rules
+fn admit(network: &NetworkState, data: &JoinData, conn: &ConnectionId) -> Result<(),String> {
+    if network.is_banned(conn, data.token) {
+        return Err("You are banned from this game".into());
+    }
+
+    let password = network.password();
+    if !password.is_empty() && data.password != password {
+        return Err("Wrong password".into());
+    }
+
+    let resuming = data.token
+        .map(|t| network.players.has_token(t))
+        .unwrap_or(false);
+
+    if data.spectate && !resuming && !network.allows_spectators() {
+        return Err("This game does not allow spectators".into());
+    }
+
+    let full = network.players.count() >= network.max_players();
+    if full && !resuming && !network.allows_spectators() {
+        return Err("The game is full".into());
+    }
+
+    Ok(())
+}
+
+impl SessionMessage {
+    pub fn apply(&self, network: &mut NetworkState) {
+        require_registered!(self);
+        require_unapplied!(self);
+
+        debug!("applying session message");
+
+        let data = self.value();
+        network.set_id(data.player);
+        network.set_token(data.token);
+
+        // anything the server missed before a drop goes again
+        network.resend();
+        self.set_applied();
+    }
+}
+
+impl AckMessage {
+    pub fn apply(&self, network: &mut NetworkState) {
+        require_client!(network);
+        require_registered!(self);
+        require_unapplied!(self);
+
+        network.acknowledge(self.value().seq);
+        self.set_applied();
+    }
+}
+
+impl SnapshotMessage {
+    /// return the snapshot to restore, unless this is
+    /// the host that the snapshot was taken from
+    pub fn apply(&self, network: &NetworkState) -> Option<Snapshot> {
+        require_registered!(self, None);
+        require_unapplied!(self, None);
+
+        debug!("applying snapshot message");
+
+        self.set_applied();
+        match network.is_client() {
+            true => Some(self.value().snapshot.clone()),
+            false => None,
+        }
+    }
+}
+
+impl SyncMessage {
+    /// return the changes to make, unless this is
+    /// the host that they were taken from
+    pub fn apply(&self, network: &NetworkState) -> Option<SyncData> {
+        require_registered!(self, None);
+        require_unapplied!(self, None);
+
+        debug!("applying sync message");
+
+        self.set_applied();
+        match network.is_client() {
+            true => Some(self.value().clone()),
+            false => None,
+        }
+    }
+}
+
+impl RefreshMessage {
+    pub fn apply(&self, network: &mut NetworkState, state: &State) {
+        require_server!(network);
+
+        debug!("applying refresh message");
+
+        network.send_update_event(state);
+        if let Some(conn) = network.players.connection(&self.sender()).cloned() {
+            network.send_snapshot_event(&conn, state.snapshot());
         }
+    }
+}
+
+impl ChecksumMessage {
+    pub fn apply(&self, network: &mut NetworkState, state: &State) {
+        require_server!(network);
+        require_unapplied!(self);
+
+        debug!("applying checksum message");
+
+        // only compare against the same turn, and only send
+        // the units that differ
+        let data = self.value();
+        if data.turn == state.turn() && data.checksum != state.checksum() {
+            warn!("player {} is out of sync on turn {}", self.name(), data.turn);
+            if let Some(conn) = network.players.connection(&self.sender()).cloned() {
+                network.send_sync_event(&conn, state, &data.units);
+            }
+        }
+
         self.set_applied();
     }
 }
@@ -329,6 +764,7 @@ impl ChangeMessage {
         debug!("applying change message");
 
         state.units.execute(map,&self.value().changes);
+        state.events.send(Action::UpdateUnits);
         self.set_applied();
     }
 }
@@ -340,15 +776,31 @@ impl ConfirmMessage {
 
         debug!("applying confirm message");
 
-        network.send_join_event(self.value().code);
+        let data = self.value();
+        network.send_join_event(data.code);
         gui.add_message(
             "Server".into(),
-            self.value().motd.clone(),
+            data.motd.clone(),
         );
         self.set_applied();
     }
 }
 
+impl RejectMessage {
+    pub fn apply(&self, network: &mut NetworkState, gui: &mut GuiState) {
+        require_client!(network);
+        require_registered!(self);
+        require_unapplied!(self);
+
+        debug!("applying reject message");
+
+        let reason = self.value().reason.clone();
+        gui.add_system(reason.clone());
+        network.reject(reason);
+        self.set_applied();
+    }
+}
+
 impl CreateMessage {
     pub fn apply(&self, network: &NetworkState, map: &mut Tilemap, state: &mut State) {
         require_other!(network,self.sender()); // cannot apply to self
@@ -381,7 +833,7 @@ impl MoveMessage {
             let movement = moves
                 .into_iter()
                 .map(|m| {
-                    warn!("moving {} to {:?}",m.0,point);
+                    debug!("moving {} to {:?}",m.0,point);
                     (m.0,m.2)
                 })
                 .collect();
@@ -399,16 +851,17 @@ impl ChatMessage {
 
         debug!("applying chat message");
 
-        gui.add_message(
+        gui.add_chat(
             self.name(),
-            self.value().message.clone());
+            self.value().message.clone(),
+            self.value().channel);
 
         self.set_applied();
     }
 }
 
 impl UpdateMessage {
-    pub fn apply(&self, network: &mut NetworkState, state: &mut State) {
+    pub fn apply(&self, network: &mut NetworkState, context: &mut Context, state: &mut State) {
         require_registered!(self);
         require_unapplied!(self);
 
@@ -417,8 +870,99 @@ impl UpdateMessage {
         let data = self.value();
 
         network.set_players(data.players.clone());
-        state.sync(data);
+
+        // units are lost when the map is rebuilt mid-game
+        if state.sync(context, data) && network.is_client() {
+            network.set_flag(NetworkFlag::Resync);
+        }
+
+        self.set_applied();
+    }
+}
+impl ReadyMessage {
+    /// mark the player on the connection ready, whoever
+    /// the message claims to be from
+    pub fn apply(&self, network: &mut NetworkState, conn: &ConnectionId, state: &State) {
+        require_server!(network);
+        require_unapplied!(self);
+
+        debug!("applying ready message");
+
+        let player = match network.players.id(conn) {
+            Some(id) => *id,
+            None => return,
+        };
+
+        // ignore stale messages from a previous turn
+        if self.value().turn == state.turn() {
+            network.players.set_ready(&player, self.value().ready);
+            network.send_update_event(state);
+        }
+
+        self.set_applied();
+    }
+}
+
+impl SeatMessage {
+    pub fn apply(&self, network: &mut NetworkState, state: &State) {
+        require_server!(network);
+        require_unapplied!(self);
+
+        debug!("applying seat message");
+
+        // slots are fixed once the game starts
+        let data = self.value();
+        if state.in_lobby() && network.players.seat(&self.sender(), data.slot, data.side, data.team) {
+            network.send_update_event(state);
+        }
+
+        self.set_applied();
+    }
+}
+
+impl AttackMessage {
+    pub fn apply(&self, network: &NetworkState, state: &mut State) {
+        require_server!(network);
+        require_unapplied!(self);
+
+        debug!("applying attack message");
+
+        let data = self.value();
+        state.queue_attack(Attack::new(
+            self.sender(),
+            data.units.clone(),
+            data.target,
+        ).with_fire(data.fire));
 
         self.set_applied();
     }
-}
\ No newline at end of file
+}
+
+impl TurnMessage {
+    pub fn apply(&self, network: &mut NetworkState, map: &mut Tilemap, state: &mut State) {
+        require_registered!(self);
+        require_unapplied!(self);
+
+        debug!("applying turn message");
+
+        let data = self.value();
+        state.advance(map, data.turn, &data.changes);
+        network.players.clear_ready();
+
+        self.set_applied();
+    }
+}
+
+impl OrderMessage {
+    pub fn apply(&self, network: &NetworkState, state: &mut State) {
+        require_other!(network,self.sender()); // cannot apply to self
+        require_registered!(self);
+        require_unapplied!(self);
+
+        debug!("applying order message");
+
+        state.units.set_orders(&self.sender(), &self.value().orders);
+
+        self.set_applied();
+    }
+}
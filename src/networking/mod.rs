@@ -1,2 +1,6 @@
-
-pub mod messages;
\ No newline at end of file
+pub mod chat;
+pub mod delivery;
+pub mod delta;
+pub mod messages;
+pub mod packing;
+pub mod version;
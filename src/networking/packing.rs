@@ -0,0 +1,133 @@
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+use crate::error::{Error, Result};
+use crate::networking::messages::{MessageData, PackedData};
+
+/// encoded messages larger than this many bytes are compressed
+pub const THRESHOLD: usize = 8 * 1024;
+
+/// packed messages may not expand past this many bytes
+pub const MAX_UNPACKED: usize = 16 * 1024 * 1024;
+
+/// true for messages that carry whole units or the whole game
+pub fn is_bulky(message: &MessageData) -> bool {
+    matches!(message,
+        MessageData::Create(_) |
+        MessageData::Snapshot(_) |
+        MessageData::Update(_) |
+        MessageData::Sync(_))
+}
+
+/// encode a bulky message as binary, compressing it if it's
+/// still large. Returns None for messages sent as they are
+pub fn pack(message: &MessageData) -> Option<PackedData> {
+    if !is_bulky(message) {
+        return None;
+    }
+
+    match encode(message) {
+        Ok(packed) => Some(packed),
+        Err(e) => {
+            log::warn!("sending unpacked: {}", e);
+            None
+        }
+    }
+}
+
+/// decode a packed message back into the one that was sent,
+/// refusing any that would expand past MAX_UNPACKED
+pub fn unpack(packed: &PackedData) -> Result<MessageData> {
+    let bytes = match packed.compressed {
+        true => {
+            // read one byte past the limit to tell if it was reached
+            let mut bytes = vec![];
+            DeflateDecoder::new(packed.bytes.as_slice())
+                .take(MAX_UNPACKED as u64 + 1)
+                .read_to_end(&mut bytes)?;
+            bytes
+        },
+        false => packed.bytes.clone(),
+    };
+
+    if bytes.len() > MAX_UNPACKED {
+        return Err(Error::PackFailed("message is too large".into()));
+    }
+
+    bincode::deserialize(&bytes)
+        .map_err(|e| Error::PackFailed(e.to_string()))
+}
+
+fn encode(message: &MessageData) -> Result<PackedData> {
+    let mut bytes = bincode::serialize(message)
+        .map_err(|e| Error::PackFailed(e.to_string()))?;
+
+    let compressed = bytes.len() > THRESHOLD;
+    if compressed {
+        let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+        encoder.write_all(&bytes)?;
+        bytes = encoder.finish()?;
+    }
+
+    Ok(PackedData {
+        header: message.header().clone(),
+        compressed,
+        bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::{PlayerId, Soldier, Specialty, Unit};
+    use crate::networking::chat::Channel;
+    use crate::networking::messages::{ChatData, HeaderData, UnitData};
+    use crate::state::traits::HasId;
+
+    #[test]
+    fn test_large_units_are_compressed() {
+        let mut unit = Unit::new(PlayerId::new());
+        unit.attach(vec![Soldier::new(&Specialty::Infantry); 100]);
+
+        let message = MessageData::Create(UnitData {
+            header: HeaderData::new(PlayerId::new(), "a".into()),
+            unit: unit.clone(),
+        });
+
+        let packed = pack(&message).unwrap();
+        assert!(packed.compressed);
+
+        match unpack(&packed).unwrap() {
+            MessageData::Create(data) => {
+                assert_eq!(data.unit.id(), unit.id());
+                assert_eq!(data.unit.soldiers().len(), 100);
+            },
+            _ => panic!("expected a create message"),
+        }
+    }
+
+    #[test]
+    fn test_small_messages_are_not_packed() {
+        let chat = MessageData::Chat(ChatData {
+            header: HeaderData::new(PlayerId::new(), "a".into()),
+            message: "hello".into(),
+            channel: Channel::All,
+        });
+        assert!(pack(&chat).is_none());
+    }
+
+    #[test]
+    fn test_oversized_messages_are_refused() {
+        let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+        encoder.write_all(&vec![0; MAX_UNPACKED + 1]).unwrap();
+
+        let packed = PackedData {
+            header: HeaderData::new(PlayerId::new(), "a".into()),
+            compressed: true,
+            bytes: encoder.finish().unwrap(),
+        };
+        assert!(matches!(unpack(&packed), Err(Error::PackFailed(_))));
+    }
+}
@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use bevy_spicy_networking::{ClientMessage, NetworkMessage, ServerMessage};
+use sha2::{Digest, Sha256};
+use std::convert::TryInto;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::error::{Error, Result};
+use crate::generation::Specialty;
+use crate::resources::TEXTURES;
+
+/// bumped whenever messages change shape
+pub const PROTOCOL: u32 = 2;
+
+/// What a peer was built from, compared when a client connects.
+/// It's part of the handshake, so its shape must never change
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Version {
+    pub protocol: u32,
+    pub game: String,
+    pub data: u64,
+}
+
+impl Version {
+    pub fn current() -> Self {
+        Self {
+            protocol: PROTOCOL,
+            game: env!("CARGO_PKG_VERSION").into(),
+            data: data_hash(),
+        }
+    }
+
+    /// fail with the first difference from the server's version
+    pub fn check(&self, server: &Version) -> Result<()> {
+        if self.protocol != server.protocol {
+            return Err(Error::ProtocolMismatch(self.protocol, server.protocol));
+        }
+
+        if self.game != server.game {
+            return Err(Error::GameMismatch(self.game.clone(), server.game.clone()));
+        }
+
+        if self.data != server.data {
+            return Err(Error::DataMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} (protocol {})", self.game, self.protocol)
+    }
+}
+
+/// Sent by the server as soon as a client connects, before
+/// anything else. Its shape must never change, so that a client
+/// from any build can read it and say why it can't join
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ServerHello {
+    pub version: Version,
+}
+
+/// Sent by a client that agrees with the server's version,
+/// which the server answers with a confirm message. Its
+/// shape must never change either
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClientHello {
+    pub version: Version,
+}
+
+impl ServerHello {
+    /// greet with this build's version
+    pub fn current() -> Self {
+        Self { version: Version::current() }
+    }
+}
+
+impl ClientHello {
+    /// greet with this build's version
+    pub fn current() -> Self {
+        Self { version: Version::current() }
+    }
+}
+
+#[typetag::serde]
+impl NetworkMessage for ServerHello {}
+
+impl ClientMessage for ServerHello {
+    const NAME: &'static str = "warfare:ServerHello";
+}
+
+#[typetag::serde]
+impl NetworkMessage for ClientHello {}
+
+impl ServerMessage for ClientHello {
+    const NAME: &'static str = "warfare:ClientHello";
+}
+
+/// a hash of the unit types and textures that both
+/// peers need to agree on to play together
+fn data_hash() -> u64 {
+    // the same on every platform and toolchain, unlike
+    // the standard library's hasher
+    let mut hasher = Sha256::new();
+
+    for specialty in Specialty::all().iter() {
+        hasher.update(format!("{:?}", specialty).as_bytes());
+        hasher.update([0u8]);
+        hasher.update(specialty.range().to_le_bytes());
+        hasher.update([specialty.has_vehicles() as u8, specialty.is_indirect() as u8]);
+    }
+
+    for texture in TEXTURES.iter() {
+        hasher.update(texture.as_bytes());
+        hasher.update([0u8]);
+    }
+
+    let digest = hasher.finalize();
+    u64::from_le_bytes(digest[..8].try_into().expect("digest is longer than 8 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_reports_first_difference() {
+        let ours = Version::current();
+        assert!(ours.check(&Version::current()).is_ok());
+
+        let mut theirs = ours.clone();
+        theirs.data = theirs.data.wrapping_add(1);
+        assert!(matches!(ours.check(&theirs), Err(Error::DataMismatch)));
+
+        theirs.protocol += 1;
+        assert!(matches!(ours.check(&theirs), Err(Error::ProtocolMismatch(..))));
+    }
+}
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use crate::generation::{Area, PlayerId, Soil, Structure};
+use crate::objects::Location;
+
+/// progress needed to finish a fortification
+const COMPLETE: u8 = 100;
+
+/// progress made in a turn of digging on ideal ground
+const RATE: f32 = 25.0;
+
+/// progress lost each turn while abandoned
+const DECAY: u8 = 10;
+
+/// below this temperature the ground is frozen
+const FROZEN: f32 = 0.0;
+
+/// fraction of losses prevented by finished works
+const COVER: f32 = 0.5;
+
+/// Field works dug by units on a single tile
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Fortification {
+    kind: Structure,
+    owner: PlayerId,
+    progress: u8,
+}
+
+/// All fortifications on the map
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Fortifications {
+    sites: HashMap<Location, Fortification>,
+
+    /// locations currently drawn on the map
+    #[serde(skip)]
+    pub drawn: Vec<Location>,
+}
+
+impl Fortification {
+    pub fn new(owner: PlayerId) -> Self {
+        Self {
+            kind: Structure::Trenches,
+            owner,
+            progress: 0,
+        }
+    }
+
+    /// progress made by a unit digging for a turn
+    pub fn rate(area: &Area) -> u8 {
+        let soil = match area.soil() {
+            Soil::Sand => 1.5,
+            Soil::Loam => 1.2,
+            Soil::Silt => 1.0,
+            Soil::Peat => 0.9,
+            Soil::Clay => 0.7,
+            Soil::Chalk => 0.6,
+            Soil::None => 1.0,
+        };
+
+        let rocks = 1.0 - area.rocks() as f32 / 100. * 0.7;
+
+        let frozen = if area.temperature() < FROZEN { 0.4 } else { 1.0 };
+
+        (RATE * soil * rocks * frozen).round().max(1.0) as u8
+    }
+
+    pub fn kind(&self) -> Structure {
+        self.kind
+    }
+
+    pub fn owner(&self) -> &PlayerId {
+        &self.owner
+    }
+
+    pub fn progress(&self) -> u8 {
+        self.progress
+    }
+
+    /// fraction of the works that have been dug
+    pub fn completion(&self) -> f32 {
+        self.progress.min(COMPLETE) as f32 / COMPLETE as f32
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.progress >= COMPLETE
+    }
+
+    /// fraction of losses prevented for units on this tile
+    pub fn cover(&self) -> f32 {
+        COVER * self.completion()
+    }
+}
+
+impl Fortifications {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, location: &Location) -> Option<&Fortification> {
+        self.sites.get(location)
+    }
+
+    pub fn sites(&self) -> Vec<(&Location, &Fortification)> {
+        self.sites.iter().collect()
+    }
+
+    pub fn progress(&self, location: &Location) -> u8 {
+        self.get(location)
+            .map(|f| f.progress())
+            .unwrap_or(0)
+    }
+
+    /// progress still needed to finish the works at a location
+    pub fn remaining(&self, location: &Location) -> u8 {
+        COMPLETE.saturating_sub(self.progress(location))
+    }
+
+    pub fn cover(&self, location: &Location) -> f32 {
+        self.get(location)
+            .map(|f| f.cover())
+            .unwrap_or(0.0)
+    }
+
+    /// add progress to the works at a location, starting
+    /// them if they don't exist yet
+    pub fn dig(&mut self, location: Location, owner: PlayerId, amount: u8) {
+        let site = self
+            .sites
+            .entry(location)
+            .or_insert_with(|| Fortification::new(owner));
+
+        site.owner = owner;
+        site.progress = site.progress.saturating_add(amount).min(COMPLETE);
+    }
+
+    /// wear down works that nobody is holding, removing
+    /// them once nothing is left
+    pub fn decay(&mut self, occupied: &HashSet<Location>) {
+        for (location, site) in self.sites.iter_mut() {
+            if !occupied.contains(location) {
+                site.progress = site.progress.saturating_sub(DECAY);
+            }
+        }
+        self.sites.retain(|_, s| s.progress > 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(soil: Soil, rocks: u8, temperature: f32) -> Area {
+        Area::create()
+            .with_soil(soil)
+            .with_rocks(rocks)
+            .with_temperature(temperature)
+            .with_textures(vec![0])
+            .build()
+    }
+
+    #[test]
+    fn test_sand_is_easier_than_rock() {
+        let sand = Fortification::rate(&area(Soil::Sand, 0, 15.));
+        let rocky = Fortification::rate(&area(Soil::Chalk, 90, 15.));
+        assert!(sand > rocky);
+    }
+
+    #[test]
+    fn test_frozen_ground_is_slow() {
+        let thawed = Fortification::rate(&area(Soil::Loam, 0, 15.));
+        let frozen = Fortification::rate(&area(Soil::Loam, 0, -10.));
+        assert!(thawed > frozen);
+    }
+
+    #[test]
+    fn test_abandoned_works_decay() {
+        let mut works = Fortifications::new();
+        let player = PlayerId::new();
+        works.dig((0, 0), player, 15);
+        works.dig((1, 0), player, 15);
+
+        let occupied = vec![(1, 0)].into_iter().collect();
+        works.decay(&occupied);
+        assert_eq!(works.progress(&(0, 0)), 5);
+        assert_eq!(works.progress(&(1, 0)), 15);
+
+        works.decay(&occupied);
+        assert!(works.get(&(0, 0)).is_none());
+    }
+}
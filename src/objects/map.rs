@@ -1,9 +1,10 @@
-use crate::generation::{Unit,Change,ChangeType,Id,PlayerId};
+use crate::generation::{Unit,Change,ChangeType,Id,PlayerId,Order};
 use crate::objects::Point;
 use crate::state::traits::HasId;
-use crate::state::Context;
+use crate::state::{Context, Teams};
 use crate::error::{Result,Error};
-use crate::behavior::Pathfinder;
+use crate::behavior::{Control,Pathfinder};
+use crate::resources::Textures;
 use crate::state::traits::{HasPosition,AsTile,HasLayer};
 
 use std::collections::HashMap;
@@ -15,7 +16,7 @@ use indexmap::IndexMap;
 use log::*;
 
 macro_rules! point {
-    ($i:expr) => { Point::from_index($i as i32) }
+    ($c:expr, $i:expr) => { Point::from_index($i as i32, &$c) }
 }
 
 /// alias for map index position
@@ -39,17 +40,18 @@ pub struct Position {
 
 #[derive(Debug, Clone)]
 pub struct Map {
+    context: Context,
     positions: Vec<Position>,
     selected: Vec<Selection>,
 }
 
 impl Selection {
 
-    pub fn new(point: &Point, unit: &Unit) -> Self {
+    pub fn new(context: &Context, point: &Point, unit: &Unit) -> Self {
         Self {
             id: *unit.id(),
-            start: point.as_index() as usize,
-            end: point.as_index() as usize,
+            start: point.as_index(context) as usize,
+            end: point.as_index(context) as usize,
             actions: (
                 unit.actions(), // initial actions
                 unit.actions(), // current actions
@@ -57,16 +59,16 @@ impl Selection {
         }
     }
 
-    pub fn update(&mut self, point: &Point) {
-        self.end = point.as_index() as usize;
+    pub fn update(&mut self, context: &Context, point: &Point) {
+        self.end = point.as_index(context) as usize;
     }
 
-    pub fn start_point(&self) -> Point {
-        Point::from_index(self.start as i32)
+    pub fn start_point(&self, context: &Context) -> Point {
+        Point::from_index(self.start as i32, context)
     }
 
-    pub fn end_point(&self) -> Point {
-        Point::from_index(self.end as i32)
+    pub fn end_point(&self, context: &Context) -> Point {
+        Point::from_index(self.end as i32, context)
     }
 
     pub fn current(&self) -> u8 {
@@ -134,17 +136,17 @@ impl Position {
     }
 
     /// check if the position contains enemies
-    pub fn enemies(&self, id: &PlayerId) -> bool {
+    pub fn enemies(&self, id: &PlayerId, teams: &Teams) -> bool {
         self.units
             .iter()
-            .any(|(_,v)| v.player_id() != id)
+            .any(|(_,v)| teams.is_enemy(v.player_id(), id))
     }
 
-    pub fn targeted_units(&self, id: &PlayerId) -> Vec<&Unit> {
+    pub fn targeted_units(&self, id: &PlayerId, teams: &Teams) -> Vec<&Unit> {
         self.units
             .iter()
             .map(|(_,v)| v)
-            .filter(|v| v.player_id() != id)
+            .filter(|v| teams.is_enemy(v.player_id(), id))
             .collect()
     }
 
@@ -173,14 +175,19 @@ impl Position {
 }
 
 impl Map {
-    pub fn new() -> Self {
-        let size = Context::total();
+    pub fn new(context: &Context) -> Self {
         Self {
-            positions: vec![Position::new(); size],
+            context: *context,
+            positions: vec![Position::new(); context.total()],
             selected: vec![],
         }
     }
 
+    /// the size of the map that units are placed on
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
     pub fn count(&self) -> usize {
         self.selected.len()
     }
@@ -202,15 +209,15 @@ impl Map {
             .collect()
     }
 
-    pub fn has_enemy(&self, point: &Point, player: &PlayerId) -> bool {
+    pub fn has_enemy(&self, point: &Point, player: &PlayerId, teams: &Teams) -> bool {
         self.get(point)
-            .map(|p| p.enemies(player))
+            .map(|p| p.enemies(player, teams))
             .unwrap_or(false)
     }
 
-    pub fn targeted_units(&self, point: &Point, player: &PlayerId) -> Vec<&Unit> {
+    pub fn targeted_units(&self, point: &Point, player: &PlayerId, teams: &Teams) -> Vec<&Unit> {
         self.get(point)
-            .map(|p| p.targeted_units(player))
+            .map(|p| p.targeted_units(player, teams))
             .unwrap_or(Vec::new())
     }
 
@@ -224,21 +231,37 @@ impl Map {
             .find(|u| u.id() == id)
     }
 
-    pub fn execute(&mut self, map: &mut Tilemap, changes: &Vec<Change>) {
-        println!("------------------------------------------------------");
-        println!("applying {} changes",changes.len());
+    /// apply changes to units, returning the soldiers each unit
+    /// lost. Soldiers moved to another unit aren't counted
+    pub fn execute(&mut self, map: &mut Tilemap, changes: &Vec<Change>) -> HashMap<Id,usize> {
+        debug!("applying {} changes", changes.len());
         let mut remove: Vec<(Id,Point)> = vec![];
+        let mut losses: HashMap<Id,usize> = HashMap::new();
         for change in changes {
+            if let ChangeType::Reassign(target, soldiers) = &change.action {
+                self.reassign(&change.id, target, soldiers);
+                continue;
+            }
+            if let ChangeType::Order(point, _) = &change.action {
+                self.relocate(map, &change.id, point);
+                if let Some(unit) = self.find(&change.id) {
+                    let position = *unit.position();
+                    unit.orders_mut().progress(&position);
+                }
+                continue;
+            }
             if let Some(unit) = self.find(&change.id) {
-                match change.action {
-                    ChangeType::Health(v) => {
-                        println!("unit \"{}\" health changed: {}",unit.name(), v);
-                        unit.set_health(v)
-                    },
-                    _ => ()
-                };
+                let living = unit.living();
+                if let ChangeType::Health(v) = change.action {
+                    debug!("unit \"{}\" health changed by {}", unit.name(), v);
+                }
+                unit.apply(&change.action);
+                let lost = living.saturating_sub(unit.living());
+                if lost > 0 {
+                    *losses.entry(*unit.id()).or_insert(0) += lost;
+                }
                 if unit.health() == 0 {
-                    println!("unit \"{}\" destroyed",unit.name());
+                    info!("unit \"{}\" destroyed", unit.name());
                     remove.push((
                         *unit.id(),
                         *unit.position(),
@@ -247,7 +270,6 @@ impl Map {
             }
         }
 
-        println!("selecting tiles");
         let tiles: Vec<_> = remove
             .into_iter()
             .filter_map(|(i,p)| self
@@ -258,13 +280,135 @@ impl Map {
             .map(|(p,u)| (p.integers(),*u.layer()))
             .collect();
 
-        println!("{} tiles selected",tiles.len());
+        debug!("clearing {} tiles", tiles.len());
         // clear the tile locations
         if let Err(e) = map.clear_tiles(tiles) {
             log::warn!("{:?}", e);
         }
 
-        println!("------------------------------------------------------");
+        losses
+    }
+
+    /// move soldiers between two units at the same position
+    pub fn reassign(&mut self, from: &Id, to: &Id, soldiers: &Vec<usize>) -> bool {
+        let p1 = self.find(from).map(|u| *u.position());
+        let p2 = self.find(to).map(|u| *u.position());
+
+        if p1.is_none() || p1 != p2 || from == to {
+            return false;
+        }
+
+        let moved = self
+            .find(from)
+            .map(|u| u.detach(soldiers))
+            .unwrap_or(vec![]);
+
+        if moved.is_empty() {
+            return false;
+        }
+
+        if let Some(unit) = self.find(to) {
+            unit.attach(moved);
+        }
+
+        true
+    }
+
+    /// move a single unit without disturbing the current selection
+    pub fn relocate(&mut self, map: &mut Tilemap, id: &Id, point: &Point) -> bool {
+        let unit = match self.find(id) {
+            Some(u) => Selection::new(&self.context, &u.position().clone(), u),
+            None => return false,
+        };
+
+        if unit.end_point(&self.context) == *point {
+            return true;
+        }
+
+        let previous = std::mem::replace(&mut self.selected, vec![unit]);
+
+        self.hide(map, &self.selected);
+        let result = self.moveto(point);
+        self.show(map, &self.selected);
+
+        self.selected = previous;
+
+        if let Err(e) = &result {
+            log::warn!("{:?}", e);
+        }
+
+        result.is_ok()
+    }
+
+    /// replace the standing orders of units owned by the player
+    pub fn set_orders(&mut self, player: &PlayerId, orders: &Vec<(Id, Vec<Order>)>) {
+        for (id, list) in orders.iter() {
+            // orders that lead off the map are refused
+            if list
+                .iter()
+                .filter_map(|o| o.target())
+                .any(|p| !p.in_bounds(&self.context))
+            {
+                continue;
+            }
+
+            if let Some(unit) = self.find(id) {
+                if unit.player_id() == player {
+                    unit.orders_mut().set(list.clone());
+                }
+            }
+        }
+    }
+
+    /// waypoints of all orders given to the selected units
+    pub fn waypoints(&self) -> Vec<Point> {
+        self.selected_units()
+            .into_iter()
+            .map(|u| u.orders().waypoints())
+            .flatten()
+            .collect()
+    }
+
+    /// draw waypoints for the selected units, returning the points drawn
+    pub fn draw_waypoints(&self, map: &mut Tilemap, layer: usize, sprite: usize) -> Vec<Point> {
+        let points = self.waypoints();
+        self.draw(map, &points, layer, sprite);
+        points
+    }
+
+    /// draw every hex in range of the selected units, returning the points drawn
+    pub fn draw_range(&self, map: &mut Tilemap, layer: usize, sprite: usize) -> Vec<Point> {
+        let units = self.selected_units();
+        let points: Vec<Point> = (0..self.positions.len())
+            .map(|i| point!(self.context, i))
+            .filter(|p| units
+                .iter()
+                .any(|u| {
+                    let range = u.specialty().range();
+                    range > 0 && u.position().distance(*p) <= range
+                }))
+            .collect();
+
+        self.draw(map, &points, layer, sprite);
+        points
+    }
+
+    /// update unit textures and redraw the top unit at each position
+    pub fn refresh(&mut self, map: &mut Tilemap, textures: &Textures) {
+        for unit in self.units_mut() {
+            unit.refresh(textures);
+        }
+
+        let tiles: Vec<Tile<_>> = self
+            .positions
+            .iter()
+            .filter_map(|p| p.top())
+            .map(|u| u.as_tile())
+            .collect();
+
+        if let Err(e) = map.insert_tiles(tiles) {
+            log::warn!("{:?}", e);
+        }
     }
 
     pub fn has_unit(&self, point: &Point, id: &Id) -> bool {
@@ -297,7 +441,7 @@ impl Map {
     pub fn select_top(&mut self, owner: PlayerId, point: &Point) {
         if let Some(unit) = self.get_top(&point) {
             if unit.player_id() == &owner {
-                self.selected = vec![Selection::new(&point,unit)];
+                self.selected = vec![Selection::new(&self.context,&point,unit)];
             }
         }
     }
@@ -307,14 +451,14 @@ impl Map {
             .append(&mut self
                 .get_all(&point)
                 .iter()
-                .map(|u| Selection::new(&point,u))
+                .map(|u| Selection::new(&self.context,&point,u))
                 .collect());
         log::info!("selected: {}",self.selected.len());
     }
 
     pub fn select_id(&mut self, point: &Point, id: Id) {
         if let Some(unit) = self.get_id(point,&id) {
-            self.selected.push(Selection::new(&point,unit));
+            self.selected.push(Selection::new(&self.context,&point,unit));
         }
     }
 
@@ -322,7 +466,7 @@ impl Map {
         self.selected = self
             .get_all_ids(ids)
             .iter()
-            .map(|u| Selection::new(u.position(),u))
+            .map(|u| Selection::new(&self.context,u.position(),u))
             .collect()
     }
 
@@ -332,7 +476,7 @@ impl Map {
                 .get_ids(&point,&ids)
                 .iter()
                 .filter(|u| u.player_id() == owner)
-                .map(|u| Selection::new(&point,u))
+                .map(|u| Selection::new(&self.context,&point,u))
                 .collect());
     }
 
@@ -345,15 +489,12 @@ impl Map {
         self.select(&ids);
         self.move_selection(map,&point);
 
-        let zipped = self
-            .selected
-            .iter_mut()
-            .zip(moves
-                .iter()
-                .map(|m| m.1));
-
-        for (selection,current) in zipped {
-            selection.actions.1 = current;
+        // selections come back in board order, so match
+        // actions by id to apply the same way on every peer
+        for selection in self.selected.iter_mut() {
+            if let Some((_,current)) = moves.iter().find(|m| m.0 == selection.id) {
+                selection.actions.1 = *current;
+            }
         }
 
         self.select_none();
@@ -366,7 +507,7 @@ impl Map {
         // move to each starting position and leave the
         // unit(s) that started there.
         for p in previous.iter_mut() {
-            self.moveto(&Point::from_index(p.start as i32));
+            self.moveto(&Point::from_index(p.start as i32, &self.context));
             self.selected.retain(|s| s.start != p.start);
             p.end = p.start;
         }
@@ -444,7 +585,7 @@ impl Map {
         Ok(())
     }
 
-    fn space(&self, point: &Point) -> usize {
+    pub fn space(&self, point: &Point) -> usize {
         self.get(point)
             .map(|p| p.space())
             .unwrap_or(0)
@@ -466,7 +607,7 @@ impl Map {
         }
     }
 
-    pub fn pathto(&mut self, map: &mut Tilemap, impedance: &HashMap<Point, f32>, point: &Point, layer: usize, sprite: usize) -> Vec<Point> {
+    pub fn pathto(&mut self, map: &mut Tilemap, impedance: &HashMap<Point, f32>, control: &Control, point: &Point, layer: usize, sprite: usize) -> Vec<Point> {
         self.hide(map, &self.selected);
 
         // find all selected units
@@ -484,20 +625,25 @@ impl Map {
         let mut paths: IndexMap<Id,Vec<Point>> = IndexMap::new();
 
         for s in self.selected.iter_mut() {
-            let finder = Pathfinder::new(&impedance, point!(s.start), *point);
+            let start = point!(self.context, s.start);
+            let finder = Pathfinder::new(&self.context, &impedance, start, *point);
 
             // init actions to initial values
             let ( i, _ ) = s.actions;
-            let mut c = i;
 
-            let path = finder
-                .find_weighted()
+            let weighted = finder.find_weighted();
+            let total = weighted.len();
+
+            // stop early when actions run out or the unit
+            // moves next to an enemy
+            let walked = control.walk(&start, weighted, i);
+            let c = match walked.last() {
+                Some((_, c)) if walked.len() == total => *c,
+                _ => 0,
+            };
+
+            let path = walked
                 .into_iter()
-                .filter(|(_, n)| {
-                    let cost = n.max(0.).min(100.) as u8;
-                    c = c.saturating_sub(cost);
-                    c > 0
-                })
                 .map(|(p, _)| p)
                 .collect::<Vec<Point>>();
             
@@ -555,7 +701,7 @@ impl Map {
                         path.retain(|&p| p != last);
                         
                         // update the selection to the last valid point
-                        s.end = last.to_index() as usize;
+                        s.end = last.to_index(&self.context) as usize;
         
                         // update the unit location to the last point
                         unit.set_position(&last);
@@ -614,12 +760,35 @@ impl Map {
         }
     }
 
+    /// swap every unit on the map for the given units,
+    /// clearing the old ones from the tilemap
+    pub fn replace(&mut self, map: &mut Tilemap, units: Vec<Unit>) {
+        let tiles: Vec<_> = self
+            .units()
+            .into_iter()
+            .map(|u| (u.position().integers(), *u.layer()))
+            .collect();
+
+        if let Err(e) = map.clear_tiles(tiles) {
+            log::warn!("{:?}", e);
+        }
+
+        self.positions = vec![Position::new(); self.context.total()];
+        self.selected.clear();
+
+        for unit in units.into_iter() {
+            let point = *unit.position();
+            self.add(point, unit);
+        }
+    }
+
     fn get(&self, point: &Point) -> Option<&Position> {
-        self.get_idx(point.as_index() as usize)
+        self.get_idx(point.as_index(&self.context) as usize)
     }
 
     fn get_mut(&mut self, point: &Point) -> Option<&mut Position> {
-        self.get_idx_mut(point.as_index() as usize)
+        let index = point.as_index(&self.context) as usize;
+        self.get_idx_mut(index)
     }
 
     fn get_idx(&self, index: Index) -> Option<&Position> {
@@ -685,7 +854,7 @@ impl Map {
             .filter_map(|(s,_)| self
                 .get_unit(s)
                 .map(|u| (s.end,*u.layer())))
-            .map(Point::tuple_index)
+            .map(|t| Point::tuple_index(t, &self.context))
             .collect();
 
         // clear the tile locations
@@ -1,10 +1,12 @@
 // TODO: remove location
+mod fortifications;
 mod location;
 mod map;
 mod name;
 mod points;
 mod property;
 
+pub use fortifications::{Fortification, Fortifications};
 pub use location::Location;
 pub use map::{Map,Selection};
 pub use name::{Name, NameGenerator};
@@ -316,8 +316,8 @@ impl Point<Offset> {
     pub fn cubic(x: i32, y: i32, z: i32) -> Point<Cubic> {
         (x, y, z).into()
     }
-    pub fn index(i: Index) -> Point<Offset> {
-        Point::from_index(i)
+    pub fn index(i: Index, context: &Context) -> Point<Offset> {
+        Point::from_index(i, context)
     }
 
     pub fn subtype(&self) -> SystemType {
@@ -331,8 +331,8 @@ impl Point<Offset> {
         self.into()
     }
 
-    pub fn to_index(self) -> Index {
-        self.as_index()
+    pub fn to_index(self, context: &Context) -> Index {
+        self.as_index(context)
     }
     pub fn to_axial(self) -> Point<Axial> {
         self.into()
@@ -348,25 +348,25 @@ impl Point<Offset> {
         self.into()
     }
 
-    pub fn as_index(&self) -> Index {
-        let (w, h) = Context::size();
+    pub fn as_index(&self, context: &Context) -> Index {
+        let (w, h) = context.size();
         let x = self.x + w / 2;
         let y = self.y + h / 2;
         x + y * w
     }
 
-    pub fn from_index(i: Index) -> Point<Offset> {
-        let (w, h) = Context::size();
+    pub fn from_index(i: Index, context: &Context) -> Point<Offset> {
+        let (w, h) = context.size();
         ((i % w) - (w / 2), (i / w) - (h / 2)).into()
     }
 
-    pub fn tuple_index((i,l): (usize,Layer)) -> ((i32,i32),usize) {
-        (Self::from_index(i as i32).integers(),l)
+    pub fn tuple_index((i,l): (usize,Layer), context: &Context) -> ((i32,i32),usize) {
+        (Self::from_index(i as i32, context).integers(),l)
     }
 
     /// https://www.gamedev.net/tutorials/programming/general-and-gameplay-programming/coordinates-in-hexagon-based-tile-maps-r1800/
-    pub fn from_global(ox: f32, oy: f32) -> Point<Offset> {
-        let s = Context::tile_size();
+    pub fn from_global(ox: f32, oy: f32, context: &Context) -> Point<Offset> {
+        let s = context.tile_size();
 
         let w = s.0 as f32;
         let h = s.1 as f32;
@@ -434,9 +434,23 @@ impl Point<Offset> {
         (m, n).into()
     }
 
-    pub fn neighbors(&self) -> Vec<Point<Offset>> {
+    /// the center of the hex in global coordinates
+    pub fn to_global(&self, context: &Context) -> (f32, f32) {
+        let (w, h) = context.tile_size();
+        let w = w as f32;
+        let h = h as f32;
+
+        let shift = match self.y.abs() % 2 {
+            1 => w / 2.0,
+            _ => 0.0,
+        };
+
+        (self.x as f32 * w + shift, self.y as f32 * 0.75 * h)
+    }
+
+    pub fn neighbors(&self, context: &Context) -> Vec<Point<Offset>> {
         self.to_cubic()
-            .neighbors()
+            .neighbors(context)
             .into_iter()
             .map(Self::from)
             .collect()
@@ -446,13 +460,22 @@ impl Point<Offset> {
         self.as_cubic().distance(other.into())
     }
 
-    pub fn bounds(&self) -> (Point<Offset>, Point<Offset>) {
-        let (w, h) = Context::size();
+    /// hexes along a straight line to another point (inclusive)
+    pub fn line<T: Into<Point<Offset>>>(&self, other: T) -> Vec<Point<Offset>> {
+        self.as_cubic()
+            .line(other.into())
+            .into_iter()
+            .map(Self::from)
+            .collect()
+    }
+
+    pub fn bounds(&self, context: &Context) -> (Point<Offset>, Point<Offset>) {
+        let (w, h) = context.size();
         (Point::new(-w / 2, -h / 2), Point::new(w / 2, h / 2))
     }
 
-    pub fn in_bounds(&self) -> bool {
-        let (s, e) = self.bounds();
+    pub fn in_bounds(&self, context: &Context) -> bool {
+        let (s, e) = self.bounds(context);
         let (x, y) = self.integers();
         s.x <= x && s.y <= y && e.x > x && e.y > y
     }
@@ -470,8 +493,8 @@ impl Point<Axial> {
         self.into()
     }
 
-    pub fn to_index(self) -> Index {
-        self.to_offset().as_index()
+    pub fn to_index(self, context: &Context) -> Index {
+        self.to_offset().as_index(context)
     }
     pub fn to_offset(self) -> Point<Offset> {
         self.into()
@@ -480,8 +503,8 @@ impl Point<Axial> {
         self.into()
     }
 
-    pub fn as_index(&self) -> Index {
-        self.as_offset().as_index()
+    pub fn as_index(&self, context: &Context) -> Index {
+        self.as_offset().as_index(context)
     }
     pub fn as_offset(&self) -> Point<Offset> {
         self.into()
@@ -490,9 +513,9 @@ impl Point<Axial> {
         self.into()
     }
 
-    pub fn neighbors(&self) -> Vec<Point<Axial>> {
+    pub fn neighbors(&self, context: &Context) -> Vec<Point<Axial>> {
         self.to_cubic()
-            .neighbors()
+            .neighbors(context)
             .into_iter()
             .map(Self::from)
             .collect()
@@ -502,13 +525,13 @@ impl Point<Axial> {
         self.as_cubic().distance(other.into())
     }
 
-    pub fn bounds(&self) -> (Point<Axial>, Point<Axial>) {
-        let (s, e) = self.as_offset().bounds();
+    pub fn bounds(&self, context: &Context) -> (Point<Axial>, Point<Axial>) {
+        let (s, e) = self.as_offset().bounds(context);
         (s.into(), e.into())
     }
 
-    pub fn in_bounds(&self) -> bool {
-        self.to_offset().in_bounds()
+    pub fn in_bounds(&self, context: &Context) -> bool {
+        self.to_offset().in_bounds(context)
     }
 }
 
@@ -524,8 +547,8 @@ impl Point<Cubic> {
         self.into()
     }
 
-    pub fn to_index(self) -> Index {
-        self.to_offset().as_index()
+    pub fn to_index(self, context: &Context) -> Index {
+        self.to_offset().as_index(context)
     }
     pub fn to_offset(self) -> Point<Offset> {
         self.into()
@@ -534,8 +557,8 @@ impl Point<Cubic> {
         self.into()
     }
 
-    pub fn as_index(&self) -> Index {
-        self.as_offset().as_index()
+    pub fn as_index(&self, context: &Context) -> Index {
+        self.as_offset().as_index(context)
     }
     pub fn as_offset(&self) -> Point<Offset> {
         self.into()
@@ -544,10 +567,10 @@ impl Point<Cubic> {
         self.into()
     }
 
-    pub fn neighbors(&self) -> Vec<Point<Cubic>> {
+    pub fn neighbors(&self, context: &Context) -> Vec<Point<Cubic>> {
         let (x, y, z) = self.integers();
         let mut result = Vec::with_capacity(6);
-        if self.in_bounds() {
+        if self.in_bounds(context) {
             let p1 = Point::cubic(x - 1, y + 1, z + 0);
             let p2 = Point::cubic(x + 0, y + 1, z - 1);
             let p3 = Point::cubic(x + 0, y - 1, z + 1);
@@ -555,22 +578,22 @@ impl Point<Cubic> {
             let p5 = Point::cubic(x - 1, y + 0, z + 1);
             let p6 = Point::cubic(x + 1, y + 0, z - 1);
 
-            if p1.in_bounds() {
+            if p1.in_bounds(context) {
                 result.push(p1);
             }
-            if p2.in_bounds() {
+            if p2.in_bounds(context) {
                 result.push(p2);
             }
-            if p3.in_bounds() {
+            if p3.in_bounds(context) {
                 result.push(p3);
             }
-            if p4.in_bounds() {
+            if p4.in_bounds(context) {
                 result.push(p4);
             }
-            if p5.in_bounds() {
+            if p5.in_bounds(context) {
                 result.push(p5);
             }
-            if p6.in_bounds() {
+            if p6.in_bounds(context) {
                 result.push(p6);
             }
         }
@@ -583,13 +606,55 @@ impl Point<Cubic> {
         ((x1 - x2).abs() + (y1 - y2).abs() + (z1 - z2).abs()) / 2
     }
 
-    pub fn bounds(&self) -> (Point<Cubic>, Point<Cubic>) {
-        let (s, e) = self.as_offset().bounds();
+    /// hexes along a straight line to another point (inclusive)
+    pub fn line<T: Into<Point<Cubic>>>(&self, other: T) -> Vec<Point<Cubic>> {
+        let other = other.into();
+        let n = self.distance(other);
+        let (x1, y1, z1) = self.floats();
+        let (x2, y2, z2) = other.floats();
+
+        (0..=n)
+            .map(|i| {
+                let t = if n == 0 { 0.0 } else { i as f32 / n as f32 };
+
+                // nudge off of hex edges so that rounding is consistent
+                Self::round(
+                    x1 + (x2 - x1) * t + 1e-6,
+                    y1 + (y2 - y1) * t + 1e-6,
+                    z1 + (z2 - z1) * t - 2e-6,
+                )
+            })
+            .collect()
+    }
+
+    /// round fractional cubic coordinates to the nearest hex
+    fn round(x: f32, y: f32, z: f32) -> Point<Cubic> {
+        let mut rx = x.round();
+        let mut ry = y.round();
+        let mut rz = z.round();
+
+        let dx = (rx - x).abs();
+        let dy = (ry - y).abs();
+        let dz = (rz - z).abs();
+
+        if dx > dy && dx > dz {
+            rx = -ry - rz;
+        } else if dy > dz {
+            ry = -rx - rz;
+        } else {
+            rz = -rx - ry;
+        }
+
+        Point::cubic(rx as i32, ry as i32, rz as i32)
+    }
+
+    pub fn bounds(&self, context: &Context) -> (Point<Cubic>, Point<Cubic>) {
+        let (s, e) = self.as_offset().bounds(context);
         (s.into(), e.into())
     }
 
-    pub fn in_bounds(&self) -> bool {
-        self.to_offset().in_bounds()
+    pub fn in_bounds(&self, context: &Context) -> bool {
+        self.to_offset().in_bounds(context)
     }
 }
 
@@ -600,18 +665,23 @@ mod tests {
 
     macro_rules! initialize {
         ( $w:expr, $h:expr ) => {
-            initialize!($w, $h, 175, 200);
+            initialize!($w, $h, 175, 200)
         };
         ( $w:expr, $h:expr, $tw:expr, $th:expr ) => {
-            crate::state::Context::set_size($w, $h);
-            crate::state::Context::set_tile_size($tw, $th);
+            Context::new($w, $h, $tw, $th)
         };
     }
 
-    macro_rules! index {
-        ( $i:expr ) => {
-            Point::index($i)
-        };
+    #[test]
+    fn test_global_round_trip() {
+        let context = initialize!(30, 30);
+        for x in -5..5 {
+            for y in -5..5 {
+                let point: Point<Offset> = (x, y).into();
+                let (gx, gy) = point.to_global(&context);
+                assert_eq!(Point::from_global(gx, gy, &context), point);
+            }
+        }
     }
 
     macro_rules! point {
@@ -640,35 +710,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn offset_line_30x30() {
+        let line = point!(0, 0).line(point!(3, 0));
+        assert_eq!(line, points![(0, 0), (1, 0), (2, 0), (3, 0)]);
+    }
+
+    #[test]
+    fn offset_line_matches_distance_30x30() {
+        let (a, b) = (point!(-4, 2), point!(3, -5));
+        let line = a.line(b);
+        assert_eq!(line.len() as i32, a.distance(b) + 1);
+        assert_eq!(line.first(), Some(&a));
+        assert_eq!(line.last(), Some(&b));
+    }
+
     #[test]
     fn offset_to_offset_comparison() {
-        initialize!(30, 30);
         assert_eq!(Point::offset(-2, 2), Point::offset(-2, 2));
     }
 
     #[test]
     fn offset_to_axial_comparison() {
-        initialize!(30, 30);
         assert_eq!(Point::offset(-2, 2), Point::offset(-2, 2).as_axial());
         assert_eq!(Point::offset(-2, 2).as_axial(), Point::offset(-2, 2));
     }
 
     #[test]
     fn offset_to_cubic_comparison() {
-        initialize!(30, 30);
         assert_eq!(Point::offset(-2, 2), Point::offset(-2, 2).as_cubic());
         assert_eq!(Point::offset(-2, 2).as_cubic(), Point::offset(-2, 2));
     }
 
     #[test]
     fn offset_to_tuple_comparison() {
-        initialize!(30, 30);
         assert_eq!(Point::offset(-2, 2), (-2, 2));
     }
 
     #[test]
     fn offset_to_axial_conversion() {
-        initialize!(30, 30);
         assert_eq!(Point::offset(-2, 2).as_axial(), Point::axial(-3, 2));
         assert_eq!(Point::offset(-2, -2).as_axial(), Point::axial(-1, -2));
         assert_eq!(Point::offset(2, -2).as_axial(), Point::axial(3, -2));
@@ -676,7 +756,6 @@ mod tests {
 
     #[test]
     fn axial_to_offset_conversion() {
-        initialize!(30, 30);
         assert_eq!(Point::axial(-3, 2).as_offset(), Point::offset(-2, 2));
         assert_eq!(Point::axial(-1, -2).as_offset(), Point::offset(-2, -2));
         assert_eq!(Point::axial(3, -2).as_offset(), Point::offset(2, -2));
@@ -684,7 +763,6 @@ mod tests {
 
     #[test]
     fn offset_to_cubic_conversion() {
-        initialize!(30, 30);
         assert_eq!(Point::offset(-2, 2).as_cubic(), Point::cubic(-3, 2, 1));
         assert_eq!(Point::offset(-2, -2).as_cubic(), Point::cubic(-1, -2, 3));
         assert_eq!(Point::offset(2, -2).as_cubic(), Point::cubic(3, -2, -1));
@@ -692,7 +770,6 @@ mod tests {
 
     #[test]
     fn cubic_to_offset_conversion() {
-        initialize!(30, 30);
         assert_eq!(Point::cubic(-3, 2, 1).as_offset(), Point::offset(-2, 2));
         assert_eq!(Point::cubic(-1, -2, 3).as_offset(), Point::offset(-2, -2));
         assert_eq!(Point::cubic(3, -2, -1).as_offset(), Point::offset(2, -2));
@@ -700,11 +777,11 @@ mod tests {
 
     #[test]
     fn neighbors_offset_compare_cubic_30x30() {
-        initialize!(30, 30);
+        let context = initialize!(30, 30);
 
-        let n1 = point!(0, 0).neighbors();
+        let n1 = point!(0, 0).neighbors(&context);
         let n2 = cubic!(0, 0)
-            .neighbors()
+            .neighbors(&context)
             .into_iter()
             .map(|p| p.to_offset())
             .collect::<Vec<Point>>();
@@ -714,102 +791,102 @@ mod tests {
 
     #[test]
     fn offset_point_as_index_30x30() {
-        initialize!(30, 30);
-        assert_eq!(point!(-15, -15).as_index(), 0);
-        assert_eq!(point!(0, -8).as_index(), 225);
-        assert_eq!(point!(-11, -4).as_index(), 334);
-        assert_eq!(point!(14, 0).as_index(), 479);
-        assert_eq!(point!(-15, 4).as_index(), 570);
-        assert_eq!(point!(14, 14).as_index(), 899);
+        let context = initialize!(30, 30);
+        assert_eq!(point!(-15, -15).as_index(&context), 0);
+        assert_eq!(point!(0, -8).as_index(&context), 225);
+        assert_eq!(point!(-11, -4).as_index(&context), 334);
+        assert_eq!(point!(14, 0).as_index(&context), 479);
+        assert_eq!(point!(-15, 4).as_index(&context), 570);
+        assert_eq!(point!(14, 14).as_index(&context), 899);
     }
 
     #[test]
     fn axial_point_as_index_30x30() {
-        initialize!(30, 30);
-        assert_eq!(axial!(-15, -15).as_index(), 0);
-        assert_eq!(axial!(0, -8).as_index(), 225);
-        assert_eq!(axial!(-11, -4).as_index(), 334);
-        assert_eq!(axial!(14, 0).as_index(), 479);
-        assert_eq!(axial!(-15, 4).as_index(), 570);
-        assert_eq!(axial!(14, 14).as_index(), 899);
+        let context = initialize!(30, 30);
+        assert_eq!(axial!(-15, -15).as_index(&context), 0);
+        assert_eq!(axial!(0, -8).as_index(&context), 225);
+        assert_eq!(axial!(-11, -4).as_index(&context), 334);
+        assert_eq!(axial!(14, 0).as_index(&context), 479);
+        assert_eq!(axial!(-15, 4).as_index(&context), 570);
+        assert_eq!(axial!(14, 14).as_index(&context), 899);
     }
 
     #[test]
     fn cubic_point_as_index_30x30() {
-        initialize!(30, 30);
-        assert_eq!(cubic!(-15, -15).as_index(), 0);
-        assert_eq!(cubic!(0, -8).as_index(), 225);
-        assert_eq!(cubic!(-11, -4).as_index(), 334);
-        assert_eq!(cubic!(14, 0).as_index(), 479);
-        assert_eq!(cubic!(-15, 4).as_index(), 570);
-        assert_eq!(cubic!(14, 14).as_index(), 899);
+        let context = initialize!(30, 30);
+        assert_eq!(cubic!(-15, -15).as_index(&context), 0);
+        assert_eq!(cubic!(0, -8).as_index(&context), 225);
+        assert_eq!(cubic!(-11, -4).as_index(&context), 334);
+        assert_eq!(cubic!(14, 0).as_index(&context), 479);
+        assert_eq!(cubic!(-15, 4).as_index(&context), 570);
+        assert_eq!(cubic!(14, 14).as_index(&context), 899);
     }
 
     #[test]
     fn point_as_index_30x42() {
-        initialize!(30, 42);
-        assert_eq!(point!(-15, -21).as_index(), 0);
-        assert_eq!(point!(14, -21).as_index(), 29);
-        assert_eq!(point!(14, 20).as_index(), 1259);
-        assert_eq!(point!(-15, 20).as_index(), 1230);
-        assert_eq!(point!(10, 16).as_index(), 1135);
-        assert_eq!(point!(0, -6).as_index(), 465);
-        assert_eq!(point!(8, -17).as_index(), 143);
+        let context = initialize!(30, 42);
+        assert_eq!(point!(-15, -21).as_index(&context), 0);
+        assert_eq!(point!(14, -21).as_index(&context), 29);
+        assert_eq!(point!(14, 20).as_index(&context), 1259);
+        assert_eq!(point!(-15, 20).as_index(&context), 1230);
+        assert_eq!(point!(10, 16).as_index(&context), 1135);
+        assert_eq!(point!(0, -6).as_index(&context), 465);
+        assert_eq!(point!(8, -17).as_index(&context), 143);
     }
 
     #[test]
     fn index_to_point_30x30() {
-        initialize!(30, 30);
-        assert_eq!(index!(0), point!(-15, -15));
-        assert_eq!(index!(225), point!(0, -8));
-        assert_eq!(index!(334), point!(-11, -4));
-        assert_eq!(index!(479), point!(14, 0));
-        assert_eq!(index!(570), point!(-15, 4));
-        assert_eq!(index!(899), point!(14, 14));
+        let context = initialize!(30, 30);
+        assert_eq!(Point::index(0, &context), point!(-15, -15));
+        assert_eq!(Point::index(225, &context), point!(0, -8));
+        assert_eq!(Point::index(334, &context), point!(-11, -4));
+        assert_eq!(Point::index(479, &context), point!(14, 0));
+        assert_eq!(Point::index(570, &context), point!(-15, 4));
+        assert_eq!(Point::index(899, &context), point!(14, 14));
     }
 
     #[test]
     fn index_to_point_30x42() {
-        initialize!(30, 42);
-        assert_eq!(index!(0), point!(-15, -21));
-        assert_eq!(index!(29), point!(14, -21));
-        assert_eq!(index!(1259), point!(14, 20));
-        assert_eq!(index!(1230), point!(-15, 20));
-        assert_eq!(index!(1135), point!(10, 16));
-        assert_eq!(index!(465), point!(0, -6));
-        assert_eq!(index!(143), point!(8, -17));
+        let context = initialize!(30, 42);
+        assert_eq!(Point::index(0, &context), point!(-15, -21));
+        assert_eq!(Point::index(29, &context), point!(14, -21));
+        assert_eq!(Point::index(1259, &context), point!(14, 20));
+        assert_eq!(Point::index(1230, &context), point!(-15, 20));
+        assert_eq!(Point::index(1135, &context), point!(10, 16));
+        assert_eq!(Point::index(465, &context), point!(0, -6));
+        assert_eq!(Point::index(143, &context), point!(8, -17));
     }
 
     #[test]
     fn point_neighbors_30x30_corners() {
-        initialize!(30, 30);
+        let context = initialize!(30, 30);
 
         // bottom-left corner
         assert_eq!(
-            point!(-15, -15).neighbors(),
+            point!(-15, -15).neighbors(&context),
             points![(-15, -14), (-14, -14), (-14, -15)],
         );
 
         // bottom-right corner
-        assert_eq!(point!(14, -15).neighbors(), points![(14, -14), (13, -15)],);
+        assert_eq!(point!(14, -15).neighbors(&context), points![(14, -14), (13, -15)],);
 
         // top-left corner
-        assert_eq!(point!(-15, 14).neighbors(), points![(-15, 13), (-14, 14)],);
+        assert_eq!(point!(-15, 14).neighbors(&context), points![(-15, 13), (-14, 14)],);
 
         // top-right corner
         assert_eq!(
-            point!(14, 14).neighbors(),
+            point!(14, 14).neighbors(&context),
             points![(13, 13), (14, 13), (13, 14)],
         );
     }
 
     #[test]
     fn point_neighbors_30x30_quadrants() {
-        initialize!(30, 30);
+        let context = initialize!(30, 30);
 
         // top-left quadrant
         assert_eq!(
-            point!(-10, 10).neighbors(),
+            point!(-10, 10).neighbors(&context),
             points![
                 (-11, 11),
                 (-10, 11),
@@ -822,13 +899,13 @@ mod tests {
 
         // top-right quadrant
         assert_eq!(
-            point!(10, 10).neighbors(),
+            point!(10, 10).neighbors(&context),
             points![(9, 11), (10, 11), (9, 9), (10, 9), (9, 10), (11, 10)],
         );
 
         // bottom-left quadrant
         assert_eq!(
-            point!(-10, -10).neighbors(),
+            point!(-10, -10).neighbors(&context),
             points![
                 (-11, -9),
                 (-10, -9),
@@ -841,90 +918,90 @@ mod tests {
 
         // bottom-right quadrant
         assert_eq!(
-            point!(10, -10).neighbors(),
+            point!(10, -10).neighbors(&context),
             points![(9, -9), (10, -9), (9, -11), (10, -11), (9, -10), (11, -10)],
         );
     }
 
     #[test]
     fn point_neighbors_30x30_edges() {
-        initialize!(30, 30);
+        let context = initialize!(30, 30);
 
         // top-left quadrant top-edge
         assert_eq!(
-            point!(-7, 14).neighbors(),
+            point!(-7, 14).neighbors(&context),
             points![(-8, 13), (-7, 13), (-8, 14), (-6, 14)],
         );
 
         // top-left quadrant left edge
         assert_eq!(
-            point!(-15, 9).neighbors(),
+            point!(-15, 9).neighbors(&context),
             points![(-15, 10), (-14, 10), (-15, 8), (-14, 8), (-14, 9)],
         );
 
         // bot-left quadrant bot-edge
         assert_eq!(
-            point!(-9, -15).neighbors(),
+            point!(-9, -15).neighbors(&context),
             points![(-9, -14), (-8, -14), (-10, -15), (-8, -15)],
         );
 
         // bot-left quadrant left-edge
         assert_eq!(
-            point!(-15, -10).neighbors(),
+            point!(-15, -10).neighbors(&context),
             points![(-15, -9), (-15, -11), (-14, -10)],
         );
 
         // bot-right quadrant bot-edge
         assert_eq!(
-            point!(8, -15).neighbors(),
+            point!(8, -15).neighbors(&context),
             points![(8, -14), (9, -14), (7, -15), (9, -15)],
         );
 
         // bot-right quadrant right-edge
         assert_eq!(
-            point!(14, -8).neighbors(),
+            point!(14, -8).neighbors(&context),
             points![(13, -7), (14, -7), (13, -9), (14, -9), (13, -8)],
         );
 
         // top-right quadrant right-edge
         assert_eq!(
-            point!(14, 10).neighbors(),
+            point!(14, 10).neighbors(&context),
             points![(13, 11), (14, 11), (13, 9), (14, 9), (13, 10)],
         );
 
         // top-right quadrant top-edge
         assert_eq!(
-            point!(6, 14).neighbors(),
+            point!(6, 14).neighbors(&context),
             points![(5, 13), (6, 13), (5, 14), (7, 14)],
         );
     }
 
     #[test]
     fn point_neighbors_30x30_negative() {
-        initialize!(30, 30);
+        let context = initialize!(30, 30);
 
         // top-left quadrant top-edge
-        assert!(point!(-7, 15).neighbors().is_empty());
+        assert!(point!(-7, 15).neighbors(&context).is_empty());
 
         // top-left quadrant left edge
-        assert!(point!(-16, 9).neighbors().is_empty());
+        assert!(point!(-16, 9).neighbors(&context).is_empty());
 
         // bot-left quadrant bot-edge
-        assert!(point!(-9, -16).neighbors().is_empty());
+        assert!(point!(-9, -16).neighbors(&context).is_empty());
 
         // bot-left quadrant left-edge
-        assert!(point!(-16, -10).neighbors().is_empty());
+        assert!(point!(-16, -10).neighbors(&context).is_empty());
 
         // bot-right quadrant bot-edge
-        assert!(point!(8, -16).neighbors().is_empty());
+        assert!(point!(8, -16).neighbors(&context).is_empty());
 
         // bot-right quadrant right-edge
-        assert!(point!(15, -8).neighbors().is_empty());
+        assert!(point!(15, -8).neighbors(&context).is_empty());
 
         // top-right quadrant right-edge
-        assert!(point!(15, 10).neighbors().is_empty());
+        assert!(point!(15, 10).neighbors(&context).is_empty());
 
         // top-right quadrant top-edge
-        assert!(point!(6, 15).neighbors().is_empty());
+        assert!(point!(6, 15).neighbors(&context).is_empty());
     }
 }
@@ -2,4 +2,4 @@ mod spectrum;
 mod textures;
 
 pub use spectrum::Spectrum;
-pub use textures::{Textures,Label};
+pub use textures::{Textures,Label,TEXTURES};
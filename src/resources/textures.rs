@@ -64,6 +64,46 @@ impl Label {
     }
 }
 
+/// every texture loaded into the atlas
+pub const TEXTURES: &[&str] = &[
+    "water",
+    "water_deep",
+    "water_shallow",
+    "grass1",
+    "grass2",
+    "grass3",
+    "grass4",
+    "clay",
+    "sand",
+    "silt",
+    "peat",
+    "chalk",
+    "loam",
+    "blank",
+    "trees",
+    "marker",
+    "snow",
+    "units/veteran/veteran_0",
+    "units/veteran/veteran_1",
+    "units/veteran/veteran_2",
+    "units/veteran/veteran_3",
+    "units/armor/armor_0",
+    "units/armor/armor_1",
+    "units/armor/armor_2",
+    "units/armor/armor_3",
+    "units/armor/armor_4",
+    "units/infantry/infantry_0",
+    "units/infantry/infantry_1",
+    "units/infantry/infantry_2",
+    "units/infantry/infantry_3",
+    "units/infantry/infantry_4",
+    "units/militia/militia_0",
+    "units/militia/militia_1",
+    "units/militia/militia_2",
+    "units/militia/militia_3",
+    "units/militia/militia_4",
+];
+
 #[derive(Default, Clone)]
 pub struct Textures {
     textures: HashMap<&'static str, usize>,
@@ -80,46 +120,7 @@ fn index(server: &AssetServer, atlas: &TextureAtlas, name: &str) -> usize {
 
 impl Textures {
     pub fn load(&mut self, server: &AssetServer, atlas: &TextureAtlas) {
-        let labels = vec![
-            "water",
-            "water_deep",
-            "water_shallow",
-            "grass1",
-            "grass2",
-            "grass3",
-            "grass4",
-            "clay",
-            "sand",
-            "silt",
-            "peat",
-            "chalk",
-            "loam",
-            "blank",
-            "trees",
-            "marker",
-            "snow",
-            "units/veteran/veteran_0",
-            "units/veteran/veteran_1",
-            "units/veteran/veteran_2",
-            "units/veteran/veteran_3",
-            "units/armor/armor_0",
-            "units/armor/armor_1",
-            "units/armor/armor_2",
-            "units/armor/armor_3",
-            "units/armor/armor_4",
-            "units/infantry/infantry_0",
-            "units/infantry/infantry_1",
-            "units/infantry/infantry_2",
-            "units/infantry/infantry_3",
-            "units/infantry/infantry_4",
-            "units/militia/militia_0",
-            "units/militia/militia_1",
-            "units/militia/militia_2",
-            "units/militia/militia_3",
-            "units/militia/militia_4",
-        ];
-
-        self.textures = labels
+        self.textures = TEXTURES
             .iter()
             .map(|&l| (l, index(server, atlas, l)))
             .collect();
@@ -147,13 +148,25 @@ impl Textures {
     }
 
     pub fn unit(&self, unit: &Specialty, v: u8) -> usize {
-        match unit {
-            Specialty::Infantry => self.variant(Label::Infantry,v),
-            Specialty::Armor => self.variant(Label::Armor,v),
-            Specialty::Militia => self.variant(Label::Militia,v),
-            Specialty::Medical => self.variant(Label::Medical,v),
-            Specialty::Logistics => self.variant(Label::Logistics,v),    
-            Specialty::Mechanic => self.variant(Label::Mechanic,v),
-        }
+        self.variant(unit_label(unit),v)
+    }
+
+    /// true if there's artwork to draw a specialty with
+    pub fn has_unit(unit: &Specialty) -> bool {
+        let first = format!("{}_0", unit_label(unit).as_str());
+        TEXTURES.contains(&first.as_str())
+    }
+}
+
+fn unit_label(unit: &Specialty) -> Label {
+    match unit {
+        Specialty::Infantry => Label::Infantry,
+        Specialty::Armor => Label::Armor,
+        Specialty::Militia => Label::Militia,
+        Specialty::Medical => Label::Medical,
+        Specialty::Logistics => Label::Logistics,
+        Specialty::Mechanic => Label::Mechanic,
+        // no artwork of its own yet
+        Specialty::Artillery => Label::Armor,
     }
 }
@@ -1,5 +1,5 @@
 use chrono::naive::NaiveDate;
-use chrono::Datelike;
+use chrono::{Datelike, Duration};
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
 
 /// default year
@@ -47,7 +47,8 @@ impl Calendar {
     }
 
     pub fn from_turn(turn: u32) -> Self {
-        let date = NaiveDate::from_yo(YEAR, turn);
+        let days = turn.saturating_sub(1) as i64;
+        let date = NaiveDate::from_yo(YEAR, 1) + Duration::days(days);
         let season = Season::from(turn);
 
         Self { turn, date, season }
@@ -93,3 +94,17 @@ impl Debug for Calendar {
         Display::fmt(self, f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_turn_matches_advance() {
+        let mut calendar = Calendar::from_turn(360);
+        for _ in 0..10 {
+            calendar.advance();
+        }
+        assert_eq!(calendar, Calendar::from_turn(370));
+    }
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+/// The size of a map and of the tiles it's drawn with
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Context {
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self {
+            width: 30,
+            height: 30,
+            tile_width: 175,
+            tile_height: 200,
+        }
+    }
+}
+
+impl Context {
+    pub fn new(width: u32, height: u32, tile_width: u32, tile_height: u32) -> Self {
+        Self {
+            width,
+            height,
+            tile_width,
+            tile_height,
+        }
+    }
+
+    /// the number of tiles in the map
+    pub fn total(&self) -> usize {
+        self.width as usize *
+        self.height as usize
+    }
+
+    pub fn size(&self) -> (i32, i32) {
+        (self.width as i32, self.height as i32)
+    }
+
+    pub fn tile_size(&self) -> (i32, i32) {
+        (self.tile_width as i32, self.tile_height as i32)
+    }
+}
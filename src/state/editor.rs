@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+
+use crate::generation::{bounds, Area, Biome, Foliage, Soil, Structure};
+use crate::objects::{Location, Point};
+use crate::state::TileOverride;
+
+/// meters of elevation added per point of strength
+const STEP: f32 = 10.0;
+
+/// edits kept for undo
+const HISTORY: usize = 50;
+
+/// What the brush does to the tiles under it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Tool {
+    Raise,
+    Lower,
+    Moisture,
+    Soil,
+    Biome,
+    Foliage,
+    Structure,
+    River,
+    Road,
+    Erase,
+}
+
+/// The tool and values used when painting
+#[derive(Debug, Clone)]
+pub struct Brush {
+    pub tool: Tool,
+
+    /// radius of the brush (in tiles)
+    pub size: u8,
+
+    /// how strongly each stroke changes a tile (1-100)
+    pub strength: u8,
+
+    pub moisture: u8,
+    pub soil: Soil,
+    pub biome: Biome,
+    pub foliage: Foliage,
+    pub structure: Structure,
+}
+
+/// Paints hand-edited tiles over the generated map
+#[derive(Debug, Clone, Default)]
+pub struct Editor {
+    pub enabled: bool,
+    pub brush: Brush,
+    undo: Vec<Vec<TileOverride>>,
+    redo: Vec<Vec<TileOverride>>,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            tool: Tool::Raise,
+            size: 1,
+            strength: 20,
+            moisture: 50,
+            soil: Soil::Loam,
+            biome: Biome::Grassland,
+            foliage: Foliage::Trees,
+            structure: Structure::Village,
+        }
+    }
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// remember the tiles before a stroke so it can be undone
+    pub fn begin(&mut self, overrides: &Vec<TileOverride>) {
+        self.undo.push(overrides.clone());
+        self.redo.clear();
+
+        if self.undo.len() > HISTORY {
+            self.undo.remove(0);
+        }
+    }
+
+    pub fn undo(&mut self, overrides: &mut Vec<TileOverride>) -> bool {
+        match self.undo.pop() {
+            Some(previous) => {
+                self.redo.push(std::mem::replace(overrides, previous));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self, overrides: &mut Vec<TileOverride>) -> bool {
+        match self.redo.pop() {
+            Some(next) => {
+                self.undo.push(std::mem::replace(overrides, next));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// apply the brush around a tile, returning true if
+    /// any of the edited tiles changed
+    pub fn paint(
+        &self,
+        overrides: &mut Vec<TileOverride>,
+        areas: &HashMap<Location, Area>,
+        center: Location,
+    ) -> bool {
+        let brush = &self.brush;
+        let origin = Point::from(center);
+        let radius = brush.size as i32;
+        let strength = brush.strength.max(1).min(100) as f32 / 100.0;
+
+        let mut changed = false;
+
+        for (location, area) in areas.iter() {
+            let distance = origin.distance(Point::from(*location));
+            if distance > radius {
+                continue;
+            }
+
+            if brush.tool == Tool::Erase {
+                let count = overrides.len();
+                overrides.retain(|o| o.location != *location);
+                changed |= overrides.len() != count;
+                continue;
+            }
+
+            // strokes fade towards the edge of the brush
+            let falloff = 1.0 - distance as f32 / (radius + 1) as f32;
+
+            let tile = match overrides.iter().position(|o| o.location == *location) {
+                Some(i) => &mut overrides[i],
+                None => {
+                    overrides.push(TileOverride {
+                        location: *location,
+                        ..Default::default()
+                    });
+                    overrides.last_mut().unwrap()
+                }
+            };
+
+            let before = tile.clone();
+
+            match brush.tool {
+                Tool::Raise | Tool::Lower => {
+                    let current = tile.elevation.unwrap_or(area.elevation());
+                    let delta = brush.strength as f32 * STEP * falloff;
+                    let value = match brush.tool {
+                        Tool::Raise => current + delta,
+                        _ => current - delta,
+                    };
+                    tile.elevation = Some(value.min(bounds::MAX_ELEV).max(bounds::MIN_ELEV));
+                }
+                Tool::Moisture => {
+                    let current = tile.moisture.unwrap_or(area.moisture()) as f32;
+                    let target = brush.moisture.min(100) as f32;
+                    let value = current + (target - current) * strength * falloff;
+                    tile.moisture = Some(value.round() as u8);
+                }
+                Tool::Soil => tile.soil = Some(brush.soil),
+                Tool::Biome => tile.biome = Some(brush.biome),
+                Tool::Foliage => tile.foliage = Some(brush.foliage),
+                Tool::Structure => tile.structure = Some(brush.structure),
+                Tool::River => tile.river = true,
+                Tool::Road => tile.road = true,
+                Tool::Erase => (),
+            }
+
+            changed |= *tile != before;
+        }
+
+        // painting may add tiles that end up unchanged
+        overrides.retain(|o| !o.is_empty());
+
+        changed
+    }
+}
+
+impl Display for Tool {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn areas() -> HashMap<Location, Area> {
+        let mut areas = HashMap::new();
+        for x in -3..=3 {
+            for y in -3..=3 {
+                let area = Area::create()
+                    .with_location((x, y))
+                    .with_elevation(100.0)
+                    .with_moisture(20)
+                    .with_textures(vec![0])
+                    .build();
+                areas.insert((x, y), area);
+            }
+        }
+        areas
+    }
+
+    #[test]
+    fn test_brush_fades_from_center() {
+        let areas = areas();
+        let mut overrides = vec![];
+
+        let mut editor = Editor::new();
+        editor.brush.tool = Tool::Raise;
+        editor.brush.size = 1;
+        editor.brush.strength = 10;
+
+        assert!(editor.paint(&mut overrides, &areas, (0, 0)));
+        assert_eq!(overrides.len(), 7);
+
+        let center = overrides.iter().find(|o| o.location == (0, 0)).unwrap();
+        let edge = overrides.iter().find(|o| o.location == (1, 0)).unwrap();
+        assert_eq!(center.elevation, Some(200.0));
+        assert_eq!(edge.elevation, Some(150.0));
+
+        editor.brush.tool = Tool::Erase;
+        editor.brush.size = 0;
+        assert!(editor.paint(&mut overrides, &areas, (0, 0)));
+        assert_eq!(overrides.len(), 6);
+    }
+
+    #[test]
+    fn test_strokes_can_be_undone_and_redone() {
+        let areas = areas();
+        let mut overrides = vec![];
+
+        let mut editor = Editor::new();
+        editor.brush.tool = Tool::Road;
+        editor.brush.size = 0;
+
+        editor.begin(&overrides);
+        editor.paint(&mut overrides, &areas, (2, 2));
+        assert!(overrides[0].road);
+
+        assert!(editor.undo(&mut overrides));
+        assert!(overrides.is_empty());
+        assert!(!editor.undo(&mut overrides));
+
+        assert!(editor.redo(&mut overrides));
+        assert_eq!(overrides[0].location, (2, 2));
+        assert!(!editor.can_redo());
+    }
+}
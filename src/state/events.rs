@@ -4,8 +4,12 @@ use std::collections::HashSet;
 #[derive(Clone, Eq, PartialEq, Hash)]
 pub enum Action {
     UpdateTerrain,
+    RebuildMap,
     UpdateOverlay,
     PlaceUnit,
+    UpdateUnits,
+    UpdateStructures,
+    EndTurn,
 }
 
 #[derive(Default, Clone)]
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use bevy::prelude::Color;
+
+use crate::generation::PlayerId;
+
+/// the most players a game can hold
+pub const SLOTS: usize = 4;
+
+/// The team each player fights for. Players on the same team
+/// are allies, while team 0 and unknown players fight alone
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Teams {
+    teams: HashMap<PlayerId, u8>,
+}
+
+/// The colors players fight under
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Red,
+    Blue,
+    Green,
+    Yellow,
+}
+
+impl Side {
+    pub fn all() -> [Side; SLOTS] {
+        [Side::Red, Side::Blue, Side::Green, Side::Yellow]
+    }
+
+    /// the side a slot starts with
+    pub fn from_slot(slot: usize) -> Self {
+        Self::all()[slot % SLOTS]
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Side::Red => Color::rgb(0.8, 0.2, 0.2),
+            Side::Blue => Color::rgb(0.2, 0.3, 0.8),
+            Side::Green => Color::rgb(0.2, 0.6, 0.2),
+            Side::Yellow => Color::rgb(0.8, 0.7, 0.1),
+        }
+    }
+}
+
+impl Teams {
+    pub fn new(teams: HashMap<PlayerId, u8>) -> Self {
+        Self { teams }
+    }
+
+    pub fn team(&self, player: &PlayerId) -> u8 {
+        self.teams.get(player).cloned().unwrap_or(0)
+    }
+
+    /// true if the players fight against each other
+    pub fn is_enemy(&self, a: &PlayerId, b: &PlayerId) -> bool {
+        if a == b {
+            return false;
+        }
+
+        match (self.team(a), self.team(b)) {
+            (0, _) | (_, 0) => true,
+            (x, y) => x != y,
+        }
+    }
+}
+
+impl Default for Side {
+    fn default() -> Self {
+        Side::Red
+    }
+}
+
+impl Display for Side {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Side::Red => write!(f, "Red"),
+            Side::Blue => write!(f, "Blue"),
+            Side::Green => write!(f, "Green"),
+            Side::Yellow => write!(f, "Yellow"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_teammates_are_allies() {
+        let (a, b, c, d) = (PlayerId::new(), PlayerId::new(), PlayerId::new(), PlayerId::new());
+        let teams = Teams::new(vec![(a, 1), (b, 1), (c, 2), (d, 0)].into_iter().collect());
+
+        assert!(!teams.is_enemy(&a, &b));
+        assert!(teams.is_enemy(&a, &c));
+        assert!(!teams.is_enemy(&d, &d));
+        assert!(teams.is_enemy(&d, &a));
+        assert!(teams.is_enemy(&a, &PlayerId::new()));
+    }
+}
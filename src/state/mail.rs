@@ -0,0 +1,401 @@
+use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::generation::{ChangeType, Factors, Id, Order, PlayerId};
+use crate::networking::messages::MessageData;
+use crate::networking::version::Version;
+use crate::state::{Attack, Report, Snapshot, Victory};
+
+/// A player taking part in a game played by turn files
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Seat {
+    pub id: PlayerId,
+    pub name: String,
+}
+
+/// Everything one player did during their turn
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Submission {
+    pub player: PlayerId,
+
+    /// units created, moved and changed, in the order
+    /// they were applied
+    pub messages: Vec<MessageData>,
+
+    /// standing orders for the player's units
+    pub orders: Vec<(Id, Vec<Order>)>,
+
+    /// attacks waiting for the end of the turn
+    pub attacks: Vec<Attack>,
+}
+
+impl Submission {
+    /// true if everything in the turn was done by its own seat to
+    /// its own units, adding any units it created to the owners
+    pub fn is_bound(&self, owners: &mut HashMap<Id, PlayerId>) -> bool {
+        let player = self.player;
+        let owns = |owners: &HashMap<Id, PlayerId>, id: &Id| owners.get(id) == Some(&player);
+
+        for message in self.messages.iter() {
+            if message.header().sender != player {
+                return false;
+            }
+
+            let bound = match message {
+                MessageData::Create(v) => {
+                    let id = *v.unit.id();
+                    let bound = *v.unit.player_id() == player && !owners.contains_key(&id);
+                    owners.insert(id, player);
+                    bound
+                }
+                MessageData::Change(v) => v.changes
+                    .iter()
+                    .all(|c| owns(owners, &c.id) && match &c.action {
+                        ChangeType::Reassign(to, _) => owns(owners, to),
+                        _ => true,
+                    }),
+                MessageData::Move(v) => v.moves
+                    .iter()
+                    .all(|(id, _, _)| owns(owners, id)),
+                MessageData::Order(v) => v.orders
+                    .iter()
+                    .all(|(id, _)| owns(owners, id)),
+                _ => false,
+            };
+
+            if !bound {
+                return false;
+            }
+        }
+
+        self.orders.iter().all(|(id, _)| owns(owners, id)) &&
+        self.attacks.iter().all(|a| a.player == player && a.units.iter().all(|id| owns(owners, id)))
+    }
+}
+
+/// A game passed between players one turn at a time
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Mail {
+    pub version: Version,
+    pub seed: u32,
+    pub factors: Factors,
+    pub victory: Victory,
+
+    /// the game as the turn began
+    pub start: Snapshot,
+
+    /// players in the order they take their turns
+    pub seats: Vec<Seat>,
+
+    /// the seat the file was written for
+    pub to: Option<PlayerId>,
+
+    /// turns already taken by earlier seats
+    pub submissions: Vec<Submission>,
+
+    /// what happened during the last resolved turn
+    pub report: Report,
+}
+
+/// The file as written, with a signature over the game
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    body: String,
+    signature: String,
+}
+
+impl Mail {
+    pub fn new(seed: u32, factors: Factors, victory: Victory, start: Snapshot, seats: Vec<Seat>) -> Self {
+        Self {
+            version: Version::current(),
+            seed,
+            factors,
+            victory,
+            start,
+            to: seats.first().map(|s| s.id),
+            seats,
+            submissions: vec![],
+            report: Report::default(),
+        }
+    }
+
+    /// read a turn file, failing if it was changed by anyone who
+    /// doesn't know the game password or made by another build
+    pub fn load<P: AsRef<Path>>(path: P, password: &str) -> Result<Self> {
+        if password.is_empty() {
+            return Err(Error::MailPassword);
+        }
+
+        let text = fs::read_to_string(path)?;
+
+        let envelope: Envelope = serde_json::from_str(&text)
+            .map_err(|e| Error::MailInvalid(e.to_string()))?;
+
+        if !verify(&envelope.body, password, &envelope.signature) {
+            return Err(Error::MailSignature);
+        }
+
+        let mail: Self = serde_json::from_str(&envelope.body)
+            .map_err(|e| Error::MailInvalid(e.to_string()))?;
+
+        Version::current().check(&mail.version)?;
+
+        if !mail.is_bound() {
+            return Err(Error::MailInvalid("a turn was played for the wrong seat".into()));
+        }
+
+        Ok(mail)
+    }
+
+    /// write a turn file signed with the game password
+    pub fn save<P: AsRef<Path>>(&self, path: P, password: &str) -> Result<()> {
+        if password.is_empty() {
+            return Err(Error::MailPassword);
+        }
+
+        let body = serde_json::to_string(self)
+            .map_err(|e| Error::MailInvalid(e.to_string()))?;
+
+        let envelope = Envelope {
+            signature: sign(&body, password),
+            body,
+        };
+
+        let text = serde_json::to_string(&envelope)
+            .map_err(|e| Error::MailInvalid(e.to_string()))?;
+
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn turn(&self) -> u32 {
+        self.start.turn
+    }
+
+    /// the seat that plays next, or None if everyone has played
+    pub fn next(&self) -> Option<&Seat> {
+        self.seats
+            .iter()
+            .find(|s| !self.has_submitted(&s.id))
+    }
+
+    pub fn has_submitted(&self, player: &PlayerId) -> bool {
+        self.submissions
+            .iter()
+            .any(|s| s.player == *player)
+    }
+
+    /// true once every seat has played the turn
+    pub fn is_complete(&self) -> bool {
+        self.next().is_none()
+    }
+
+    /// the seat that plays the file, which is the first
+    /// seat once the turn is ready to be resolved
+    pub fn recipient(&self) -> Option<&Seat> {
+        self.next().or_else(|| self.seats.first())
+    }
+
+    /// add a player's turn, refusing anyone but the next seat
+    /// and turns that act for another seat
+    pub fn submit(&mut self, submission: Submission) -> bool {
+        let next = self.next().map(|s| s.id);
+        if next != Some(submission.player) {
+            return false;
+        }
+
+        self.submissions.push(submission);
+        if !self.is_played_in_order() {
+            self.submissions.pop();
+            return false;
+        }
+
+        self.to = self.recipient().map(|s| s.id);
+        true
+    }
+
+    /// true if the file is for the seat that plays next, the
+    /// turns were taken in seat order and each only acts for
+    /// its own seat
+    pub fn is_bound(&self) -> bool {
+        self.to == self.recipient().map(|s| s.id) && self.is_played_in_order()
+    }
+
+    fn is_played_in_order(&self) -> bool {
+        let mut owners: HashMap<Id, PlayerId> = self.start.units
+            .iter()
+            .map(|u| (*u.id(), *u.player_id()))
+            .collect();
+
+        self.submissions.len() <= self.seats.len() &&
+        self.submissions
+            .iter()
+            .zip(self.seats.iter())
+            .all(|(t, s)| t.player == s.id && t.is_bound(&mut owners))
+    }
+
+    /// start the next turn once the last one is resolved
+    pub fn advance(&mut self, start: Snapshot, victory: Victory, report: Report) {
+        self.start = start;
+        self.victory = victory;
+        self.report = report;
+        self.submissions.clear();
+        self.to = self.recipient().map(|s| s.id);
+    }
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn mac(body: &str, password: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(password.as_bytes())
+        .expect("HMAC takes keys of any length");
+    mac.update(body.as_bytes());
+    mac
+}
+
+/// authenticate the body with the game password
+fn sign(body: &str, password: &str) -> String {
+    format!("{:x}", mac(body, password).finalize().into_bytes())
+}
+
+/// check a signature without leaking how much of it matched
+fn verify(body: &str, password: &str, signature: &str) -> bool {
+    let bytes: Option<Vec<u8>> = match signature.len() % 2 {
+        0 => (0..signature.len())
+            .step_by(2)
+            .map(|i| signature
+                .get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok()))
+            .collect(),
+        _ => None,
+    };
+
+    match bytes {
+        Some(b) => mac(body, password).verify(&b).is_ok(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::Fortifications;
+    use crate::state::Context;
+
+    fn mail(seats: Vec<Seat>) -> Mail {
+        let start = Snapshot {
+            context: Context::default(),
+            turn: 1,
+            units: vec![],
+            tiles: vec![],
+            fortifications: Fortifications::new(),
+            pending: vec![],
+        };
+        Mail::new(1, Factors::default(), Victory::default(), start, seats)
+    }
+
+    fn submission(player: PlayerId) -> Submission {
+        Submission {
+            player,
+            messages: vec![],
+            orders: vec![],
+            attacks: vec![],
+        }
+    }
+
+    #[test]
+    fn test_seats_play_in_order() {
+        let a = Seat { id: PlayerId::new(), name: "A".into() };
+        let b = Seat { id: PlayerId::new(), name: "B".into() };
+        let mut mail = mail(vec![a.clone(), b.clone()]);
+
+        assert_eq!(mail.next(), Some(&a));
+        assert!(mail.submit(submission(a.id)));
+        assert!(!mail.submit(submission(a.id)));
+        assert!(!mail.submit(submission(PlayerId::new())));
+
+        assert_eq!(mail.next(), Some(&b));
+        assert!(mail.submit(submission(b.id)));
+        assert!(mail.is_complete());
+    }
+
+    #[test]
+    fn test_signature_needs_password() {
+        let body = "{}";
+        assert_eq!(sign(body, "secret"), sign(body, "secret"));
+        assert_ne!(sign(body, "secret"), sign(body, "other"));
+        assert_ne!(sign(body, "secret"), sign("{ }", "secret"));
+
+        assert!(verify(body, "secret", &sign(body, "secret")));
+        assert!(!verify(body, "other", &sign(body, "secret")));
+        assert!(!verify(body, "secret", "not hex"));
+    }
+
+    #[test]
+    fn test_empty_password_is_refused() {
+        let mail = mail(vec![]);
+        let path = std::env::temp_dir().join("warfare-empty-password.json");
+        assert!(matches!(mail.save(&path, ""), Err(Error::MailPassword)));
+        assert!(matches!(Mail::load(&path, ""), Err(Error::MailPassword)));
+    }
+
+    #[test]
+    fn test_turns_act_for_their_own_seat() {
+        let a = Seat { id: PlayerId::new(), name: "A".into() };
+        let b = Seat { id: PlayerId::new(), name: "B".into() };
+        let mut mail = mail(vec![a.clone(), b.clone()]);
+
+        let mut forged = submission(a.id);
+        forged.attacks.push(Attack::new(b.id, vec![], (0, 0).into()));
+        assert!(!mail.submit(forged));
+
+        assert!(mail.submit(submission(a.id)));
+        assert!(mail.is_bound());
+
+        mail.submissions[0].player = b.id;
+        assert!(!mail.is_bound());
+    }
+
+    #[test]
+    fn test_turns_only_touch_their_own_units() {
+        use crate::generation::{Change, Unit};
+        use crate::networking::messages::{ChangeData, HeaderData};
+
+        let a = Seat { id: PlayerId::new(), name: "A".into() };
+        let b = Seat { id: PlayerId::new(), name: "B".into() };
+        let mut mail = mail(vec![a.clone(), b.clone()]);
+
+        let ours = Unit::new(a.id);
+        let theirs = Unit::new(b.id);
+        mail.start.units = vec![ours.clone(), theirs.clone()];
+
+        let change = |unit: &Unit| MessageData::Change(ChangeData {
+            header: HeaderData::new(a.id, "A".into()),
+            changes: vec![Change::order(unit, (1, 1).into(), 0)],
+        });
+
+        let mut forged = submission(a.id);
+        forged.messages.push(change(&theirs));
+        assert!(!mail.submit(forged));
+
+        let mut forged = submission(a.id);
+        forged.orders.push((*theirs.id(), vec![]));
+        assert!(!mail.submit(forged));
+
+        let mut honest = submission(a.id);
+        honest.messages.push(change(&ours));
+        honest.orders.push((*ours.id(), vec![]));
+        assert!(mail.submit(honest));
+        assert!(mail.is_bound());
+        assert_eq!(mail.to, Some(b.id));
+
+        // the file can't be redirected to another seat
+        mail.to = Some(a.id);
+        assert!(!mail.is_bound());
+    }
+}
@@ -1,6 +1,16 @@
 mod calendar;
+mod context;
+mod editor;
 mod events;
+mod lobby;
+mod mail;
 mod movement;
+mod replay;
+mod report;
+mod scenario;
+mod snapshot;
+mod turn;
+mod victory;
 mod state;
 
 #[macro_use]
@@ -10,6 +20,16 @@ pub mod demographics;
 pub mod traits;
 
 pub use calendar::{Calendar, Season};
+pub use editor::{Brush, Editor, Tool};
 pub use events::{Action, Events};
-pub use state::{Context, State, Terrain};
+pub use context::Context;
+pub use state::{State, Terrain, MAX_SIZE, MIN_SIZE};
 pub use flags::Flags;
+pub use lobby::{Side, Teams, SLOTS};
+pub use mail::{Mail, Seat, Submission};
+pub use replay::{Frame, Recording, Replay};
+pub use report::{Category, Log, Record, Report};
+pub use scenario::{Scenario, Slot, TileOverride, UnitSpec};
+pub use snapshot::Snapshot;
+pub use turn::{Attack, Fire, TurnTimer};
+pub use victory::{Objective, Outcome, Statistics, Victory};
@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::generation::{Change, Factors};
+use crate::networking::messages::MessageData;
+use crate::networking::version::Version;
+use crate::state::{Snapshot, Victory};
+
+/// One recorded turn of a game
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Frame {
+    /// the game as the turn began
+    pub start: Snapshot,
+    pub victory: Victory,
+
+    /// units created, moved and changed during the turn,
+    /// in the order they were applied
+    pub messages: Vec<MessageData>,
+
+    /// the turn that the changes advanced to
+    pub turn: u32,
+    pub changes: Vec<Change>,
+
+    /// checksum of the game once the changes were applied
+    pub checksum: u64,
+}
+
+/// A recorded game that can be played back turn by turn
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Replay {
+    pub version: Version,
+    pub seed: u32,
+    pub factors: Factors,
+    pub frames: Vec<Frame>,
+}
+
+impl Replay {
+    /// read a replay from a RON or JSON file, failing if
+    /// it was recorded by an incompatible build
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+
+        let replay: Self = match path.extension().and_then(|e| e.to_str()) {
+            Some("ron") => ron::from_str(&text)
+                .map_err(|e| Error::ReplayInvalid(e.to_string()))?,
+            Some("json") => serde_json::from_str(&text)
+                .map_err(|e| Error::ReplayInvalid(e.to_string()))?,
+            e => return Err(Error::UnknownFormat(e.unwrap_or("").into())),
+        };
+
+        Version::current().check(&replay.version)?;
+        Ok(replay)
+    }
+
+    /// write a replay as RON or JSON depending on the extension
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+
+        let text = match path.extension().and_then(|e| e.to_str()) {
+            Some("ron") => ron::ser::to_string(self)
+                .map_err(|e| Error::ReplayInvalid(e.to_string()))?,
+            Some("json") => serde_json::to_string(self)
+                .map_err(|e| Error::ReplayInvalid(e.to_string()))?,
+            e => return Err(Error::UnknownFormat(e.unwrap_or("").into())),
+        };
+
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+/// Collects frames while a game is being recorded
+#[derive(Default)]
+pub struct Recording {
+    replay: Option<Replay>,
+    frame: Option<Frame>,
+}
+
+impl Recording {
+    /// start recording from the current turn
+    pub fn begin(&mut self, seed: u32, factors: Factors, victory: Victory, start: Snapshot) {
+        self.replay = Some(Replay {
+            version: Version::current(),
+            seed,
+            factors,
+            frames: vec![],
+        });
+        self.frame = Some(Frame::new(start, victory));
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// the number of turns recorded so far
+    pub fn len(&self) -> usize {
+        self.replay
+            .as_ref()
+            .map(|r| r.len())
+            .unwrap_or(0)
+    }
+
+    /// add a message to the turn, ignoring those
+    /// that don't change the board
+    pub fn message(&mut self, message: MessageData) {
+        if !message.is_ordered() {
+            return;
+        }
+
+        if let Some(frame) = self.frame.as_mut() {
+            frame.messages.push(message);
+        }
+    }
+
+    /// close the current turn and start the next one
+    pub fn turn(&mut self, turn: u32, changes: &Vec<Change>, checksum: u64, next: Snapshot, victory: Victory) {
+        let (replay, frame) = match (self.replay.as_mut(), self.frame.take()) {
+            (Some(r), Some(f)) => (r, f),
+            _ => return,
+        };
+
+        replay.frames.push(Frame {
+            turn,
+            changes: changes.clone(),
+            checksum,
+            ..frame
+        });
+
+        self.frame = Some(Frame::new(next, victory));
+    }
+
+    /// stop recording, dropping the unfinished turn
+    pub fn finish(&mut self) -> Option<Replay> {
+        self.frame = None;
+        self.replay.take()
+    }
+}
+
+impl Frame {
+    fn new(start: Snapshot, victory: Victory) -> Self {
+        Self {
+            turn: start.turn,
+            start,
+            victory,
+            messages: vec![],
+            changes: vec![],
+            checksum: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::Fortifications;
+    use crate::state::Context;
+
+    fn snapshot(turn: u32) -> Snapshot {
+        Snapshot {
+            context: Context::default(),
+            turn,
+            units: vec![],
+            tiles: vec![],
+            fortifications: Fortifications::new(),
+            pending: vec![],
+        }
+    }
+
+    #[test]
+    fn test_recording_closes_turns() {
+        let mut recording = Recording::default();
+        recording.turn(2, &vec![], 0, snapshot(2), Victory::default());
+        assert!(!recording.is_recording());
+
+        recording.begin(1, Factors::default(), Victory::default(), snapshot(1));
+        recording.turn(2, &vec![], 7, snapshot(2), Victory::default());
+        recording.turn(3, &vec![], 8, snapshot(3), Victory::default());
+
+        let replay = recording.finish().unwrap();
+        assert_eq!(replay.len(), 2);
+        assert_eq!(replay.frames[0].start.turn, 1);
+        assert_eq!(replay.frames[1].turn, 3);
+        assert_eq!(replay.frames[1].checksum, 8);
+        assert!(!recording.is_recording());
+    }
+}
@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::generation::{Condition, Id};
+
+/// The kinds of record a report can be filtered by
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Combat,
+    Casualties,
+    Destroyed,
+    Incidents,
+    Weather,
+}
+
+/// A single thing that happened during a turn
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Record {
+    /// a unit was hit during combat
+    Combat {
+        unit: Id,
+        unit_name: String,
+        player_name: String,
+        damage: u32,
+    },
+
+    /// soldiers in a unit were killed
+    Casualties {
+        unit: Id,
+        unit_name: String,
+        lost: usize,
+    },
+
+    /// a unit was wiped out and removed from the map
+    Destroyed {
+        unit: Id,
+        unit_name: String,
+        player_name: String,
+    },
+
+    /// a soldier suffered an accident or illness
+    Incident {
+        unit: Id,
+        unit_name: String,
+        soldier: String,
+        condition: Condition,
+    },
+
+    /// the season turned
+    Weather {
+        conditions: String,
+    },
+}
+
+/// Everything that happened during a single turn
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct Report {
+    turn: u32,
+    records: Vec<Record>,
+}
+
+/// The reports of every turn played so far
+#[derive(Default, Debug, Clone)]
+pub struct Log {
+    reports: Vec<Report>,
+}
+
+impl Category {
+    pub fn all() -> [Category; 5] {
+        [
+            Category::Combat,
+            Category::Casualties,
+            Category::Destroyed,
+            Category::Incidents,
+            Category::Weather,
+        ]
+    }
+}
+
+impl Record {
+    pub fn category(&self) -> Category {
+        match self {
+            Record::Combat { .. } => Category::Combat,
+            Record::Casualties { .. } => Category::Casualties,
+            Record::Destroyed { .. } => Category::Destroyed,
+            Record::Incident { .. } => Category::Incidents,
+            Record::Weather { .. } => Category::Weather,
+        }
+    }
+}
+
+impl Report {
+    pub fn new(turn: u32) -> Self {
+        Self {
+            turn,
+            records: vec![],
+        }
+    }
+
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    pub fn add(&mut self, record: Record) {
+        log::info!("turn {}: {}", self.turn, record);
+        self.records.push(record);
+    }
+
+    pub fn records(&self) -> &Vec<Record> {
+        &self.records
+    }
+
+    /// the records in any of the categories
+    pub fn filtered(&self, categories: &[Category]) -> Vec<&Record> {
+        self.records
+            .iter()
+            .filter(|r| categories.contains(&r.category()))
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+impl Log {
+    pub fn push(&mut self, report: Report) {
+        self.reports.push(report);
+    }
+
+    pub fn reports(&self) -> &Vec<Report> {
+        &self.reports
+    }
+
+    pub fn clear(&mut self) {
+        self.reports.clear();
+    }
+
+    /// write the log as JSON, or as plain text for any
+    /// other extension
+    pub fn export<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+
+        let text = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::to_string_pretty(&self.reports)
+                .map_err(|e| Error::ExportFailed(e.to_string()))?,
+            _ => self.to_string(),
+        };
+
+        fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Category::Combat => write!(f, "Combat"),
+            Category::Casualties => write!(f, "Casualties"),
+            Category::Destroyed => write!(f, "Destroyed"),
+            Category::Incidents => write!(f, "Incidents"),
+            Category::Weather => write!(f, "Weather"),
+        }
+    }
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Record::Combat { unit_name, player_name, damage, .. } => {
+                write!(f, "{} ({}) took {} damage", unit_name, player_name, damage)
+            }
+            Record::Casualties { unit_name, lost, .. } => {
+                write!(f, "{} lost {} soldiers", unit_name, lost)
+            }
+            Record::Destroyed { unit_name, player_name, .. } => {
+                write!(f, "{} ({}) was destroyed", unit_name, player_name)
+            }
+            Record::Incident { unit_name, soldier, condition, .. } => {
+                write!(f, "{} ({}): {}", soldier, unit_name, condition)
+            }
+            Record::Weather { conditions } => {
+                write!(f, "{}", conditions)
+            }
+        }
+    }
+}
+
+impl Display for Log {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        for report in self.reports.iter() {
+            writeln!(f, "Turn {}", report.turn)?;
+            for record in report.records.iter() {
+                writeln!(f, "    {}", record)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_by_category() {
+        let mut report = Report::new(2);
+        report.add(Record::Weather { conditions: "Winter has come".into() });
+        report.add(Record::Casualties {
+            unit: Id::new(),
+            unit_name: "1st".into(),
+            lost: 3,
+        });
+
+        assert_eq!(report.filtered(&Category::all()).len(), 2);
+        assert_eq!(report.filtered(&[Category::Weather]).len(), 1);
+        assert!(report.filtered(&[Category::Combat]).is_empty());
+    }
+}
@@ -0,0 +1,353 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::behavior::Difficulty;
+use crate::error::{Error, Result};
+use crate::generation::{Area, Biome, Factors, Foliage, PlayerId, Soil, Specialty, Structure};
+use crate::objects::{Location, Point};
+use crate::resources::{Label, Textures};
+use crate::state::{Context, Objective, MAX_SIZE, MIN_SIZE};
+
+/// impedance of a tile with a river running through it
+const RIVER: u8 = 80;
+
+/// impedance of a tile with a road across it
+const ROAD: u8 = 5;
+
+/// A prebuilt map with starting forces and objectives
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Scenario {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    /// size of the map (in tiles)
+    pub width: u32,
+    pub height: u32,
+
+    /// seed for the generated terrain
+    pub seed: u32,
+
+    /// generator factors, the defaults are used if missing
+    #[serde(default)]
+    pub factors: Option<Factors>,
+
+    /// turn the game starts on
+    #[serde(default)]
+    pub turn: Option<u32>,
+
+    /// hand-edited tiles that replace generated ones
+    #[serde(default)]
+    pub tiles: Vec<TileOverride>,
+
+    /// players and their starting forces
+    #[serde(default)]
+    pub slots: Vec<Slot>,
+
+    /// victory conditions, eliminate all enemies if empty
+    #[serde(default)]
+    pub objectives: Vec<Objective>,
+}
+
+/// Changes made to a single generated tile
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct TileOverride {
+    pub location: Location,
+    pub biome: Option<Biome>,
+    pub soil: Option<Soil>,
+    pub moisture: Option<u8>,
+    pub rocks: Option<u8>,
+    pub fertility: Option<u8>,
+    pub elevation: Option<f32>,
+    pub temperature: Option<f32>,
+    pub impedance: Option<u8>,
+    pub foliage: Option<Foliage>,
+    pub structure: Option<Structure>,
+    pub river: bool,
+    pub road: bool,
+}
+
+/// A player slot in a scenario
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Slot {
+    pub name: String,
+
+    /// filled by a computer player if set
+    #[serde(default)]
+    pub computer: Option<Difficulty>,
+
+    /// tiles where the player may place units
+    #[serde(default)]
+    pub deployment: Vec<Location>,
+
+    /// units the player starts with
+    #[serde(default)]
+    pub units: Vec<UnitSpec>,
+
+    /// the player that took this slot
+    #[serde(default)]
+    pub player: Option<PlayerId>,
+
+    /// true once the starting units have been placed
+    #[serde(default)]
+    pub deployed: bool,
+}
+
+/// A unit placed at the start of a scenario
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UnitSpec {
+    pub name: String,
+    pub specialty: Specialty,
+    pub soldiers: usize,
+    pub position: Location,
+}
+
+impl Scenario {
+    /// read a scenario from a RON or JSON file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("ron") => Self::from_ron(&text),
+            Some("json") => Self::from_json(&text),
+            e => Err(Error::UnknownFormat(e.unwrap_or("").into())),
+        }
+    }
+
+    /// write a scenario as RON or JSON depending on the extension
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+
+        let text = match path.extension().and_then(|e| e.to_str()) {
+            Some("ron") => ron::ser::to_string_pretty(self, Default::default())
+                .map_err(|e| Error::ScenarioInvalid(e.to_string()))?,
+            Some("json") => serde_json::to_string_pretty(self)
+                .map_err(|e| Error::ScenarioInvalid(e.to_string()))?,
+            e => return Err(Error::UnknownFormat(e.unwrap_or("").into())),
+        };
+
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self> {
+        let scenario: Self = ron::from_str(text)
+            .map_err(|e| Error::ScenarioInvalid(e.to_string()))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        let scenario: Self = serde_json::from_str(text)
+            .map_err(|e| Error::ScenarioInvalid(e.to_string()))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// check that the map fits the size limits and that every
+    /// unit and deployment zone can actually be placed on it
+    pub fn validate(&self) -> Result<()> {
+        let sizes = MIN_SIZE..=MAX_SIZE;
+        if !sizes.contains(&self.width) || !sizes.contains(&self.height) {
+            return Err(Error::ScenarioInvalid(format!(
+                "map must be between {} and {} tiles across", MIN_SIZE, MAX_SIZE)));
+        }
+
+        let context = Context::new(self.width, self.height, 0, 0);
+        let on_map = |l: &Location| {
+            let point: Point = (*l).into();
+            point.in_bounds(&context)
+        };
+
+        for slot in self.slots.iter() {
+            if let Some(l) = slot.deployment.iter().find(|l| !on_map(l)) {
+                return Err(Error::ScenarioInvalid(format!(
+                    "{} deploys off the map at {:?}", slot.name, l)));
+            }
+
+            for spec in slot.units.iter() {
+                let problem = match spec {
+                    s if !Textures::has_unit(&s.specialty) => "can't be drawn",
+                    s if s.soldiers == 0 => "has no soldiers",
+                    s if !on_map(&s.position) => "is off the map",
+                    _ => continue,
+                };
+                return Err(Error::ScenarioInvalid(format!("{} {}", spec.name, problem)));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn factors(&self) -> Factors {
+        self.factors.clone().unwrap_or_default()
+    }
+
+    /// the slot taken by a player
+    pub fn slot(&self, player: &PlayerId) -> Option<&Slot> {
+        self.slots
+            .iter()
+            .find(|s| s.player.as_ref() == Some(player))
+    }
+
+    /// give the next open human slot to a player
+    pub fn claim(&mut self, player: PlayerId) -> Option<&Slot> {
+        if self.slot(&player).is_some() {
+            return None;
+        }
+
+        let slot = self.slots
+            .iter_mut()
+            .find(|s| s.computer.is_none() && s.player.is_none())?;
+
+        slot.player = Some(player);
+        Some(slot)
+    }
+
+    /// true if the player may place a unit at a location
+    pub fn can_deploy(&self, player: &PlayerId, location: &Location) -> bool {
+        match self.slot(player) {
+            Some(s) if !s.deployment.is_empty() => s.deployment.contains(location),
+            _ => true,
+        }
+    }
+}
+
+impl TileOverride {
+    /// true if the tile doesn't change anything
+    pub fn is_empty(&self) -> bool {
+        *self == Self {
+            location: self.location,
+            ..Default::default()
+        }
+    }
+
+    /// replace the generated values of an area
+    pub fn apply(&self, area: Area, textures: &Textures) -> Area {
+        let mut area = area;
+
+        if let Some(v) = self.biome {
+            area = area.with_biome(v);
+        }
+        if let Some(v) = self.soil {
+            let mut stack = area.textures().clone();
+            stack[0] = textures.soil(&v);
+            area = area.with_soil(v).with_textures(stack);
+        }
+        if let Some(v) = self.moisture {
+            area = area.with_moisture(v);
+        }
+        if let Some(v) = self.rocks {
+            area = area.with_rocks(v);
+        }
+        if let Some(v) = self.fertility {
+            area = area.with_fertility(v);
+        }
+        if let Some(v) = self.elevation {
+            area = area.with_elevation(v);
+        }
+        if let Some(v) = self.temperature {
+            area = area.with_temperature(v);
+        }
+        if let Some(v) = self.impedance {
+            area = area.with_impedance(v);
+        }
+        if let Some(v) = self.foliage {
+            let trees = textures.get(Label::Trees);
+            let mut stack: Vec<usize> = area
+                .textures()
+                .iter()
+                .cloned()
+                .filter(|t| *t != trees)
+                .collect();
+            if v == Foliage::Trees {
+                stack.push(trees);
+            }
+            area = area.with_textures(stack);
+        }
+        if self.river {
+            let soil = area.textures()[0];
+            let water = textures.get(Label::ShallowWater);
+            let impedance = area.impedance().max(RIVER);
+            area = area
+                .with_textures(vec![soil, water])
+                .with_moisture(100)
+                .with_impedance(impedance);
+        }
+        if self.road {
+            let impedance = area.impedance().min(ROAD);
+            area = area.with_impedance(impedance);
+        }
+
+        area.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = r#"(
+        name: "Crossing",
+        width: 30,
+        height: 30,
+        seed: 42,
+        tiles: [
+            (location: (0, 0), soil: Some(Sand), elevation: Some(120.0)),
+        ],
+        slots: [
+            (
+                name: "Blue",
+                deployment: [(0, 0), (1, 0)],
+                units: [
+                    (name: "1st", specialty: Infantry, soldiers: 100, position: (0, 0)),
+                ],
+            ),
+            (name: "Red", computer: Some(Hard)),
+        ],
+        objectives: [Eliminate],
+    )"#;
+
+    #[test]
+    fn test_ron_scenario_roundtrip() {
+        let scenario = Scenario::from_ron(EXAMPLE).unwrap();
+        assert_eq!(scenario.slots.len(), 2);
+        assert_eq!(scenario.tiles[0].soil, Some(Soil::Sand));
+        assert_eq!(scenario.slots[1].computer, Some(Difficulty::Hard));
+
+        let json = serde_json::to_string(&scenario).unwrap();
+        let copy = Scenario::from_json(&json).unwrap();
+        assert_eq!(copy.name, "Crossing");
+        assert_eq!(copy.slots[0].units[0].position, (0, 0));
+    }
+
+    #[test]
+    fn test_invalid_scenarios_are_refused() {
+        let cases = [
+            EXAMPLE.replace("width: 30", "width: 5000"),
+            EXAMPLE.replace("specialty: Infantry", "specialty: Medical"),
+            EXAMPLE.replace("soldiers: 100", "soldiers: 0"),
+            EXAMPLE.replace("soldiers: 100, position: (0, 0)", "soldiers: 100, position: (90, 0)"),
+            EXAMPLE.replace("(1, 0)]", "(1, 90)]"),
+        ];
+
+        for case in cases.iter() {
+            assert!(matches!(Scenario::from_ron(case), Err(Error::ScenarioInvalid(_))));
+        }
+    }
+
+    #[test]
+    fn test_slots_are_claimed_in_order() {
+        let mut scenario = Scenario::from_ron(EXAMPLE).unwrap();
+        let player = PlayerId::new();
+
+        assert!(scenario.claim(player).is_some());
+        assert!(scenario.claim(PlayerId::new()).is_none());
+        assert!(scenario.can_deploy(&player, &(1, 0)));
+        assert!(!scenario.can_deploy(&player, &(5, 5)));
+    }
+}
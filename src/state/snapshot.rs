@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::generation::Unit;
+use crate::objects::Fortifications;
+use crate::state::{Attack, Context, TileOverride};
+use crate::state::traits::*;
+
+/// Everything a joining or returning player needs that
+/// can't be regenerated from the seed and factors
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Snapshot {
+    pub context: Context,
+    pub turn: u32,
+    pub units: Vec<Unit>,
+    pub tiles: Vec<TileOverride>,
+    pub fortifications: Fortifications,
+    pub pending: Vec<Attack>,
+}
+
+impl Snapshot {
+    pub fn checksum(&self) -> u64 {
+        checksum(self.turn, self.units.iter().collect(), &self.fortifications)
+    }
+}
+
+/// hash the parts of the game that every peer should agree
+/// on after a turn, independent of the order units are stored
+pub fn checksum(turn: u32, mut units: Vec<&Unit>, fortifications: &Fortifications) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    turn.hash(&mut hasher);
+
+    units.sort_by_key(|u| u.id().to_string());
+    for unit in units.into_iter() {
+        unit.id().hash(&mut hasher);
+        unit.player_id().hash(&mut hasher);
+        unit.position().integers().hash(&mut hasher);
+        unit.soldiers().len().hash(&mut hasher);
+        unit.health().hash(&mut hasher);
+        unit.actions().hash(&mut hasher);
+    }
+
+    let mut sites: Vec<_> = fortifications
+        .sites()
+        .into_iter()
+        .map(|(l, f)| (*l, f.progress()))
+        .collect();
+
+    sites.sort();
+    sites.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::PlayerId;
+    use crate::objects::Point;
+
+    #[test]
+    fn test_checksum_ignores_unit_order() {
+        let player = PlayerId::new();
+        let a = Unit::new(player).with_position(Point::new(1, 1));
+        let b = Unit::new(player).with_position(Point::new(2, 1));
+        let works = Fortifications::new();
+
+        assert_eq!(
+            checksum(4, vec![&a, &b], &works),
+            checksum(4, vec![&b, &a], &works),
+        );
+    }
+
+    #[test]
+    fn test_checksum_detects_moved_unit() {
+        let player = PlayerId::new();
+        let a = Unit::new(player).with_position(Point::new(1, 1));
+        let b = a.clone().with_position(Point::new(1, 2));
+        let works = Fortifications::new();
+
+        assert_ne!(
+            checksum(4, vec![&a], &works),
+            checksum(4, vec![&b], &works),
+        );
+    }
+}
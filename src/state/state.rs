@@ -1,33 +1,44 @@
 use bevy_tilemap::point::Point3;
-use once_cell::sync::Lazy;
+use bevy_tilemap::Tilemap;
 use std::collections::hash_map::HashMap;
-use std::sync::Mutex;
+use std::collections::HashSet;
 use log::*;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+use rand::seq::SliceRandom;
 
-use crate::objects::Location;
+use crate::objects::{Fortification, Fortifications, Location};
 use crate::objects::Point;
 use crate::objects::Map;
 use crate::resources::{Spectrum, Textures, Label};
 use crate::state::Action;
 use crate::state::Flags;
+use crate::state::{Attack, Fire, Log, Record, Recording, Report, Scenario, Slot, Snapshot, Teams, TileOverride, TurnTimer, UnitSpec, Victory};
+use crate::behavior::{Control, Hazards, Pathfinder, Sight};
 
-use crate::state::{traits::*, Calendar, Events};
+use crate::state::{snapshot, traits::*, Calendar, Context, Events};
 use crate::networking::messages::*;
-use crate::generation::{bounds, Area, Attribute, Cursor, Factors, Generator, Layers, Unit};
+use crate::networking::delta::{self, Summary};
+use crate::generation::{bounds, Area, Attribute, Cursor, Factors, Generator, Layers, Order, Structure, Time, Unit, Units, Change, ChangeType, Id, PlayerId};
 
-static CONTEXT: Lazy<Mutex<Context>> = Lazy::new(|| Mutex::new(Context::default()));
+/// fraction of an artillery barrage felt next to the impact
+const SPLASH: f32 = 0.5;
+
+/// actions spent by each unit that attacks
+pub const ATTACK_COST: u8 = 25;
+
+/// smallest and largest map (in tiles)
+pub const MIN_SIZE: u32 = 10;
+pub const MAX_SIZE: u32 = 200;
+
+/// smallest and largest tile (in pixels)
+const MIN_TILE: u32 = 20;
+const MAX_TILE: u32 = 400;
 
 #[derive(PartialEq, Eq, Hash, Clone, Copy)]
 pub enum StateFlag {
     Loaded,
-}
-
-#[derive(Debug, Clone)]
-pub struct Context {
-    pub width: u32,
-    pub height: u32,
-    pub tile_width: u32,
-    pub tile_height: u32,
+    Lobby,
 }
 
 #[derive(Default, Clone)]
@@ -36,6 +47,8 @@ pub struct Terrain {
     pub overlay: Attribute,
     pub seed: String,
     pub size: String,
+    pub tile_size: String,
+    pub overrides: Vec<TileOverride>,
 }
 
 pub struct State {
@@ -74,18 +87,34 @@ pub struct State {
     /// all units on the board
     pub units: Map,
 
-    pub cursor: Cursor,
-}
+    /// what happened during the last turn
+    pub report: Report,
 
-impl Default for Context {
-    fn default() -> Self {
-        Self {
-            width: 30,
-            height: 30,
-            tile_width: 175,
-            tile_height: 200,
-        }
-    }
+    /// what happened during every turn so far
+    pub log: Log,
+
+    /// attacks waiting for the end of the turn
+    pub pending: Vec<Attack>,
+
+    /// which players are allies, kept in line with the lobby
+    pub teams: Teams,
+
+    /// time limit for the current turn
+    pub timer: TurnTimer,
+
+    /// field works dug by units
+    pub fortifications: Fortifications,
+
+    /// how the game is won and who has won it
+    pub victory: Victory,
+
+    /// the scenario being played, if any
+    pub scenario: Option<Scenario>,
+
+    /// turns saved for a replay, if recording
+    pub recording: Recording,
+
+    pub cursor: Cursor,
 }
 
 impl Default for State {
@@ -102,7 +131,16 @@ impl Default for State {
             terrain: Default::default(),
             events: Default::default(),
             calendar: Default::default(),
-            units: Map::new(),
+            units: Map::new(&Context::default()),
+            report: Default::default(),
+            log: Default::default(),
+            pending: Default::default(),
+            teams: Default::default(),
+            timer: Default::default(),
+            fortifications: Default::default(),
+            victory: Default::default(),
+            scenario: None,
+            recording: Default::default(),
             cursor: Default::default(),
         }
     }
@@ -114,96 +152,289 @@ impl Terrain {
             .parse::<u32>()
             .unwrap_or(0)
     }
-}
 
-macro_rules! context {
-    () => {
-        CONTEXT.lock().expect("Could not lock context")
-    };
-}
+    /// the requested map size as "width x height" (in tiles)
+    pub fn size(&self) -> Option<(u32, u32)> {
+        dimensions(&self.size, MIN_SIZE, MAX_SIZE)
+    }
 
-impl Context {
-    pub fn total() -> usize {
-        let c = context!();
-        c.width as usize * 
-        c.height as usize
+    /// the requested tile size as "width x height" (in pixels)
+    pub fn tile_size(&self) -> Option<(u32, u32)> {
+        dimensions(&self.tile_size, MIN_TILE, MAX_TILE)
     }
 
-    pub fn width() -> u32 {
-        context!().width
+    /// show the current context in the size fields
+    pub fn set_size(&mut self, context: &Context) {
+        self.size = format!("{}x{}", context.width, context.height);
+        self.tile_size = format!("{}x{}", context.tile_width, context.tile_height);
     }
 
-    pub fn height() -> u32 {
-        context!().height
+    /// every village, town and city placed on the map
+    pub fn settlements(&self) -> Vec<Location> {
+        self.overrides
+            .iter()
+            .filter(|t| matches!(t.structure,
+                Some(Structure::Village) |
+                Some(Structure::Town) |
+                Some(Structure::City)))
+            .map(|t| t.location)
+            .collect()
     }
+}
+
+/// parse a "width x height" string, rejecting values out of bounds
+fn dimensions(text: &str, min: u32, max: u32) -> Option<(u32, u32)> {
+    let mut parts = text
+        .split(|c| c == 'x' || c == 'X')
+        .map(|p| p.trim().parse::<u32>().ok());
 
-    pub fn tile_width() -> u32 {
-        context!().tile_width
+    match (parts.next()??, parts.next()??, parts.next()) {
+        (w, h, None) if (min..=max).contains(&w) && (min..=max).contains(&h) => Some((w, h)),
+        _ => None,
     }
+}
 
-    pub fn tile_height() -> u32 {
-        context!().tile_height
+impl State {
+    pub fn is_loaded(&self) -> bool {
+        self.flags.get(StateFlag::Loaded)
     }
 
-    pub fn size() -> (i32, i32) {
-        let c = context!();
-        (c.width as i32, c.height as i32)
+    pub fn set_loaded(&mut self) {
+        self.flags.set(StateFlag::Loaded);
     }
 
-    pub fn tile_size() -> (i32, i32) {
-        let c = context!();
-        (c.tile_width as i32, c.tile_height as i32)
+    pub fn set_unloaded(&mut self) {
+        self.flags.unset(StateFlag::Loaded);
     }
 
-    pub fn clone() -> Context {
-        context!().clone()
+    /// true while players are still choosing slots, before
+    /// any turn can be played
+    pub fn in_lobby(&self) -> bool {
+        self.flags.get(StateFlag::Lobby)
     }
 
-    pub fn set_size(w: u32, h: u32) {
-        let mut c = context!();
-        c.width = w;
-        c.height = h;
+    pub fn open_lobby(&mut self) {
+        self.flags.set(StateFlag::Lobby);
     }
 
-    pub fn set_tile_size(w: u32, h: u32) {
-        let mut c = context!();
-        c.tile_width = w;
-        c.tile_height = h;
+    /// leave the lobby and start the first turn
+    pub fn start(&mut self) {
+        self.flags.unset(StateFlag::Lobby);
+        self.timer.reset();
     }
 
-    pub fn init(w: u32, h: u32, tw: u32, th: u32) -> Context {
-        Self::set_size(w, h);
-        Self::set_tile_size(tw, th);
-        Self::clone()
+    /// the size of the map being played
+    pub fn context(&self) -> &Context {
+        self.units.context()
     }
-}
 
-impl State {
-    pub fn is_loaded(&self) -> bool {
-        self.flags.get(StateFlag::Loaded)
+    /// set up the map, calendar and objectives of a scenario,
+    /// leaving the units to be placed once terrain is generated
+    pub fn load(&mut self, context: &mut Context, scenario: Scenario) {
+        self.resize(context, Context {
+            width: scenario.width,
+            height: scenario.height,
+            ..*context
+        });
+
+        self.terrain.seed = format!("{}", scenario.seed);
+        self.terrain.overrides = scenario.tiles.clone();
+        self.factors = scenario.factors();
+        self.calendar = Calendar::from_turn(scenario.turn.unwrap_or(1));
+        self.victory = match scenario.objectives.is_empty() {
+            true => Victory::default(),
+            false => Victory::new(scenario.objectives.clone()),
+        };
+
+        self.pending.clear();
+        self.log.clear();
+        self.scenario = Some(scenario);
+        self.events.send(Action::UpdateTerrain);
     }
 
-    pub fn set_loaded(&mut self) {
-        self.flags.set(StateFlag::Loaded);
+    /// change the size of the map and its tiles, rebuilding
+    /// the tilemap if either is different
+    pub fn resize(&mut self, context: &mut Context, size: Context) -> bool {
+        let changed = *context != size;
+
+        if changed {
+            *context = size;
+            self.terrain.set_size(context);
+            self.events.send(Action::RebuildMap);
+        }
+
+        changed
     }
 
-    pub fn sync(&mut self, data: &UpdateData) {
+    /// bring the game in line with the server, returning true if
+    /// the map was resized (and its units lost) outside the lobby
+    pub fn sync(&mut self, context: &mut Context, data: &UpdateData) -> bool {
         let seed = format!("{}",data.seed);
         let calendar = Calendar::from_turn(data.turn);
         let factors = data.factors.clone();
 
+        self.timer.limit = data.timer;
+        self.victory = data.victory.clone();
+
+        match (data.lobby, self.in_lobby()) {
+            (true, false) => self.open_lobby(),
+            (false, true) => self.start(),
+            _ => (),
+        }
+
+        let resized = self.resize(context, data.context);
+
+        self.scenario = data.scenario.clone();
+
+        if self.terrain.overrides != data.tiles {
+            self.terrain.overrides = data.tiles.clone();
+            self.events.send(Action::UpdateTerrain);
+        }
+
         if self.terrain.seed != seed || self.calendar != calendar || self.factors != factors {
             self.terrain.seed = seed;
             self.calendar = calendar;
             self.factors = factors;
             self.events.send(Action::UpdateTerrain);
         }
+
+        resized && !self.in_lobby()
+    }
+
+    /// start saving turns for a replay from this one
+    pub fn record(&mut self) {
+        let start = self.snapshot();
+        self.recording.begin(self.seed(), self.factors.clone(), self.victory.clone(), start);
+    }
+
+    /// capture the parts of the game that can't be
+    /// rebuilt from an update message
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            context: *self.context(),
+            turn: self.turn(),
+            units: self.units
+                .units()
+                .into_iter()
+                .cloned()
+                .collect(),
+            tiles: self.terrain.overrides.clone(),
+            fortifications: self.fortifications.clone(),
+            pending: self.pending.clone(),
+        }
+    }
+
+    /// a hash of units and works for spotting peers that
+    /// have drifted apart
+    pub fn checksum(&self) -> u64 {
+        snapshot::checksum(self.turn(), self.units.units(), &self.fortifications)
+    }
+
+    /// replace units, terrain edits, works and orders
+    /// with those from a snapshot taken by the server
+    pub fn restore(&mut self, map: &mut Tilemap, snapshot: Snapshot) {
+        let units = snapshot.units
+            .into_iter()
+            .map(|u| u.rebuild(self))
+            .collect();
+
+        self.units.replace(map, units);
+        self.terrain.overrides = snapshot.tiles;
+        self.pending = snapshot.pending;
+        self.calendar = Calendar::from_turn(snapshot.turn);
+        self.fortifications = Fortifications {
+            drawn: self.fortifications.drawn.clone(),
+            ..snapshot.fortifications
+        };
+
+        self.events.send(Action::UpdateUnits);
+        self.events.send(Action::UpdateTerrain);
+        self.events.send(Action::UpdateStructures);
+    }
+
+    /// bring units, works and orders in line with the server,
+    /// leaving units that already match untouched
+    pub fn patch(&mut self, map: &mut Tilemap, data: SyncData) {
+        let units = self.units
+            .units()
+            .into_iter()
+            .cloned()
+            .collect();
+
+        let units = delta::patch(units, &data.units)
+            .into_iter()
+            .map(|u| u.rebuild(self))
+            .collect();
+
+        self.units.replace(map, units);
+        self.pending = data.pending;
+        self.fortifications = Fortifications {
+            drawn: self.fortifications.drawn.clone(),
+            ..data.fortifications
+        };
+
+        self.events.send(Action::UpdateUnits);
+        self.events.send(Action::UpdateStructures);
+    }
+
+    /// the state of each unit, for the server to compare
+    pub fn summaries(&self) -> Vec<Summary> {
+        self.units
+            .units()
+            .into_iter()
+            .map(Summary::of)
+            .collect()
     }
 
     pub fn seed(&self) -> u32 {
         self.terrain.seed()
     }
 
+    /// describe the current game as a scenario, with a slot
+    /// for every player that has units on the map
+    pub fn scenario(&self) -> Scenario {
+        if let Some(scenario) = &self.scenario {
+            return Scenario {
+                tiles: self.terrain.overrides.clone(),
+                ..scenario.clone()
+            };
+        }
+
+        let context = self.context();
+        let mut slots: Vec<Slot> = vec![];
+
+        for unit in self.units.units() {
+            let spec = UnitSpec {
+                name: unit.name(),
+                specialty: *unit.specialty(),
+                soldiers: unit.soldiers().len(),
+                position: unit.position().integers(),
+            };
+
+            match slots.iter_mut().find(|s| s.name == unit.player_name()) {
+                Some(slot) => slot.units.push(spec),
+                None => slots.push(Slot {
+                    name: unit.player_name(),
+                    units: vec![spec],
+                    ..Default::default()
+                }),
+            }
+        }
+
+        Scenario {
+            name: self.terrain.seed.clone(),
+            width: context.width,
+            height: context.height,
+            seed: self.seed(),
+            factors: Some(self.factors()),
+            turn: Some(self.turn()),
+            tiles: self.terrain.overrides.clone(),
+            slots,
+            objectives: self.victory.objectives().clone(),
+            ..Default::default()
+        }
+    }
+
     pub fn turn(&self) -> u32 {
         self.calendar.turn()
     }
@@ -212,11 +443,510 @@ impl State {
         self.factors.clone()
     }
 
-    pub fn end_turn(&mut self) {
-        self.calendar.advance();
+    /// the part of an attack that can go ahead: the units that
+    /// are the player's, haven't attacked this turn and can afford it
+    pub fn affordable(&self, attack: &Attack) -> Attack {
+        let attacking: HashSet<&Id> = self.pending
+            .iter()
+            .flat_map(|a| a.units.iter())
+            .collect();
+
+        let units = self.units
+            .get_all_ids(&attack.units)
+            .into_iter()
+            .filter(|u| u.player_id() == &attack.player)
+            .filter(|u| u.actions() >= ATTACK_COST)
+            .map(|u| *u.id())
+            .filter(|id| !attacking.contains(id))
+            .collect();
+
+        Attack { units, ..attack.clone() }
+    }
+
+    /// true if a player may place the unit, checked by the
+    /// server before it passes on units placed by clients
+    pub fn can_create(&self, player: &PlayerId, unit: &Unit) -> bool {
+        let location = unit.position().integers();
+        let deployable = self.scenario
+            .as_ref()
+            .map(|s| s.can_deploy(player, &location))
+            .unwrap_or(true);
+
+        unit.player_id() == player &&
+        deployable &&
+        self.areas.contains_key(&location) &&
+        !unit.soldiers().is_empty() &&
+        Textures::has_unit(unit.specialty())
+    }
+
+    /// queue the affordable part of an attack for the end of
+    /// the turn, charging every unit that takes part
+    pub fn queue_attack(&mut self, attack: Attack) -> Option<Attack> {
+        let attack = self.affordable(&attack);
+        if attack.units.is_empty() {
+            return None;
+        }
+
+        for id in attack.units.iter() {
+            if let Some(unit) = self.units.find(id) {
+                unit.use_actions(ATTACK_COST);
+            }
+        }
+
+        self.pending.push(attack.clone());
+        Some(attack)
+    }
+
+    /// resolve queued attacks and roll the environment for the
+    /// turn, returning the changes without applying them.
+    pub fn resolve(&mut self) -> Vec<Change> {
+        self.resolve_with(&mut rand::thread_rng())
+    }
+
+    /// resolve the turn with a seeded generator, so that every
+    /// peer given the same seed gets the same changes
+    pub fn resolve_seeded(&mut self, seed: u64) -> Vec<Change> {
+        self.resolve_with(&mut Pcg64::seed_from_u64(seed))
+    }
+
+    fn resolve_with<R: Rng>(&mut self, rng: &mut R) -> Vec<Change> {
+        let (mut movement, mut engaged) = self.orders();
+        self.pending.append(&mut engaged);
+
+        let pending: Vec<Attack> = self.pending.drain(..).collect();
+        let mut changes = vec![];
+
+        // combat is resolved in the order it was ordered
+        for attack in pending.into_iter() {
+            let friends: Vec<&Unit> = self.units
+                .get_all_ids(&attack.units)
+                .into_iter()
+                .filter(|u| u.player_id() == &attack.player)
+                .collect();
+
+            if friends.is_empty() {
+                continue;
+            }
+
+            match attack.fire {
+                Fire::Assault => changes.append(&mut self.assault(&attack, friends)),
+                Fire::Direct => changes.append(&mut self.direct(&attack, friends)),
+                Fire::Indirect => changes.append(&mut self.barrage(&attack, friends, rng)),
+            }
+        }
+
+        // dug in units take fewer losses
+        self.cover(&mut changes);
+
+        // standing orders are carried out by the survivors
+        changes.append(&mut movement);
+
+        // environment is resolved after combat
+        changes.append(&mut self.hazards(rng));
+        changes
+    }
+
+    /// reduce losses for units holding fortified positions
+    fn cover(&self, changes: &mut Vec<Change>) {
+        for change in changes.iter_mut() {
+            if let ChangeType::Health(v) = change.action {
+                let cover = self.fortifications.cover(&change.point.integers());
+                if v < 0 && cover > 0.0 {
+                    let v = (v as f32 * (1.0 - cover)).round() as i16;
+                    change.action = ChangeType::Health(v);
+                }
+            }
+        }
+    }
+
+    /// close combat where both sides take losses
+    fn assault(&self, attack: &Attack, friends: Vec<&Unit>) -> Vec<Change> {
+        let enemies = self.units.targeted_units(&attack.target, &attack.player, &self.teams);
+
+        if enemies.is_empty() {
+            return vec![];
+        }
+
+        let friendly = Units::aggregate(friends);
+        let enemy = Units::aggregate(enemies);
+        friendly.attack(&enemy)
+    }
+
+    /// ranged fire from the units that are in range
+    /// and can see the target
+    fn direct(&self, attack: &Attack, friends: Vec<&Unit>) -> Vec<Change> {
+        let sight = Sight::new(&self.areas);
+        let enemies = self.units.targeted_units(&attack.target, &attack.player, &self.teams);
+
+        let firing: Vec<&Unit> = friends
+            .into_iter()
+            .filter(|u| {
+                let p = u.position();
+                p.distance(attack.target) <= u.specialty().range() &&
+                sight.visible(p, &attack.target)
+            })
+            .collect();
+
+        if firing.is_empty() || enemies.is_empty() {
+            return vec![];
+        }
+
+        let friendly = Units::aggregate(firing);
+        let mut changes = friendly.fire(&Units::aggregate(enemies), 1.0);
+        changes.append(&mut friendly.fired());
+        changes
+    }
+
+    /// artillery fire on a target seen by any friendly unit,
+    /// scattering with inexperience and hitting everything nearby
+    fn barrage<R: Rng>(&self, attack: &Attack, friends: Vec<&Unit>, rng: &mut R) -> Vec<Change> {
+        let sight = Sight::new(&self.areas);
+
+        let firing: Vec<&Unit> = friends
+            .into_iter()
+            .filter(|u| u.specialty().is_indirect())
+            .filter(|u| u.position().distance(attack.target) <= u.specialty().range())
+            .collect();
+
+        // allies spot for each other
+        let spotters: Vec<&Unit> = self.units
+            .units()
+            .into_iter()
+            .filter(|u| !self.teams.is_enemy(u.player_id(), &attack.player))
+            .collect();
+
+        if firing.is_empty() || !sight.spotted(&spotters, &attack.target) {
+            return vec![];
+        }
+
+        let accuracy = firing
+            .iter()
+            .map(|u| u.veterancy().accuracy())
+            .sum::<f32>() / firing.len() as f32;
+
+        // inexperienced crews drift off target
+        let impact = match rng.gen::<f32>() > accuracy {
+            true => attack.target
+                .neighbors(self.context())
+                .choose(rng)
+                .cloned()
+                .unwrap_or(attack.target),
+            false => attack.target,
+        };
+
+        let battery = Units::aggregate(firing);
+        let mut changes = battery.fired();
+
+        // full effect at the point of impact, less on the hexes around it
+        let mut area = vec![(impact, 1.0)];
+        area.extend(impact.neighbors(self.context()).into_iter().map(|p| (p, SPLASH)));
+
+        for (point, scale) in area.into_iter() {
+            let hit = self.units.get_units(&point);
+            if !hit.is_empty() {
+                changes.append(&mut battery.fire(&Units::aggregate(hit), scale));
+            }
+        }
+
+        changes
+    }
+
+    /// plan the current order of every unit, returning the
+    /// movement and any attacks made along the way
+    pub fn orders(&self) -> (Vec<Change>, Vec<Attack>) {
+        let mut planning: HashMap<PlayerId, (Control, HashMap<Point, f32>)> = HashMap::new();
+        let mut reserved: HashMap<Point, usize> = HashMap::new();
+        let mut changes = vec![];
+        let mut attacks = vec![];
+
+        for unit in self.units.units() {
+            let order = match unit.orders().current() {
+                Some(o) => *o,
+                None => continue,
+            };
+
+            let start = *unit.position();
+            let target = match (order, order.target()) {
+                (_, Some(p)) => p,
+                (Order::DigIn, None) => {
+                    changes.append(&mut self.dig(unit));
+                    continue;
+                }
+                (_, None) => {
+                    changes.push(Change::order(unit, start, 0));
+                    continue;
+                }
+            };
+
+            let player = unit.player_id();
+            let (control, impedance) = planning
+                .entry(*player)
+                .or_insert_with(|| {
+                    let control = self.control(player);
+                    let mut impedance = self.impedance_map();
+                    control.weigh(&mut impedance);
+                    (control, impedance)
+                });
+
+            let actions = unit.max_actions();
+            let mut spent = 0;
+            let mut end = start;
+
+            // an aggressive unit already in contact stays to fight
+            let engaged = order.is_aggressive() && control.contains(&start);
+
+            if !engaged {
+                // skip the starting position
+                let path = Pathfinder::new(self.context(), impedance, start, target)
+                    .find_weighted()
+                    .into_iter()
+                    .skip(1)
+                    .collect();
+
+                for (point, left) in control.walk(&start, path, actions) {
+                    // only stop where there is room for the unit
+                    let taken = reserved.get(&point).cloned().unwrap_or(0);
+                    if self.units.space(&point) > taken {
+                        end = point;
+                        spent = actions - left;
+                    }
+                }
+            }
+
+            if order.is_aggressive() {
+                let enemy = end
+                    .neighbors(self.context())
+                    .into_iter()
+                    .find(|p| self.units.has_enemy(p, player, &self.teams));
+
+                if let Some(point) = enemy {
+                    attacks.push(Attack::new(*player, vec![*unit.id()], point));
+                }
+            }
+
+            if end != start {
+                *reserved.entry(end).or_insert(0) += 1;
+            }
+
+            changes.push(Change::order(unit, end, spent));
+        }
+
+        (changes, attacks)
+    }
+
+    /// work on the fortifications at the unit's position, keeping
+    /// the order until they're finished or can't be improved
+    fn dig(&self, unit: &Unit) -> Vec<Change> {
+        let start = *unit.position();
+        let location = start.integers();
+        let remaining = self.fortifications.remaining(&location);
+
+        let rate = match self.areas.get(&location) {
+            Some(a) if remaining > 0 => Fortification::rate(a),
+            _ => 0,
+        };
+
+        let mut changes = vec![];
+        if rate > 0 {
+            changes.push(Change::dig(unit, rate));
+        }
+
+        if rate == 0 || rate >= remaining {
+            changes.push(Change::order(unit, start, 0));
+        }
+
+        changes
+    }
+
+    /// zones controlled by the enemies of a player
+    pub fn control(&self, player: &PlayerId) -> Control {
+        Control::new(self.context(), self.units.units(), player, &self.teams)
+    }
+
+    /// roll accidents and illness for every unit
+    pub fn hazards<R: Rng>(&self, rng: &mut R) -> Vec<Change> {
+        let season = self.calendar.season();
+        let mut changes = vec![];
+
+        for unit in self.units.units() {
+            let area = match self.areas.get(&unit.position().integers()) {
+                Some(a) => a,
+                None => continue,
+            };
+
+            // roll for the day and night stages
+            for time in [Time::Day, Time::Night].iter() {
+                let hazards = Hazards::new(area, season.clone(), *time);
+                for (soldier, condition) in unit.expose(&hazards, rng) {
+                    changes.push(Change::afflict(unit, soldier, condition));
+                }
+            }
+        }
+
+        changes
+    }
+
+    /// apply the results of a turn and move to the given turn
+    pub fn advance(&mut self, map: &mut Tilemap, turn: u32, changes: &Vec<Change>) {
+        self.report = Report::new(turn);
+
+        // attacks queued by a peer were resolved by the server
+        self.pending.clear();
+
         for unit in self.units.units_mut() {
-            unit.reset_actions()
+            unit.recover();
+        }
+
+        // record incidents before they're applied
+        for change in changes.iter() {
+            if let ChangeType::Afflict(i, condition) = change.action {
+                if let Some(unit) = self.units.find(&change.id) {
+                    if let Some(soldier) = unit.soldiers().get(i) {
+                        self.report.add(Record::Incident {
+                            unit: *unit.id(),
+                            unit_name: unit.name(),
+                            soldier: soldier.name(),
+                            condition,
+                        });
+                    }
+                }
+            }
+        }
+
+        // works are dug where the units started the turn
+        for change in changes.iter() {
+            if let ChangeType::Dig(amount) = change.action {
+                if let Some(unit) = self.units.find(&change.id) {
+                    let owner = *unit.player_id();
+                    self.fortifications.dig(change.point.integers(), owner, amount);
+                }
+            }
+        }
+
+        let owners: HashMap<_, _> = self.units
+            .units()
+            .into_iter()
+            .map(|u| (*u.id(), (*u.player_id(), u.player_name())))
+            .collect();
+
+        // damage taken by each unit, in the order it was first hit
+        let mut damage: Vec<(Id, u32)> = vec![];
+        for change in changes.iter() {
+            if let ChangeType::Health(v) = change.action {
+                if v >= 0 {
+                    continue;
+                }
+                match damage.iter_mut().find(|(i, _)| *i == change.id) {
+                    Some((_, d)) => *d += v.unsigned_abs() as u32,
+                    None => damage.push((change.id, v.unsigned_abs() as u32)),
+                }
+            }
+        }
+
+        for (id, amount) in damage.iter() {
+            if let Some(unit) = self.units.find(id) {
+                self.report.add(Record::Combat {
+                    unit: *id,
+                    unit_name: unit.name(),
+                    player_name: unit.player_name(),
+                    damage: *amount,
+                });
+            }
+        }
+
+        let before: Vec<(Id, String)> = self.units
+            .units()
+            .into_iter()
+            .map(|u| (*u.id(), u.name()))
+            .collect();
+
+        // soldiers moved between units aren't lost
+        let losses = self.units.execute(map, changes);
+        self.victory.record(&owners, changes, &self.units.units());
+
+        for (id, name) in before.into_iter() {
+            if let Some(lost) = losses.get(&id) {
+                self.report.add(Record::Casualties {
+                    unit: id,
+                    unit_name: name.clone(),
+                    lost: *lost,
+                });
+            }
+
+            if self.units.find(&id).is_none() {
+                let player_name = owners
+                    .get(&id)
+                    .map(|(_, n)| n.clone())
+                    .unwrap_or_default();
+                self.report.add(Record::Destroyed {
+                    unit: id,
+                    unit_name: name,
+                    player_name,
+                });
+            }
+        }
+
+        for unit in self.units.units_mut() {
+            unit.train();
+            unit.reset_actions();
+        }
+
+        // actions spent on standing orders come out of the new turn
+        for change in changes.iter() {
+            match change.action {
+                ChangeType::Order(_, cost) => {
+                    if let Some(unit) = self.units.find(&change.id) {
+                        unit.use_actions(cost);
+                    }
+                }
+                ChangeType::Dig(_) => {
+                    if let Some(unit) = self.units.find(&change.id) {
+                        let actions = unit.max_actions();
+                        unit.use_actions(actions);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // works left without a garrison fall apart
+        let occupied: HashSet<Location> = self.units
+            .units()
+            .into_iter()
+            .map(|u| u.position().integers())
+            .collect();
+        self.fortifications.decay(&occupied);
+
+        let season = self.calendar.season();
+        self.calendar = Calendar::from_turn(turn);
+        self.timer.reset();
+
+        if self.calendar.season() != season {
+            self.report.add(Record::Weather {
+                conditions: format!("{} has begun", self.calendar.season()),
+            });
+        }
+
+        self.log.push(self.report.clone());
+
+        if self.recording.is_recording() {
+            let next = self.snapshot();
+            let checksum = self.checksum();
+            let victory = self.victory.clone();
+            self.recording.turn(turn, changes, checksum, next, victory);
         }
+
+        // veterancy and seasons may have changed
+        self.events.send(Action::UpdateUnits);
+        self.events.send(Action::UpdateTerrain);
+        self.events.send(Action::UpdateStructures);
+    }
+
+    /// check the victory conditions after a turn, returning
+    /// true if the game has just ended
+    pub fn judge(&mut self) -> bool {
+        let turn = self.turn();
+        self.victory.evaluate(turn, &self.units.units(), &self.teams)
     }
 
     pub fn impedance_map(&self) -> HashMap<Point, f32> {
@@ -307,3 +1037,71 @@ impl State {
         s / 100.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_is_parsed_within_bounds() {
+        let mut terrain = Terrain::default();
+
+        terrain.size = "40x 25".into();
+        assert_eq!(terrain.size(), Some((40, 25)));
+
+        terrain.size = "5x25".into();
+        assert_eq!(terrain.size(), None);
+
+        terrain.size = "40x25x2".into();
+        assert_eq!(terrain.size(), None);
+
+        terrain.tile_size = "175X200".into();
+        assert_eq!(terrain.tile_size(), Some((175, 200)));
+    }
+
+    #[test]
+    fn test_units_attack_once_per_turn() {
+        use crate::generation::{Soldier, Specialty};
+
+        let mut state = State::default();
+        state.units = Map::new(&Context::default());
+
+        let player = PlayerId::new();
+        let mut unit = Unit::new(player).with_position((1, 1).into());
+        unit.attach(vec![Soldier::new(&Specialty::Infantry); 3]);
+        let id = *unit.id();
+        state.units.add((1, 1).into(), unit);
+
+        let target = Point::new(2, 1);
+        assert!(state.queue_attack(Attack::new(PlayerId::new(), vec![id], target)).is_none());
+        assert!(state.queue_attack(Attack::new(player, vec![id], target)).is_some());
+        assert_eq!(state.units.find(&id).map(|u| u.actions()), Some(100 - ATTACK_COST));
+        assert!(state.queue_attack(Attack::new(player, vec![id], target)).is_none());
+        assert_eq!(state.pending.len(), 1);
+    }
+
+    #[test]
+    fn test_orders_off_the_map_are_refused() {
+        use crate::generation::{Soldier, Specialty};
+
+        let mut state = State::default();
+        state.units = Map::new(&Context::default());
+
+        let player = PlayerId::new();
+        let mut unit = Unit::new(player).with_position((1, 1).into());
+        unit.attach(vec![Soldier::new(&Specialty::Infantry); 3]);
+        let id = *unit.id();
+        state.units.add((1, 1).into(), unit);
+
+        let orders = vec![(id, vec![Order::Move(Point::new(1000, 1000))])];
+        state.units.set_orders(&player, &orders);
+        assert!(state.units.find(&id).unwrap().orders().current().is_none());
+
+        let orders = vec![(id, vec![Order::Move(Point::new(3, 1))])];
+        state.units.set_orders(&player, &orders);
+        assert!(state.units.find(&id).unwrap().orders().current().is_some());
+
+        let (changes, _) = state.orders();
+        assert_eq!(changes.len(), 1);
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use crate::generation::{Id, PlayerId};
+use crate::objects::Point;
+
+/// An attack that is resolved at the end of the turn
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attack {
+    /// the player that ordered the attack
+    pub player: PlayerId,
+
+    /// the attacking units
+    pub units: Vec<Id>,
+
+    /// the position being attacked
+    pub target: Point,
+
+    /// how the attack is delivered
+    pub fire: Fire,
+}
+
+/// The way an attack reaches its target
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum Fire {
+    Assault,  // close combat, both sides take losses
+    Direct,   // ranged fire at a target in sight
+    Indirect, // artillery fire directed by spotters
+}
+
+/// Optional time limit for each turn
+#[derive(Default, Debug, Clone)]
+pub struct TurnTimer {
+    /// seconds allowed per turn (0 is unlimited)
+    pub limit: u32,
+
+    /// seconds since the turn started
+    elapsed: f32,
+}
+
+impl Attack {
+    pub fn new(player: PlayerId, units: Vec<Id>, target: Point) -> Self {
+        Self { player, units, target, fire: Fire::Assault }
+    }
+
+    pub fn with_fire(mut self, fire: Fire) -> Self {
+        self.fire = fire;
+        self
+    }
+}
+
+impl TurnTimer {
+    pub fn tick(&mut self, seconds: f32) {
+        self.elapsed += seconds;
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.limit > 0
+    }
+
+    pub fn expired(&self) -> bool {
+        self.is_limited() && self.elapsed >= self.limit as f32
+    }
+
+    /// seconds left in the turn
+    pub fn remaining(&self) -> u32 {
+        (self.limit as f32 - self.elapsed).max(0.0).ceil() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_never_expires() {
+        let mut timer = TurnTimer::default();
+        timer.tick(10000.0);
+        assert!(!timer.expired());
+    }
+
+    #[test]
+    fn test_limited_expires() {
+        let mut timer = TurnTimer { limit: 30, ..Default::default() };
+        timer.tick(29.5);
+        assert!(!timer.expired());
+        assert_eq!(timer.remaining(), 1);
+        timer.tick(0.5);
+        assert!(timer.expired());
+        timer.reset();
+        assert!(!timer.expired());
+    }
+}
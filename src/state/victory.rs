@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::generation::{Change, ChangeType, Id, PlayerId, Unit};
+use crate::objects::Location;
+use crate::state::traits::*;
+use crate::state::{Calendar, Teams};
+
+/// A way for a player to win the game
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Objective {
+    /// be the last player with units on the map
+    Eliminate,
+
+    /// hold every location for a number of turns in a row
+    Hold { points: Vec<Location>, turns: u32 },
+
+    /// control a number of settlements at once
+    Settlements { points: Vec<Location>, count: usize },
+
+    /// keep a player's units alive until a turn
+    Survive { player: PlayerId, turn: u32 },
+}
+
+/// How the game ended
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Outcome {
+    pub winners: Vec<PlayerId>,
+    pub turn: u32,
+    pub reason: String,
+}
+
+/// Totals for a single player over the whole game
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Statistics {
+    pub name: String,
+    pub standing: u32,
+    pub lost: u32,
+    pub damage: u32,
+    pub experience: u32,
+    pub incidents: u32,
+    pub held: u32,
+}
+
+/// Victory conditions for the game and progress towards them
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Victory {
+    objectives: Vec<Objective>,
+    holding: HashMap<PlayerId, u32>,
+    stats: HashMap<PlayerId, Statistics>,
+    outcome: Option<Outcome>,
+}
+
+impl Default for Victory {
+    fn default() -> Self {
+        Self {
+            objectives: vec![Objective::Eliminate],
+            holding: HashMap::new(),
+            stats: HashMap::new(),
+            outcome: None,
+        }
+    }
+}
+
+impl Victory {
+    pub fn new(objectives: Vec<Objective>) -> Self {
+        Self {
+            objectives,
+            ..Default::default()
+        }
+    }
+
+    pub fn objectives(&self) -> &Vec<Objective> {
+        &self.objectives
+    }
+
+    pub fn objectives_mut(&mut self) -> &mut Vec<Objective> {
+        &mut self.objectives
+    }
+
+    pub fn stats(&self) -> Vec<(&PlayerId, &Statistics)> {
+        let mut stats: Vec<_> = self.stats.iter().collect();
+        stats.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+        stats
+    }
+
+    pub fn outcome(&self) -> Option<&Outcome> {
+        self.outcome.as_ref()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    /// name of a player that has fielded units
+    pub fn name(&self, player: &PlayerId) -> String {
+        self.stats
+            .get(player)
+            .map(|s| s.name.clone())
+            .unwrap_or(format!("{}", player))
+    }
+
+    /// add the results of a turn to the statistics, given the
+    /// owners of all units before the turn and those left after
+    pub fn record(&mut self, owners: &HashMap<Id, (PlayerId, String)>, changes: &Vec<Change>, remaining: &Vec<&Unit>) {
+        for (player, name) in owners.values() {
+            self.stats
+                .entry(*player)
+                .or_insert_with(Statistics::default)
+                .name = name.clone();
+        }
+
+        for change in changes.iter() {
+            let stats = match owners
+                .get(&change.id)
+                .and_then(|(p, _)| self.stats.get_mut(p))
+            {
+                Some(s) => s,
+                None => continue,
+            };
+
+            match change.action {
+                ChangeType::Health(v) if v < 0 => stats.damage += (-v) as u32,
+                ChangeType::Experience(v) if v > 0 => stats.experience += v as u32,
+                ChangeType::Afflict(..) => stats.incidents += 1,
+                _ => (),
+            }
+        }
+
+        let alive: HashSet<&Id> = remaining.iter().map(|u| u.id()).collect();
+
+        for (id, (player, _)) in owners.iter() {
+            if !alive.contains(id) {
+                if let Some(s) = self.stats.get_mut(player) {
+                    s.lost += 1;
+                }
+            }
+        }
+
+        for stats in self.stats.values_mut() {
+            stats.standing = 0;
+        }
+
+        for unit in remaining.iter() {
+            let stats = self.stats
+                .entry(*unit.player_id())
+                .or_insert_with(Statistics::default);
+            stats.name = unit.player_name();
+            stats.standing += 1;
+        }
+    }
+
+    /// check every objective at the end of a turn, returning
+    /// true if the game has just been decided
+    pub fn evaluate(&mut self, turn: u32, units: &Vec<&Unit>, teams: &Teams) -> bool {
+        if self.is_over() {
+            return false;
+        }
+
+        let players: Vec<PlayerId> = self.stats.keys().cloned().collect();
+
+        let standing: HashSet<PlayerId> = units
+            .iter()
+            .map(|u| *u.player_id())
+            .collect();
+
+        for objective in self.objectives.clone().iter() {
+            let result = match objective {
+                Objective::Eliminate => {
+                    let rivals = players.iter().any(|a| players.iter().any(|b| teams.is_enemy(a, b)));
+                    let fighting = standing.iter().any(|a| standing.iter().any(|b| teams.is_enemy(a, b)));
+                    if !rivals || fighting {
+                        None
+                    } else {
+                        // allies of the players left standing share the win
+                        let winners: Vec<PlayerId> = players
+                            .iter()
+                            .filter(|p| standing.iter().any(|s| !teams.is_enemy(p, s)))
+                            .cloned()
+                            .collect();
+                        Some((winners, "every enemy was eliminated".into()))
+                    }
+                }
+                Objective::Hold { points, turns } => {
+                    let mut winners = vec![];
+                    for player in players.iter() {
+                        let held = !points.is_empty() && points
+                            .iter()
+                            .all(|p| holds(units, p, player, teams));
+
+                        let count = self.holding.entry(*player).or_insert(0);
+                        *count = if held { *count + 1 } else { 0 };
+
+                        if held {
+                            if let Some(s) = self.stats.get_mut(player) {
+                                s.held += 1;
+                            }
+                        }
+
+                        if *count >= *turns {
+                            winners.push(*player);
+                        }
+                    }
+                    match winners.is_empty() {
+                        true => None,
+                        false => Some((winners, format!("held the objectives for {} turns", turns))),
+                    }
+                }
+                Objective::Settlements { points, count } => {
+                    let winners: Vec<PlayerId> = players
+                        .iter()
+                        .filter(|player| points
+                            .iter()
+                            .filter(|p| holds(units, p, player, teams))
+                            .count() >= *count)
+                        .cloned()
+                        .collect();
+                    match winners.is_empty() {
+                        true => None,
+                        false => Some((winners, format!("controlled {} settlements", count))),
+                    }
+                }
+                Objective::Survive { player, turn: until } => {
+                    if turn < *until || !standing.contains(player) {
+                        None
+                    } else {
+                        let date = Calendar::from_turn(*until);
+                        Some((vec![*player], format!("survived until {}", date)))
+                    }
+                }
+            };
+
+            if let Some((winners, reason)) = result {
+                self.outcome = Some(Outcome { winners, turn, reason });
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+/// true if a player or their allies have units on a
+/// location and none of their enemies do
+fn holds(units: &Vec<&Unit>, location: &Location, player: &PlayerId, teams: &Teams) -> bool {
+    let mut present = units
+        .iter()
+        .filter(|u| u.position().integers() == *location)
+        .peekable();
+
+    present.peek().is_some() && present.all(|u| !teams.is_enemy(u.player_id(), player))
+}
+
+impl Display for Objective {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Objective::Eliminate => write!(f, "Eliminate all enemies"),
+            Objective::Hold { points, turns } => {
+                write!(f, "Hold {} objectives for {} turns", points.len(), turns)
+            }
+            Objective::Settlements { points, count } => {
+                write!(f, "Control {} of {} settlements", count, points.len())
+            }
+            Objective::Survive { turn, .. } => {
+                write!(f, "Survive until {}", Calendar::from_turn(*turn))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::Point;
+
+    fn owners(units: &Vec<&Unit>) -> HashMap<Id, (PlayerId, String)> {
+        units
+            .iter()
+            .map(|u| (*u.id(), (*u.player_id(), u.player_name())))
+            .collect()
+    }
+
+    #[test]
+    fn test_last_player_standing_wins() {
+        let a = Unit::new(PlayerId::new()).with_position(Point::new(0, 0));
+        let b = Unit::new(PlayerId::new()).with_position(Point::new(5, 0));
+
+        let mut victory = Victory::default();
+        victory.record(&owners(&vec![&a, &b]), &vec![], &vec![&a, &b]);
+        assert!(!victory.evaluate(1, &vec![&a, &b], &Teams::default()));
+
+        victory.record(&owners(&vec![&a, &b]), &vec![], &vec![&a]);
+        assert!(victory.evaluate(2, &vec![&a], &Teams::default()));
+        assert_eq!(victory.outcome().unwrap().winners, vec![*a.player_id()]);
+        assert_eq!(victory.stats.get(b.player_id()).unwrap().lost, 1);
+    }
+
+    #[test]
+    fn test_allies_win_together() {
+        let (a, b, c) = (PlayerId::new(), PlayerId::new(), PlayerId::new());
+        let teams = Teams::new(vec![(a, 1), (b, 1), (c, 2)].into_iter().collect());
+        let x = Unit::new(a).with_position(Point::new(0, 0));
+        let y = Unit::new(b).with_position(Point::new(1, 0));
+        let z = Unit::new(c).with_position(Point::new(5, 0));
+
+        let mut victory = Victory::default();
+        victory.record(&owners(&vec![&x, &y, &z]), &vec![], &vec![&x, &y, &z]);
+        assert!(!victory.evaluate(1, &vec![&x, &y, &z], &teams));
+
+        victory.record(&owners(&vec![&x, &y, &z]), &vec![], &vec![&x]);
+        assert!(victory.evaluate(2, &vec![&x], &teams));
+
+        let winners = &victory.outcome().unwrap().winners;
+        assert!(winners.contains(&a) && winners.contains(&b));
+        assert!(!winners.contains(&c));
+    }
+
+    #[test]
+    fn test_objectives_must_be_held() {
+        let point = Point::new(2, 2);
+        let a = Unit::new(PlayerId::new()).with_position(point);
+        let b = Unit::new(PlayerId::new()).with_position(Point::new(5, 0));
+        let units = vec![&a, &b];
+
+        let mut victory = Victory::new(vec![Objective::Hold { points: vec![point.integers()], turns: 2 }]);
+        victory.record(&owners(&units), &vec![], &units);
+
+        assert!(!victory.evaluate(1, &units, &Teams::default()));
+        assert!(victory.evaluate(2, &units, &Teams::default()));
+        assert_eq!(victory.outcome().unwrap().winners, vec![*a.player_id()]);
+    }
+}
@@ -18,6 +18,9 @@ pub struct Camera {
 
     /// the button that triggers camera panning
     pub button: MouseButton,
+
+    /// a point to center on in the next frame
+    pub target: Option<Vec2>,
 }
 
 impl Default for Camera {
@@ -28,6 +31,7 @@ impl Default for Camera {
             zoom_speed: 0.25,
             position: Vec2::ZERO,
             button: MouseButton::Right,
+            target: None,
         }
     }
 }
@@ -50,6 +54,11 @@ fn camera_movement_system(
                 camera.position = p;
             }
 
+            if let Some(target) = camera.target.take() {
+                transform.translation.x = target.x;
+                transform.translation.y = target.y;
+            }
+
             if scroll_delta != 0.0 {
                 camera.scale += scroll_delta * camera.zoom_speed;
                 camera.scale = camera.scale.max(1.25);
@@ -0,0 +1,71 @@
+use bevy::input::keyboard::KeyCode;
+use bevy::input::mouse::MouseButton;
+use bevy::prelude::*;
+
+use crate::objects::Location;
+use crate::state::{Action, Editor, State};
+use crate::systems::network::NetworkState;
+use crate::systems::selection::Selection;
+
+pub struct EditorPlugin;
+
+/// Paint the hovered tiles while the selection button is held
+/// and undo or redo strokes from the keyboard.
+fn editor_paint_system(
+    mut state: ResMut<State>,
+    mut editor: ResMut<Editor>,
+    network: Res<NetworkState>,
+    inputs: Res<Input<MouseButton>>,
+    keyboard: Res<Input<KeyCode>>,
+    mut last: Local<Option<Location>>,
+    query: Query<&Selection>,
+) {
+    if !state.is_loaded() || !editor.enabled || network.is_client() {
+        return;
+    }
+
+    let selection = query.single().expect("Need selection");
+    let control = keyboard.pressed(KeyCode::LControl) || keyboard.pressed(KeyCode::RControl);
+
+    let mut changed = false;
+
+    if control && keyboard.just_pressed(KeyCode::Z) {
+        changed |= editor.undo(&mut state.terrain.overrides);
+    }
+
+    if control && keyboard.just_pressed(KeyCode::Y) {
+        changed |= editor.redo(&mut state.terrain.overrides);
+    }
+
+    if inputs.just_released(selection.button) {
+        *last = None;
+    }
+
+    if selection.hovering && inputs.pressed(selection.button) {
+        let hovered = selection.hovered;
+
+        // every stroke can be undone as a whole
+        if inputs.just_pressed(selection.button) {
+            editor.begin(&state.terrain.overrides);
+        }
+
+        // only paint each tile once while dragging over it
+        if *last != Some(hovered) {
+            let mut overrides = std::mem::take(&mut state.terrain.overrides);
+            changed |= editor.paint(&mut overrides, &state.areas, hovered);
+            state.terrain.overrides = overrides;
+            *last = Some(hovered);
+        }
+    }
+
+    if changed {
+        state.events.send(Action::UpdateTerrain);
+    }
+}
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Editor>()
+           .add_system(editor_paint_system.system());
+    }
+}
@@ -7,14 +7,26 @@ use bevy::{
 use bevy_tilemap::prelude::*;
 use crate::systems::network::NetworkState;
 use crate::generation::{Area, Generator, LayerUse};
+use crate::objects::{Fortifications, Map};
 use crate::state::{traits::*, Action, Context, State};
 use crate::resources::Label;
 
 pub struct GeneratorPlugin;
 
+/// The map is rebuilt in stages ahead of the other systems, so
+/// that the old tilemap is gone before a new one is spawned and
+/// the new one exists before terrain is generated onto it
+#[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
+enum GeneratorStage {
+    Rebuild,
+    Initialize,
+    Configure,
+}
+
 fn generate(state: &mut State, width: i32, height: i32) -> Vec<Area> {
     let gen = &mut state.generator;
     let tex = &state.textures;
+    let overrides = &state.terrain.overrides;
 
     let mut results = vec![];
 
@@ -47,6 +59,12 @@ fn generate(state: &mut State, width: i32, height: i32) -> Vec<Area> {
                 .with_impedance(impedance)
                 .build();
 
+            // hand-edited tiles replace generated values
+            let area = match overrides.iter().find(|o| o.location == location) {
+                Some(o) => o.apply(area, tex),
+                None => area,
+            };
+
             results.push(area);
         }
     }
@@ -57,6 +75,7 @@ fn generate(state: &mut State, width: i32, height: i32) -> Vec<Area> {
 fn generator_initialize_system(
     mut commands: Commands,
     mut state: ResMut<State>,
+    context: Res<Context>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     mut textures: ResMut<Assets<Texture>>,
     assets: Res<AssetServer>,
@@ -74,7 +93,6 @@ fn generator_initialize_system(
             let texture_atlas = texture_atlas_builder.finish(&mut textures).unwrap();
             let atlas_handle = texture_atlases.add(texture_atlas);
 
-            let context = Context::clone();
             let width = context.width;
             let height = context.height;
             let tile_height = context.tile_height;
@@ -114,11 +132,40 @@ fn generator_initialize_system(
                 .insert_bundle(tilemap_components)
                 .insert(Timer::from_seconds(0.075, true));
 
+            // the tilemap is spawned at the end of this stage
             state.textures.loaded = true;
+            state.events.send(Action::UpdateTerrain);
         }
     }
 }
 
+/// Throw away the tilemap so that it's built again with
+/// the current map size.
+fn generator_rebuild_system(
+    mut commands: Commands,
+    mut state: ResMut<State>,
+    context: Res<Context>,
+    map_query: Query<Entity, With<Tilemap>>,
+) {
+    if !state.events.receive(Action::RebuildMap) {
+        return;
+    }
+
+    for entity in map_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    // everything placed on the old map goes with it
+    state.set_unloaded();
+    state.tiles.clear();
+    state.units = Map::new(&context);
+    state.fortifications = Fortifications::new();
+    state.textures.loaded = false;
+
+    // terrain is generated once the new tilemap is spawned
+    state.events.clear(Action::RebuildMap);
+}
+
 fn generator_configure_system(
     mut state: ResMut<State>,
     mut network: ResMut<NetworkState>,
@@ -126,6 +173,11 @@ fn generator_configure_system(
     asset_server: Res<AssetServer>,
     mut map_query: Query<&mut Tilemap>,
 ) {
+    // a tilemap waiting to be rebuilt is never generated onto
+    if state.events.receive(Action::RebuildMap) || !state.textures.loaded {
+        return;
+    }
+
     // check if update is requested for terrain generation
     if state.events.receive(Action::UpdateTerrain) {
         if let Ok(mut map) = map_query.single_mut() {
@@ -184,8 +236,9 @@ fn generator_configure_system(
             // set loaded flag
             state.set_loaded();
 
-            // update overlay
+            // update overlay and edited structures
             state.events.send(Action::UpdateOverlay);
+            state.events.send(Action::UpdateStructures);
             state.events.clear(Action::UpdateTerrain);
 
             // update selection marker
@@ -215,7 +268,11 @@ fn generator_configure_system(
 
 impl Plugin for GeneratorPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system(generator_initialize_system.system())
-            .add_system(generator_configure_system.system());
+        app.add_stage_before(CoreStage::Update, GeneratorStage::Rebuild, SystemStage::parallel())
+            .add_stage_after(GeneratorStage::Rebuild, GeneratorStage::Initialize, SystemStage::parallel())
+            .add_stage_after(GeneratorStage::Initialize, GeneratorStage::Configure, SystemStage::parallel())
+            .add_system_to_stage(GeneratorStage::Rebuild, generator_rebuild_system.system())
+            .add_system_to_stage(GeneratorStage::Initialize, generator_initialize_system.system())
+            .add_system_to_stage(GeneratorStage::Configure, generator_configure_system.system());
     }
 }
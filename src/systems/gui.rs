@@ -1,10 +1,18 @@
-use crate::generation::{Biome, Soil};
-use crate::state::traits::HasId;
-use crate::state::{Action, State, Flags};
-use crate::systems::selection::Selection;
-use crate::systems::network::NetworkState;
+use crate::generation::{Biome, Foliage, Soil, Structure};
+use crate::state::traits::{HasId, HasPosition};
+use crate::state::{Action, Category, Context, State, Editor, Flags, Mail, Objective, Replay, Scenario, Side, Tool, SLOTS};
+use crate::objects::{Location, Point};
+use crate::systems::selection::{Selection, issue_orders};
+use crate::systems::camera;
+use crate::systems::network::{NetworkState, View};
+use crate::systems::opponent::OpponentState;
+use crate::systems::mail::Mailbox;
+use crate::systems::replay::Playback;
+use crate::behavior::Difficulty;
 use crate::networking::messages::*;
-use crate::generation::{PlayerId,Id,Specialty};
+use crate::networking::chat::{self, Channel, Segment};
+use crate::networking::version::Version;
+use crate::generation::{PlayerId,Id,Specialty,Change,Veterancy,Order};
 
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContext, EguiPlugin, EguiSettings};
@@ -15,11 +23,17 @@ pub enum GuiFlag {
     UnitWindow,
     ChatWindow,
     InfoWindow,
+    ReportWindow,
+    VictoryWindow,
+    EditorWindow,
+    ReplayWindow,
+    MailWindow,
 }
 
 pub struct Message {
     player:  String,
     content: String,
+    channel: Channel,
 }
 
 pub struct GuiState {
@@ -27,8 +41,20 @@ pub struct GuiState {
     ip: String,
     port: u16,
     name: String,
+    password: String,
     unit_name: String,
     message: String,
+    channel: Channel,
+    turns: u32,
+    settlements: usize,
+    survivor: Option<PlayerId>,
+    scenario: String,
+    replay: String,
+    mail: String,
+    seats: String,
+    log: String,
+    categories: Vec<Category>,
+    all_turns: bool,
     pub history: Vec<Message>,
 }
 
@@ -39,8 +65,20 @@ impl Default for GuiState {
             ip: "127.0.0.1".into(),
             port: 8080,
             name: "None".into(),
+            password: "".into(),
             unit_name: "".into(),
             message: "".into(),
+            channel: Channel::All,
+            turns: 10,
+            settlements: 1,
+            survivor: None,
+            scenario: "assets/scenarios/example.ron".into(),
+            replay: "replay.json".into(),
+            mail: "turn.json".into(),
+            seats: "".into(),
+            log: "report.txt".into(),
+            categories: Category::all().to_vec(),
+            all_turns: false,
             history: vec![],
         }
     }
@@ -48,11 +86,21 @@ impl Default for GuiState {
 
 impl GuiState {
     pub fn add_message(&mut self, player: String, content: String) {
+        self.add_chat(player, content, Channel::All);
+    }
+
+    pub fn add_chat(&mut self, player: String, content: String, channel: Channel) {
         self.history.push(Message {
             player,
             content,
+            channel,
         });
     }
+
+    /// note something that happened in the game
+    pub fn add_system(&mut self, content: String) {
+        self.add_chat("".into(), content, Channel::System);
+    }
 }
 
 pub struct GuiPlugin;
@@ -100,10 +148,16 @@ fn gui_configure_system(
 fn gui_display_system(
     mut gui: ResMut<GuiState>,
     mut network: ResMut<NetworkState>,
+    mut opponents: ResMut<OpponentState>,
+    mut playback: ResMut<Playback>,
+    mut mailbox: ResMut<Mailbox>,
+    mut editor: ResMut<Editor>,
     mut state: ResMut<State>,
+    mut dimensions: ResMut<Context>,
     windows: Res<Windows>,
     keyboard: Res<Input<KeyCode>>,
     mut query: Query<&mut Selection>,
+    mut cameras: Query<&mut camera::Camera>,
     context: ResMut<EguiContext>,
     _assets: Res<AssetServer>,
 ) {
@@ -127,6 +181,49 @@ fn gui_display_system(
             ui.text_edit_singleline(&mut state.terrain.seed);
         });
 
+        if state.terrain.size.is_empty() || state.terrain.tile_size.is_empty() {
+            state.terrain.set_size(&dimensions);
+        }
+
+        // the map size is chosen by whoever runs the game
+        if network.is_client() {
+            ui.label(format!("Size: {} ({}px)", state.terrain.size, state.terrain.tile_size));
+        }
+        else {
+            ui.horizontal(|ui| {
+                ui.label("Size: ");
+                ui.text_edit_singleline(&mut state.terrain.size);
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Tiles: ");
+                ui.text_edit_singleline(&mut state.terrain.tile_size);
+            });
+        }
+
+        // scenarios are chosen by whoever runs the game
+        if !network.is_client() {
+            ui.horizontal(|ui| {
+                ui.label("Scenario: ");
+                ui.text_edit_singleline(&mut gui.scenario);
+            });
+
+            ui.horizontal(|ui| {
+                if ui.button("Load").clicked() {
+                    match Scenario::load(&gui.scenario) {
+                        Ok(scenario) => state.load(&mut dimensions, scenario),
+                        Err(e) => gui.add_message("Scenario".into(), e.to_string()),
+                    }
+                }
+
+                if ui.button("Save").clicked() {
+                    if let Err(e) = state.scenario().save(&gui.scenario) {
+                        gui.add_message("Scenario".into(), e.to_string());
+                    }
+                }
+            });
+        }
+
         ui.add(egui::Slider::new(&mut state.factors.elevation, 0..=100).text("Elevation"));
         ui.add(
             egui::Slider::new(&mut state.factors.temperature, 0..=100).text("Temperature"),
@@ -160,12 +257,35 @@ fn gui_display_system(
 
         ui.horizontal(|ui| {
             if ui.button("Update").clicked() {
-                state.events.send(Action::UpdateTerrain);
+                // a new size clears the board, so it's only allowed before the game starts
+                let setup = match network.is_server() {
+                    true => state.in_lobby(),
+                    false => state.units.units().is_empty(),
+                };
+
+                match (state.terrain.size(), state.terrain.tile_size()) {
+                    (Some((w, h)), Some((tw, th))) if !network.is_client() => {
+                        let size = Context::new(w, h, tw, th);
+                        if *dimensions == size {
+                            state.events.send(Action::UpdateTerrain);
+                        }
+                        else if setup {
+                            // a new size rebuilds the map, which regenerates it
+                            state.resize(&mut dimensions, size);
+                        }
+                        else {
+                            gui.add_message("Map".into(), "The map can't be resized once the game has started".into());
+                        }
+                    }
+                    (Some(_), Some(_)) => {
+                        state.events.send(Action::UpdateTerrain);
+                    }
+                    _ => gui.add_message("Map".into(), "Size must look like 30x30".into()),
+                }
             }
 
-            if ui.button("End Turn").clicked() {
-                state.end_turn();
-                state.events.send(Action::UpdateTerrain);
+            if !network.is_spectating() && ui.button("End Turn").clicked() {
+                state.events.send(Action::EndTurn);
             }
 
             if ui.button("Units").clicked() {
@@ -181,10 +301,106 @@ fn gui_display_system(
             if ui.button("Chat").clicked() {
                 gui.flags.toggle(GuiFlag::ChatWindow);
             }
+
+            if ui.button("Report").clicked() {
+                gui.flags.toggle(GuiFlag::ReportWindow);
+            }
+
+            if ui.button("Objectives").clicked() {
+                gui.flags.toggle(GuiFlag::VictoryWindow);
+            }
+
+            if ui.button("Replay").clicked() {
+                gui.flags.toggle(GuiFlag::ReplayWindow);
+            }
+
+            if network.is_offline() && ui.button("Mail").clicked() {
+                gui.flags.toggle(GuiFlag::MailWindow);
+            }
+
+            // the map is edited by whoever runs the game
+            if !network.is_client() && ui.button("Editor").clicked() {
+                gui.flags.toggle(GuiFlag::EditorWindow);
+            }
         });
 
         ui.label(format!("{}", state.calendar));
 
+        if state.timer.is_limited() {
+            ui.label(format!("Time left: {}s", state.timer.remaining()));
+        }
+
+        if !network.is_offline() {
+            let mut removed = None;
+            let mut view = None;
+
+            let (spectators, players): (Vec<PlayerData>, Vec<PlayerData>) = network
+                .players()
+                .into_iter()
+                .partition(|p| p.spectator);
+
+            for (heading, group) in vec![("Players", &players), ("Spectators", &spectators)].into_iter() {
+                if group.is_empty() {
+                    continue;
+                }
+
+                ui.label(heading);
+                for player in group.iter() {
+                    let status = match (player.connected, player.ready, player.spectator) {
+                        (false, _, _) => "disconnected",
+                        (true, _, true) => "watching",
+                        (true, true, _) => "ready",
+                        (true, false, _) => "pending",
+                    };
+                    ui.horizontal(|ui| {
+                        match player.computer {
+                            Some(d) => ui.monospace(format!("{} ({}): {}", player.name, d, status)),
+                            None => ui.monospace(format!("{}: {}", player.name, status)),
+                        };
+
+                        // the host can remove anyone but themselves
+                        let other = player.id != network.id() && player.computer.is_none();
+                        if network.is_server() && other {
+                            if ui.small_button("Kick").clicked() {
+                                removed = Some((player.id, false));
+                            }
+                            if ui.small_button("Ban").clicked() {
+                                removed = Some((player.id, true));
+                            }
+                        }
+                    });
+                }
+            }
+
+            // spectators pick whose sight limits the map
+            if network.is_spectating() {
+                let current = network.view();
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("View:");
+                    if ui.selectable_label(current == View::All, "All").clicked() {
+                        view = Some(View::All);
+                    }
+                    for player in players.iter() {
+                        let selected = current == View::Player(player.id);
+                        if ui.selectable_label(selected, &player.name).clicked() {
+                            view = Some(View::Player(player.id));
+                        }
+                    }
+                });
+            }
+
+            match removed {
+                Some((id, true)) => network.ban(&id, &state),
+                Some((id, false)) => network.kick(&id, &state, "You were kicked from the game".into()),
+                None => (),
+            }
+
+            if let Some(view) = view {
+                network.set_view(view);
+                state.events.send(Action::UpdateStructures);
+            }
+        }
+
         ui.separator();
         ui.heading("Selection");
         let area = &state.terrain.selected;
@@ -213,6 +429,7 @@ fn gui_display_system(
         ui.monospace("Fertility:   5");
         ui.monospace("Rocks:       6");
         ui.monospace("Water:       7");
+        ui.monospace("Target:      T (hold)");
 
         if hovered(window,ui) {
             selection.hovering = false;
@@ -222,6 +439,8 @@ fn gui_display_system(
     let units = state.find_units(&selection.selected);
     gui.flags.update(GuiFlag::InfoWindow,!units.is_empty());
 
+    let mut issued: Vec<(Id,Vec<Order>)> = vec![];
+
     if gui.flags.get(GuiFlag::InfoWindow) {
         egui::Window::new("Unit")
         .default_width(300.0)
@@ -231,6 +450,8 @@ fn gui_display_system(
             ui.set_width(ui.available_width());
             ui.set_height(ui.available_height());
 
+            let others = units.clone();
+
             for unit in units.into_iter() {
                 let mut select = selection.has(unit);
 
@@ -244,9 +465,56 @@ fn gui_display_system(
                     ui.monospace(format!("Type:   {:?}", unit.specialty()));
                     ui.monospace(format!("AP:     {}", unit.actions()));
                     ui.monospace(format!("Max AP: {}", unit.max_actions()));
+                    ui.monospace(format!("Ready:  {}%", unit.readiness()));
+                    ui.monospace(format!("Rank:   {}", unit.veterancy()));
+                    ui.monospace(format!("Range:  {}", unit.specialty().range()));
     
                     ui.add_space(10.);
                     ui.checkbox(&mut select,"Select unit");
+
+                    // friendly units on the same tile can receive veterans
+                    let veterans: Vec<usize> = unit
+                        .soldiers()
+                        .iter()
+                        .enumerate()
+                        .filter(|(_,s)| s.veterancy() >= Veterancy::Veteran)
+                        .map(|(i,_)| i)
+                        .collect();
+
+                    if !veterans.is_empty() && unit.player_id() == &network.id() {
+                        for other in others.iter().filter(|o| o.id() != unit.id() && o.player_id() == unit.player_id()) {
+                            if ui.button(format!("Transfer {} veterans to {}", veterans.len(), other.name())).clicked() {
+                                network.send_change_event(vec![
+                                    Change::reassign(unit, other, veterans.clone())
+                                ]);
+                            }
+                        }
+                    }
+                    ui.add_space(10.);
+                    ui.heading("Orders");
+
+                    for order in unit.orders().list() {
+                        ui.monospace(format!("  {}", order));
+                    }
+
+                    if unit.player_id() == &network.id() {
+                        ui.horizontal(|ui| {
+                            let mut list = unit.orders().list().clone();
+
+                            let hold = ui.button("Hold").clicked();
+                            let dig = ui.button("Dig In").clicked();
+                            let clear = ui.button("Clear").clicked();
+
+                            if hold { list.push(Order::Hold(1)); }
+                            if dig { list.push(Order::DigIn); }
+                            if clear { list.clear(); }
+
+                            if hold || dig || clear {
+                                issued.push((*unit.id(),list));
+                            }
+                        });
+                    }
+
                     ui.add_space(10.);
                     ui.collapsing("Soldiers", |ui| {
                         egui::ScrollArea::from_max_height(400.)
@@ -271,6 +539,10 @@ fn gui_display_system(
                                     ui.monospace(format!("  Morale:  {} / {}", m, mm));
                                     ui.monospace(format!("  Defense: {} / {}", d, md));
                                     ui.monospace(format!("  Attack:  {} / {}", a, ma));
+                                    ui.monospace(format!("  Rank:    {}", soldier.veterancy()));
+                                    for c in soldier.conditions() {
+                                        ui.monospace(format!("  Status:  {} ({} turns)", c.condition, c.turns));
+                                    }
                                 });
                                 ui.add_space(5.);
                             }
@@ -292,6 +564,10 @@ fn gui_display_system(
         });
     }
 
+    if !issued.is_empty() {
+        issue_orders(&mut state,&mut network,issued);
+    }
+
     if gui.flags.get(GuiFlag::NetworkWindow) {
         egui::Window::new("Network")
         .default_width(300.0)
@@ -322,22 +598,76 @@ fn gui_display_system(
                 ui.text_edit_singleline(&mut gui.name);
             });
 
+            ui.horizontal(|ui| {
+                ui.monospace("pass: ");
+                ui.add(egui::TextEdit::singleline(&mut gui.password).password(true));
+            });
+
+            if network.is_offline() {
+                let mut spectate = network.wants_spectate();
+                ui.checkbox(&mut spectate, "Join as spectator");
+                network.set_spectate(spectate);
+            }
+
+            if !network.is_client() {
+                let mut limit = network.max_players();
+                let mut spectators = network.allows_spectators();
+                ui.add(egui::Slider::new(&mut limit, 1..=SLOTS).text("Max players"));
+                ui.checkbox(&mut spectators, "Allow spectators");
+                network.set_max_players(limit);
+                network.set_spectators(spectators);
+            }
+
+            ui.add(egui::Slider::new(&mut state.timer.limit, 0..=600).text("Turn timer (s)"));
+
             ui.horizontal(|ui| {
                 if ui.button("Host").clicked() {
                     network.set_name(gui.name.clone());
+                    network.set_password(gui.password.clone());
                     network.host(gui.ip.clone(),gui.port);
                 }
 
                 if ui.button("Connect").clicked() {
                     network.set_name(gui.name.clone());
+                    network.set_password(gui.password.clone());
                     network.connect(gui.ip.clone(),gui.port);
                 }
 
                 if ui.button("Disconnect").clicked() {
                     network.disconnect();
                 }
+
+                if network.can_resume() && !network.is_server() && ui.button("Reconnect").clicked() {
+                    network.set_name(gui.name.clone());
+                    network.reconnect();
+                }
+
+                if network.is_client() && ui.button("Refresh").clicked() {
+                    network.send_refresh_event();
+                }
             });
 
+            ui.monospace(format!("version: {}", Version::current()));
+
+            if let Some(reason) = network.rejection() {
+                ui.colored_label(egui::Color32::RED, format!("Refused: {}", reason));
+            }
+
+            if !network.is_client() {
+                ui.horizontal(|ui| {
+                    ui.monospace("computer:");
+                    for difficulty in [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard].iter() {
+                        if ui.button(format!("{}", difficulty)).clicked() {
+                            opponents.request(*difficulty);
+                        }
+                    }
+                });
+
+                for opponent in opponents.opponents().iter() {
+                    ui.monospace(format!("{} ({})", opponent.name(), opponent.difficulty()));
+                }
+            }
+
             if hovered(window,ui) {
                 selection.hovering = false;
             }
@@ -345,6 +675,8 @@ fn gui_display_system(
     }
 
     if gui.flags.get(GuiFlag::ChatWindow) {
+        let mut clicked = None;
+
         egui::Window::new("Chat")
         .default_width(300.0)
         .default_height(400.0)
@@ -363,25 +695,81 @@ fn gui_display_system(
                 .show_rows(ui, row_height, gui.history.len(), |ui, row_range| {
                     for row in row_range {
                         if let Some(message) = gui.history.get(row) {
-                            let player = &message.player;
-                            let content = &message.content;
-                            ui.label(format!("{}: {}",player,content));
+                            ui.horizontal_wrapped(|ui| {
+                                match message.channel {
+                                    Channel::System => {
+                                        ui.colored_label(egui::Color32::GRAY, "*");
+                                    }
+                                    Channel::Team(_) => {
+                                        ui.label(format!("[team] {}:", message.player));
+                                    }
+                                    Channel::All => {
+                                        ui.label(format!("{}:", message.player));
+                                    }
+                                }
+
+                                for segment in chat::parse(&message.content).into_iter() {
+                                    match &segment {
+                                        Segment::Text(text) => {
+                                            ui.label(text);
+                                        }
+                                        Segment::Location(location) => {
+                                            if ui.selectable_label(false, chat::location_link(location)).clicked() {
+                                                clicked = Some(segment.clone());
+                                            }
+                                        }
+                                        Segment::Unit(id) => {
+                                            let name = state.units
+                                                .get_all_ids(&vec![*id])
+                                                .first()
+                                                .map(|u| u.name())
+                                                .unwrap_or("unit".into());
+
+                                            if ui.selectable_label(false, format!("[{}]", name)).clicked() {
+                                                clicked = Some(segment.clone());
+                                            }
+                                        }
+                                    }
+                                }
+                            });
                         }
                     }
                 });
 
             ui.add_space(10.);
+            ui.horizontal(|ui| {
+                let team = network.player_data().map(|p| p.team).unwrap_or(0);
+                if let Channel::Team(_) = gui.channel {
+                    gui.channel = Channel::Team(team);
+                }
+
+                ui.radio_value(&mut gui.channel, Channel::All, "All");
+                ui.radio_value(&mut gui.channel, Channel::Team(team), "Team");
+
+                // link whatever is selected on the map
+                if ui.small_button("Hex").clicked() {
+                    let location = state.terrain.selected.location();
+                    gui.message.push_str(&chat::location_link(&location));
+                }
+
+                if let Some(unit) = state.units.selected_units().first() {
+                    if ui.small_button("Unit").clicked() {
+                        gui.message.push_str(&chat::unit_link(unit.id()));
+                    }
+                }
+            });
+
             ui.horizontal(|ui| {
                 let response = ui.text_edit_singleline(&mut gui.message);
 
                 if keyboard.just_pressed(KeyCode::Return) && response.lost_focus() {
-                    network.send_chat_event(gui.message.clone());
+                    network.send_chat_event(gui.message.clone(), gui.channel);
                     gui.message.clear();
                     response.request_focus();
                 }
 
                 if ui.button("Send").clicked() {
-                    network.send_chat_event(gui.message.clone());
+                    network.send_chat_event(gui.message.clone(), gui.channel);
                     gui.message.clear();
                 }
             });
@@ -390,6 +778,573 @@ fn gui_display_system(
                 selection.hovering = false;
             }
         });
+
+        // links center the map and select the player's own units
+        let point = match clicked {
+            Some(Segment::Location(location)) => Some(Point::from(location)),
+            Some(Segment::Unit(id)) => {
+                let unit = state.units
+                    .get_all_ids(&vec![id])
+                    .first()
+                    .map(|u| (*u.position(), *u.player_id()));
+
+                if let Some((_, owner)) = unit {
+                    if owner == network.id() {
+                        state.units.select(&vec![id]);
+                    }
+                }
+
+                unit.map(|(p, _)| p)
+            }
+            _ => None,
+        };
+
+        if let Some(point) = point {
+            let (x, y) = point.to_global(&dimensions);
+            for mut view in cameras.iter_mut() {
+                view.target = Some(Vec2::new(x, y));
+            }
+        }
+    }
+
+    if gui.flags.get(GuiFlag::ReportWindow) {
+        egui::Window::new("Report")
+        .default_width(300.0)
+        .default_height(400.0)
+        .show(context.ctx(), |ui| {
+            ui.set_width(ui.available_width());
+
+            ui.horizontal_wrapped(|ui| {
+                for category in Category::all().iter() {
+                    let mut shown = gui.categories.contains(category);
+                    if ui.checkbox(&mut shown, format!("{}", category)).changed() {
+                        match shown {
+                            true => gui.categories.push(*category),
+                            false => gui.categories.retain(|c| c != category),
+                        }
+                    }
+                }
+            });
+
+            ui.checkbox(&mut gui.all_turns, "All turns");
+
+            let reports = match gui.all_turns {
+                true => state.log.reports().iter().rev().collect(),
+                false => vec![&state.report],
+            };
+
+            egui::ScrollArea::from_max_height(400.)
+                .show(ui, |ui| {
+                for report in reports {
+                    ui.heading(format!("Turn {}", report.turn()));
+
+                    let records = report.filtered(&gui.categories);
+                    if records.is_empty() {
+                        ui.label("Nothing to report");
+                    }
+                    for record in records {
+                        ui.label(format!("{}", record));
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut gui.log);
+                if ui.button("Export").clicked() {
+                    if let Err(e) = state.log.export(&gui.log) {
+                        gui.add_message("Report".into(), e.to_string());
+                    }
+                }
+            });
+
+            if hovered(window,ui) {
+                selection.hovering = false;
+            }
+        });
+    }
+
+    // players pick their slots before the first turn
+    if state.in_lobby() && !network.is_offline() {
+        let mut seat = None;
+        let mut start = false;
+
+        egui::Window::new("Lobby")
+        .default_width(300.0)
+        .show(context.ctx(), |ui| {
+            ui.set_width(ui.available_width());
+
+            let players = network.players();
+            let local = players.iter().find(|p| p.id == network.id() && !p.spectator).cloned();
+
+            for slot in 0..SLOTS {
+                ui.horizontal(|ui| {
+                    ui.monospace(format!("{}.", slot + 1));
+                    match players.iter().find(|p| p.order == slot) {
+                        Some(player) => {
+                            let status = if player.ready || player.computer.is_some() { "ready" } else { "waiting" };
+                            ui.colored_label(color(&player.side), format!("{} ({})", player.name, player.side));
+                            ui.label(format!("Team {}: {}", player.team, status));
+                        }
+                        None => {
+                            ui.label("Open");
+                            if let Some(player) = &local {
+                                if ui.small_button("Take").clicked() {
+                                    seat = Some((slot, player.side, player.team));
+                                }
+                            }
+                        }
+                    }
+                });
+            }
+
+            let watching: Vec<String> = players
+                .iter()
+                .filter(|p| p.spectator)
+                .map(|p| p.name.clone())
+                .collect();
+
+            if !watching.is_empty() {
+                ui.label(format!("Spectators: {}", watching.join(", ")));
+            }
+
+            if let Some(player) = &local {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    for side in Side::all().iter() {
+                        if ui.selectable_label(player.side == *side, format!("{}", side)).clicked() {
+                            seat = Some((player.order, *side, player.team));
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    for team in 1..=(SLOTS as u8) {
+                        if ui.selectable_label(player.team == team, format!("Team {}", team)).clicked() {
+                            seat = Some((player.order, player.side, team));
+                        }
+                    }
+                });
+            }
+
+            // settings come from the host's side panel and objectives
+            ui.separator();
+            ui.label(format!("Seed: {}", state.terrain.seed));
+            ui.label(format!("Size: {} ({}px)", state.terrain.size, state.terrain.tile_size));
+            ui.label(format!("Turn timer: {}s", state.timer.limit));
+            for objective in state.victory.objectives().iter() {
+                ui.label(format!("{}", objective));
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                let ready = local.as_ref().map(|p| p.ready).unwrap_or(false);
+                if local.is_some() && ui.button(if ready { "Not Ready" } else { "Ready" }).clicked() {
+                    network.send_ready_event(state.turn(), !ready);
+                }
+
+                let everyone = players
+                    .iter()
+                    .filter(|p| p.connected && p.computer.is_none() && !p.spectator)
+                    .all(|p| p.ready);
+
+                if network.is_server() && ui.add(egui::Button::new("Start").enabled(everyone)).clicked() {
+                    start = true;
+                }
+            });
+
+            if hovered(window,ui) {
+                selection.hovering = false;
+            }
+        });
+
+        if let Some((slot, side, team)) = seat {
+            network.send_seat_event(slot, side, team);
+        }
+
+        if start {
+            state.start();
+            network.players.clear_ready();
+            network.send_update_event(&state);
+            network.send_system_event("The game has started".into());
+        }
+    }
+
+    if gui.flags.get(GuiFlag::ReplayWindow) {
+        egui::Window::new("Replay")
+        .default_width(300.0)
+        .show(context.ctx(), |ui| {
+            ui.set_width(ui.available_width());
+
+            ui.horizontal(|ui| {
+                ui.monospace("file: ");
+                ui.text_edit_singleline(&mut gui.replay);
+            });
+
+            // recording follows the game wherever it's played
+            ui.horizontal(|ui| {
+                if !state.recording.is_recording() {
+                    if ui.button("Record").clicked() {
+                        state.record();
+                    }
+                }
+                else {
+                    ui.label(format!("Recorded {} turns", state.recording.len()));
+                    if ui.button("Stop and Save").clicked() {
+                        if let Some(replay) = state.recording.finish() {
+                            if let Err(e) = replay.save(&gui.replay) {
+                                gui.add_message("Replay".into(), e.to_string());
+                            }
+                        }
+                    }
+                }
+            });
+
+            // playback takes over the board, so only offline
+            if network.is_offline() {
+                ui.horizontal(|ui| {
+                    if ui.button("Load").clicked() {
+                        match Replay::load(&gui.replay) {
+                            Ok(replay) => {
+                                state.recording.finish();
+                                playback.load(replay);
+                            },
+                            Err(e) => gui.add_message("Replay".into(), e.to_string()),
+                        }
+                    }
+
+                    if playback.is_loaded() && ui.button("Close").clicked() {
+                        playback.close();
+                    }
+                });
+            }
+
+            if playback.is_loaded() {
+                ui.horizontal(|ui| {
+                    if playback.is_playing() {
+                        if ui.button("Pause").clicked() {
+                            playback.pause();
+                        }
+                    }
+                    else if ui.button("Play").clicked() {
+                        playback.play();
+                    }
+
+                    if ui.button("Step").clicked() {
+                        playback.step_once();
+                    }
+                });
+
+                let last = playback.len().saturating_sub(1);
+                let mut frame = playback.frame().min(last);
+                ui.add(egui::Slider::new(&mut frame, 0..=last).text("Turn"));
+                if frame != playback.frame().min(last) {
+                    playback.seek(frame);
+                }
+
+                ui.label(format!("Turn {} of {}, step {}", playback.frame() + 1, playback.len(), playback.step()));
+            }
+
+            if hovered(window,ui) {
+                selection.hovering = false;
+            }
+        });
+    }
+
+    if gui.flags.get(GuiFlag::MailWindow) && network.is_offline() {
+        egui::Window::new("Play by Mail")
+        .default_width(300.0)
+        .show(context.ctx(), |ui| {
+            ui.set_width(ui.available_width());
+
+            ui.horizontal(|ui| {
+                ui.monospace("file:     ");
+                ui.text_edit_singleline(&mut gui.mail);
+            });
+
+            // everyone signs and checks their files with the same password
+            ui.horizontal(|ui| {
+                ui.monospace("password: ");
+                ui.text_edit_singleline(&mut gui.password);
+            });
+
+            if !mailbox.is_active() {
+                ui.horizontal(|ui| {
+                    ui.monospace("players:  ");
+                    ui.text_edit_singleline(&mut gui.seats);
+                });
+
+                ui.horizontal(|ui| {
+                    if ui.button("Start").clicked() {
+                        let names: Vec<String> = gui.seats
+                            .split(',')
+                            .map(|n| n.trim().to_string())
+                            .filter(|n| !n.is_empty())
+                            .collect();
+
+                        match names.len() {
+                            0 | 1 => gui.add_system("List at least two players, separated by commas".into()),
+                            _ => mailbox.begin(&state, &mut network, names),
+                        }
+                    }
+
+                    if ui.button("Open").clicked() {
+                        match Mail::load(&gui.mail, &gui.password) {
+                            Ok(mail) => mailbox.open(mail),
+                            Err(e) => gui.add_message("Mail".into(), e.to_string()),
+                        }
+                    }
+                });
+            }
+            else {
+                if let Some(mail) = mailbox.mail() {
+                    let names: Vec<String> = mail.seats
+                        .iter()
+                        .map(|s| match mail.has_submitted(&s.id) {
+                            true => format!("{} (done)", s.name),
+                            false => s.name.clone(),
+                        })
+                        .collect();
+                    ui.label(format!("Turn {}: {}", mail.turn(), names.join(", ")));
+                }
+
+                match (mailbox.seat(), mailbox.is_sent()) {
+                    (Some(seat), false) => ui.label(format!("Playing as {}", seat.name)),
+                    (Some(_), true) => ui.label("Turn sent, waiting for the next player"),
+                    (None, _) => ui.label("Opening..."),
+                };
+
+                ui.horizontal(|ui| {
+                    if !mailbox.is_sent() && mailbox.seat().is_some() && ui.button("Send").clicked() {
+                        if let Err(e) = mailbox.send(&state, &gui.mail, &gui.password) {
+                            gui.add_message("Mail".into(), e.to_string());
+                        }
+                    }
+
+                    if ui.button("Close").clicked() {
+                        mailbox.close();
+                    }
+                });
+            }
+
+            if hovered(window,ui) {
+                selection.hovering = false;
+            }
+        });
+    }
+
+    if gui.flags.get(GuiFlag::VictoryWindow) {
+        let mut changed = false;
+
+        egui::Window::new("Objectives")
+        .default_width(300.0)
+        .show(context.ctx(), |ui| {
+            ui.set_width(ui.available_width());
+
+            let mut remove = None;
+            for (i,objective) in state.victory.objectives().iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{}", objective));
+                    if !network.is_client() && ui.small_button("x").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+
+            if let Some(i) = remove {
+                state.victory.objectives_mut().remove(i);
+                changed = true;
+            }
+
+            // only the host decides how the game is won
+            if !network.is_client() {
+                ui.separator();
+                ui.add(egui::Slider::new(&mut gui.turns, 1..=100).text("Turns"));
+
+                let settlements = state.terrain.settlements();
+                let most = settlements.len().max(1);
+                gui.settlements = gui.settlements.min(most);
+                ui.add(egui::Slider::new(&mut gui.settlements, 1..=most).text("Settlements"));
+
+                ui.label("Survivor");
+                for player in network.players().into_iter() {
+                    ui.radio_value(&mut gui.survivor, Some(player.id), player.name);
+                }
+
+                let location = state.terrain.selected.location();
+                let turns = gui.turns;
+                let count = gui.settlements;
+                let until = state.turn() + turns;
+                let survivor = gui.survivor.unwrap_or(network.id());
+
+                let mut added = None;
+                ui.horizontal(|ui| {
+                    if ui.button("Eliminate").clicked() {
+                        added = Some(Objective::Eliminate);
+                    }
+
+                    if ui.button("Hold").clicked() {
+                        added = Some(Objective::Hold { points: vec![location], turns });
+                    }
+
+                    // settlements are the villages, towns and cities on the map
+                    if ui.button("Settlements").clicked() {
+                        match settlements.is_empty() {
+                            true => warn!("there are no settlements on the map"),
+                            false => added = Some(Objective::Settlements { points: settlements, count }),
+                        }
+                    }
+
+                    if ui.button("Survive").clicked() {
+                        added = Some(Objective::Survive { player: survivor, turn: until });
+                    }
+                });
+
+                if let Some(objective) = added {
+                    add_objective(state.victory.objectives_mut(), objective);
+                    changed = true;
+                }
+            }
+
+            if hovered(window,ui) {
+                selection.hovering = false;
+            }
+        });
+
+        if changed && network.is_server() {
+            network.send_update_event(&state);
+        }
+    }
+
+    if let Some(outcome) = state.victory.outcome().cloned() {
+        egui::Window::new("Results")
+        .default_width(400.0)
+        .collapsible(false)
+        .show(context.ctx(), |ui| {
+            ui.set_width(ui.available_width());
+
+            let winners: Vec<String> = outcome.winners
+                .iter()
+                .map(|p| state.victory.name(p))
+                .collect();
+
+            if winners.is_empty() {
+                ui.heading("Draw");
+            } else {
+                ui.heading(format!("Victory: {}", winners.join(", ")));
+            }
+
+            ui.label(format!("Turn {}: {}", outcome.turn, outcome.reason));
+            ui.separator();
+
+            ui.monospace(format!("{:<12} {:>5} {:>5} {:>7} {:>5} {:>5} {:>5}",
+                "Player", "Units", "Lost", "Damage", "Exp", "Sick", "Held"));
+
+            for (_,stats) in state.victory.stats() {
+                ui.monospace(format!("{:<12} {:>5} {:>5} {:>7} {:>5} {:>5} {:>5}",
+                    stats.name,
+                    stats.standing,
+                    stats.lost,
+                    stats.damage,
+                    stats.experience,
+                    stats.incidents,
+                    stats.held));
+            }
+
+            if hovered(window,ui) {
+                selection.hovering = false;
+            }
+        });
+    }
+
+    editor.enabled = gui.flags.get(GuiFlag::EditorWindow) && !network.is_client();
+
+    if editor.enabled {
+        let mut changed = false;
+
+        egui::Window::new("Editor")
+        .default_width(300.0)
+        .show(context.ctx(), |ui| {
+            ui.set_width(ui.available_width());
+
+            let brush = &mut editor.brush;
+
+            ui.horizontal_wrapped(|ui| {
+                for tool in [
+                    Tool::Raise,
+                    Tool::Lower,
+                    Tool::Moisture,
+                    Tool::Soil,
+                    Tool::Biome,
+                    Tool::Foliage,
+                    Tool::Structure,
+                    Tool::River,
+                    Tool::Road,
+                    Tool::Erase,
+                ].iter() {
+                    ui.radio_value(&mut brush.tool, *tool, format!("{}", tool));
+                }
+            });
+
+            ui.add(egui::Slider::new(&mut brush.size, 0..=5).text("Size"));
+            ui.add(egui::Slider::new(&mut brush.strength, 1..=100).text("Strength"));
+
+            ui.horizontal_wrapped(|ui| {
+                match brush.tool {
+                    Tool::Moisture => {
+                        ui.add(egui::Slider::new(&mut brush.moisture, 0..=100).text("Moisture"));
+                    }
+                    Tool::Soil => {
+                        for v in [Soil::Clay, Soil::Sand, Soil::Silt, Soil::Peat, Soil::Chalk, Soil::Loam].iter() {
+                            ui.radio_value(&mut brush.soil, *v, format!("{}", v));
+                        }
+                    }
+                    Tool::Biome => {
+                        for v in [Biome::Grassland, Biome::Forest, Biome::Desert, Biome::Tundra, Biome::Aquatic].iter() {
+                            ui.radio_value(&mut brush.biome, *v, format!("{}", v));
+                        }
+                    }
+                    Tool::Foliage => {
+                        for v in [Foliage::Grass, Foliage::Trees, Foliage::Brush, Foliage::Crops, Foliage::Rocks].iter() {
+                            ui.radio_value(&mut brush.foliage, *v, format!("{}", v));
+                        }
+                    }
+                    Tool::Structure => {
+                        for v in [Structure::None, Structure::Base, Structure::Village, Structure::Town, Structure::City].iter() {
+                            ui.radio_value(&mut brush.structure, *v, format!("{}", v));
+                        }
+                    }
+                    _ => (),
+                }
+            });
+
+            ui.separator();
+            ui.label(format!("Edited tiles: {}", state.terrain.overrides.len()));
+
+            ui.horizontal(|ui| {
+                if ui.add(egui::Button::new("Undo").enabled(editor.can_undo())).clicked() {
+                    changed |= editor.undo(&mut state.terrain.overrides);
+                }
+
+                if ui.add(egui::Button::new("Redo").enabled(editor.can_redo())).clicked() {
+                    changed |= editor.redo(&mut state.terrain.overrides);
+                }
+
+                if ui.button("Clear").clicked() && !state.terrain.overrides.is_empty() {
+                    editor.begin(&state.terrain.overrides);
+                    state.terrain.overrides.clear();
+                    changed = true;
+                }
+            });
+
+            if hovered(window,ui) {
+                selection.hovering = false;
+            }
+        });
+
+        if changed {
+            state.events.send(Action::UpdateTerrain);
+        }
     }
 
     if gui.flags.get(GuiFlag::UnitWindow) {
@@ -414,6 +1369,10 @@ fn gui_display_system(
                     if ui.button("Militia").clicked() {
                         selection.place_request(gui.unit_name.clone(),Specialty::Militia);
                     }
+
+                    if ui.button("Artillery").clicked() {
+                        selection.place_request(gui.unit_name.clone(),Specialty::Artillery);
+                    }
                 });
 
                 if hovered(window,ui) {
@@ -425,6 +1384,39 @@ fn gui_display_system(
     // dbg!(selection.hovering);
 }
 
+/// add an objective, merging it into an existing
+/// objective of the same kind
+fn add_objective(objectives: &mut Vec<Objective>, objective: Objective) {
+    for existing in objectives.iter_mut() {
+        match (existing, &objective) {
+            (Objective::Eliminate, Objective::Eliminate) => return,
+            (Objective::Hold { points, turns }, Objective::Hold { points: new, turns: t }) => {
+                let new: Vec<Location> = new.iter().filter(|p| !points.contains(p)).cloned().collect();
+                points.extend(new);
+                *turns = *t;
+                return;
+            }
+            (Objective::Settlements { points, count }, Objective::Settlements { points: new, count: c }) => {
+                *points = new.clone();
+                *count = *c;
+                return;
+            }
+            _ => (),
+        }
+    }
+    objectives.push(objective);
+}
+
+/// the egui color of a side
+fn color(side: &Side) -> egui::Color32 {
+    let [r, g, b, _] = side.color().as_rgba_f32();
+    egui::Color32::from_rgb(
+        (r * 255.0) as u8,
+        (g * 255.0) as u8,
+        (b * 255.0) as u8,
+    )
+}
+
 fn hovered(window: &Window, ui: &egui::Ui) -> bool {
     match window.cursor_position() {
         Some(mut p) => {
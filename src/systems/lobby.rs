@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+
+use crate::state::{State, Teams};
+use crate::systems::network::NetworkState;
+
+pub struct LobbyPlugin;
+
+/// Keep the teams used for combat and victory in line
+/// with the lobby.
+fn lobby_team_system(
+    mut state: ResMut<State>,
+    network: Res<NetworkState>,
+) {
+    let teams = Teams::new(network
+        .players()
+        .into_iter()
+        .map(|p| (p.id, p.team))
+        .collect());
+
+    if state.teams != teams {
+        state.teams = teams;
+    }
+}
+
+impl Plugin for LobbyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(lobby_team_system.system());
+    }
+}
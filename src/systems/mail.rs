@@ -0,0 +1,289 @@
+use bevy::prelude::*;
+use bevy_tilemap::Tilemap;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::generation::PlayerId;
+use crate::networking::messages::*;
+use crate::state::{Action, Context, Mail, Seat, Snapshot, State, Submission};
+use crate::systems::gui::GuiState;
+use crate::systems::network::NetworkState;
+use crate::systems::replay;
+
+pub struct MailPlugin;
+
+/// A game played by passing a turn file from one player
+/// to the next, with turns resolved by whoever opens it
+pub struct Mailbox {
+    mail: Option<Mail>,
+
+    /// the seat being played on this machine
+    seat: Option<Seat>,
+
+    /// units created, moved and changed by the seat
+    messages: Vec<MessageData>,
+
+    sent: bool,
+    opening: bool,
+    waiting: Option<Snapshot>,
+
+    /// stands in for the players whose turns are replayed
+    network: NetworkState,
+}
+
+impl Default for Mailbox {
+    fn default() -> Self {
+        Self {
+            mail: None,
+            seat: None,
+            messages: vec![],
+            sent: false,
+            opening: false,
+            waiting: None,
+            network: NetworkState::default(),
+        }
+    }
+}
+
+impl Mailbox {
+    /// start a new game from the board as it is, with the
+    /// local player taking the first seat
+    pub fn begin(&mut self, state: &State, network: &mut NetworkState, names: Vec<String>) {
+        let seats: Vec<Seat> = names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| Seat {
+                id: match i {
+                    0 => network.id(),
+                    _ => PlayerId::new(),
+                },
+                name,
+            })
+            .collect();
+
+        let mail = Mail::new(
+            state.seed(),
+            state.factors(),
+            state.victory.clone(),
+            state.snapshot(),
+            seats,
+        );
+
+        self.mail = Some(mail);
+        self.messages.clear();
+        self.sent = false;
+        self.take_seat(network);
+    }
+
+    /// open a turn file, rebuilding the board before
+    /// replaying the turns already taken
+    pub fn open(&mut self, mail: Mail) {
+        self.mail = Some(mail);
+        self.seat = None;
+        self.messages.clear();
+        self.sent = false;
+        self.opening = true;
+    }
+
+    pub fn close(&mut self) {
+        self.mail = None;
+        self.seat = None;
+        self.messages.clear();
+        self.opening = false;
+        self.waiting = None;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.mail.is_some()
+    }
+
+    /// true once the local seat has written out its turn
+    pub fn is_sent(&self) -> bool {
+        self.sent
+    }
+
+    pub fn seat(&self) -> Option<&Seat> {
+        self.seat.as_ref()
+    }
+
+    pub fn mail(&self) -> Option<&Mail> {
+        self.mail.as_ref()
+    }
+
+    /// keep a message sent by the local seat during its turn
+    pub fn message(&mut self, message: MessageData) {
+        if !self.is_active() || self.sent {
+            return;
+        }
+
+        match message {
+            MessageData::Create(_) |
+            MessageData::Move(_) |
+            MessageData::Change(_) => self.messages.push(message),
+            _ => (),
+        }
+    }
+
+    /// add the local seat's turn and write the file for
+    /// the next player
+    pub fn send<P: AsRef<Path>>(&mut self, state: &State, path: P, password: &str) -> Result<()> {
+        let (mut mail, seat) = match (self.mail.clone(), self.seat.clone()) {
+            (Some(m), Some(s)) if !self.sent => (m, s),
+            _ => return Ok(()),
+        };
+
+        let orders = state.units
+            .units()
+            .into_iter()
+            .filter(|u| *u.player_id() == seat.id)
+            .map(|u| (*u.id(), u.orders().list().clone()))
+            .collect();
+
+        let attacks = state.pending
+            .iter()
+            .filter(|a| a.player == seat.id)
+            .cloned()
+            .collect();
+
+        let submitted = mail.submit(Submission {
+            player: seat.id,
+            messages: self.messages.clone(),
+            orders,
+            attacks,
+        });
+
+        if !submitted {
+            return Err(Error::MailInvalid("it isn't this seat's turn".into()));
+        }
+
+        mail.save(path, password)?;
+
+        self.mail = Some(mail);
+        self.sent = true;
+        Ok(())
+    }
+
+    /// play back the turns taken by earlier seats
+    fn replay(&self, map: &mut Tilemap, state: &mut State, gui: &mut GuiState) {
+        let mail = match self.mail.as_ref() {
+            Some(m) => m,
+            None => return,
+        };
+
+        for submission in mail.submissions.iter() {
+            for message in submission.messages.iter() {
+                replay::apply(&self.network, map, state, gui, message.clone());
+            }
+            state.units.set_orders(&submission.player, &submission.orders);
+            for attack in submission.attacks.iter() {
+                state.queue_attack(attack.clone());
+            }
+        }
+    }
+
+    /// resolve the turn locally once every seat has played
+    fn resolve(&mut self, map: &mut Tilemap, state: &mut State, gui: &mut GuiState) {
+        let mail = match self.mail.as_mut() {
+            Some(m) if m.is_complete() => m,
+            _ => return,
+        };
+
+        // whoever opens the file gets the same results
+        let turn = state.turn() + 1;
+        let seed = ((mail.seed as u64) << 32) | turn as u64;
+        let changes = state.resolve_seeded(seed);
+        state.advance(map, turn, &changes);
+        gui.add_system(format!("{} has begun", state.calendar));
+
+        if state.judge() {
+            gui.add_system("The game is over".into());
+        }
+
+        mail.advance(state.snapshot(), state.victory.clone(), state.report.clone());
+    }
+
+    /// play as the seat the file was written for
+    fn take_seat(&mut self, network: &mut NetworkState) {
+        let mail = match self.mail.as_ref() {
+            Some(m) => m,
+            None => return,
+        };
+
+        let players = mail.seats
+            .iter()
+            .enumerate()
+            .map(|(i, s)| PlayerData::new(s.id, s.name.clone()).seated(i))
+            .collect();
+        network.set_players(players);
+
+        self.seat = mail.seats
+            .iter()
+            .find(|s| Some(s.id) == mail.to)
+            .cloned();
+        if let Some(seat) = self.seat.as_ref() {
+            network.set_id(seat.id);
+            network.set_name(seat.name.clone());
+        }
+    }
+}
+
+/// Rebuild the board from an opened turn file, replay the
+/// turns already taken and resolve the turn if it's complete.
+fn mail_system(
+    mut state: ResMut<State>,
+    mut context: ResMut<Context>,
+    mut gui: ResMut<GuiState>,
+    mut network: ResMut<NetworkState>,
+    mut mailbox: ResMut<Mailbox>,
+    mut tilemap: Query<&mut Tilemap>,
+) {
+    if !state.is_loaded() || !mailbox.is_active() {
+        return;
+    }
+
+    if mailbox.opening {
+        mailbox.opening = false;
+
+        let mail = match mailbox.mail.clone() {
+            Some(m) => m,
+            None => return,
+        };
+
+        state.terrain.seed = format!("{}", mail.seed);
+        state.factors = mail.factors;
+        state.victory = mail.victory;
+        state.report = mail.report;
+        state.resize(&mut context, mail.start.context);
+        state.events.send(Action::UpdateTerrain);
+
+        mailbox.waiting = Some(mail.start);
+    }
+
+    // wait for a rebuilt map before placing units
+    if state.events.receive(Action::RebuildMap) {
+        return;
+    }
+
+    let snapshot = match mailbox.waiting.take() {
+        Some(s) => s,
+        None => return,
+    };
+
+    let mut map = tilemap.single_mut().expect("Need tilemap");
+
+    state.restore(&mut map, snapshot);
+    mailbox.replay(&mut map, &mut state, &mut gui);
+    mailbox.resolve(&mut map, &mut state, &mut gui);
+    mailbox.take_seat(&mut network);
+
+    if let Some(seat) = mailbox.seat() {
+        gui.add_system(format!("Turn {}: {} to play", state.turn(), seat.name));
+    }
+}
+
+impl Plugin for MailPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Mailbox>()
+           .add_system(mail_system.system());
+    }
+}
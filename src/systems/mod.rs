@@ -1,7 +1,15 @@
 pub mod camera;
+pub mod editor;
 pub mod generate;
 pub mod gui;
 pub mod icon;
+pub mod lobby;
+pub mod mail;
+pub mod opponent;
 pub mod overlay;
+pub mod replay;
+pub mod scenario;
 pub mod selection;
-pub mod network;
\ No newline at end of file
+pub mod structures;
+pub mod network;
+pub mod turn;
\ No newline at end of file
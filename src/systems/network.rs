@@ -1,13 +1,14 @@
 use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::ops::{Deref,DerefMut};
 use itertools::Itertools;
 use bimap::hash::BiHashMap;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use indexmap::IndexMap;
 use rand::Rng;
 
 use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
 use bevy_tilemap::Tilemap;
 use bevy_spicy_networking::{
     ClientMessage,
@@ -19,21 +20,39 @@ use bevy_spicy_networking::{
     NetworkSettings,
     NetworkClient,
     ServerNetworkEvent,
+    ClientNetworkEvent,
 };
 
-use crate::state::{traits::*, State, Flags};
+use crate::state::{traits::*, Action, Attack, Context, Side, Snapshot, State, Flags, SLOTS};
 use crate::networking::messages::{self,*};
-use crate::generation::{Unit,id::*,Change};
+use crate::networking::chat::Channel;
+use crate::networking::delivery::{Inbox, Outbox, Stream};
+use crate::networking::delta::{self, Summary, UnitDelta};
+use crate::networking::packing;
+use crate::networking::version::{ClientHello, ServerHello, Version};
+use crate::generation::{Unit,id::*,Change,Order};
 use crate::generation::Factors;
 use crate::resources::Label;
-use crate::objects::Selection;
+use crate::objects::{Fortifications, Selection};
 use crate::systems::gui::GuiState;
+use crate::systems::mail::Mailbox;
 
 macro_rules! rebroadcast {
     ( $n:ident, $s:ident, $m:ident ) => {
         if $n.is_server() {
             debug!("broadcasting message",);
-            $n.send_server_message(&$s,None,(*$m).clone());
+            $n.send_server_message(&$s,None,$m.clone());
+        }
+    }
+}
+
+/// drop messages that only players may send. The server has
+/// already bound the sender to the connection it came from
+macro_rules! skip_spectator {
+    ( $n:ident, $m:ident ) => {
+        if $n.is_spectator(&$m.sender()) {
+            warn!("ignoring message from spectator");
+            continue;
         }
     }
 }
@@ -47,6 +66,7 @@ pub enum NetworkFlag {
     Connect,
     Disconnect,
     Send,
+    Resync,
 }
 
 #[derive(Clone,Eq,PartialEq)]
@@ -62,9 +82,25 @@ pub struct NetworkEvents {
 
 pub struct Players {
     ids: BiHashMap<ConnectionId,PlayerId>,
+    tokens: HashMap<Token,PlayerId>,
     data: IndexMap<PlayerId,PlayerData>,
 }
 
+/// What a spectator sees of the map
+#[derive(Clone, Copy, PartialEq)]
+pub enum View {
+    All,
+    Player(PlayerId),
+}
+
+/// Resume tokens and addresses the host has banned. Clients
+/// make up their own ids, so those can't be banned
+#[derive(Default)]
+pub struct Bans {
+    tokens: HashSet<Token>,
+    addresses: HashSet<IpAddr>,
+}
+
 pub struct NetworkState {
     flags: Flags<NetworkFlag>,
     mode: Mode,
@@ -74,8 +110,21 @@ pub struct NetworkState {
     events: NetworkEvents,
     pub players: Players,
     player:  PlayerId,
+    token: Option<Token>,
+    rejection: Option<String>,
     confirm: bool,
     name: String,
+    password: String,
+    max_players: usize,
+    spectators: bool,
+    spectate: bool,
+    view: View,
+    bans: Bans,
+    dropping: Vec<ConnectionId>,
+    sequence: u64,
+    inbox: Inbox<(ConnectionId,MessageData)>,
+    outbox: Outbox<MessageData>,
+    deferred: Vec<MessageData>,
     expecting: HashMap<usize,ConnectionId>,
 }
 
@@ -96,11 +145,36 @@ impl Default for Players {
     fn default() -> Self {
         Self {
             ids: BiHashMap::new(),
+            tokens: HashMap::new(),
             data: IndexMap::new(),
         }
     }
 }
 
+impl Bans {
+    pub fn add(&mut self, token: Token, address: Option<IpAddr>) {
+        self.tokens.insert(token);
+
+        // the host plays over loopback and must not lock itself out
+        if let Some(address) = address.filter(|a| !a.is_loopback()) {
+            self.addresses.insert(address);
+        }
+    }
+
+    pub fn has_token(&self, token: Token) -> bool {
+        self.tokens.contains(&token)
+    }
+
+    pub fn has_address(&self, address: &IpAddr) -> bool {
+        self.addresses.contains(address)
+    }
+
+    pub fn clear(&mut self) {
+        self.tokens.clear();
+        self.addresses.clear();
+    }
+}
+
 impl Default for NetworkEvents {
     fn default() -> Self {
         Self {
@@ -120,8 +194,21 @@ impl Default for NetworkState {
             events: NetworkEvents::default(),
             players: Players::default(),
             player: PlayerId::new(),
+            token: None,
+            rejection: None,
             confirm: false,
             name: "NAME".into(),
+            password: String::new(),
+            max_players: SLOTS,
+            spectators: false,
+            spectate: false,
+            view: View::All,
+            bans: Bans::default(),
+            dropping: Vec::new(),
+            sequence: 0,
+            inbox: Inbox::new(),
+            outbox: Outbox::new(),
+            deferred: Vec::new(),
             expecting: HashMap::new(),
         }
     }
@@ -130,23 +217,124 @@ impl Default for NetworkState {
 impl Players {
 
     pub fn insert(&mut self, id: PlayerId, conn: ConnectionId, name: String) -> PlayerData {
-        let player = PlayerData::new(id,name);
+        let player = PlayerData::new(id,name).seated(self.free_slot());
         self.ids.insert(conn,id);
         self.data.insert(id,player.clone());
         player
     }
 
-    pub fn destroy(&mut self, conn: &ConnectionId) -> Option<PlayerData> {
-        match self.ids.remove_by_left(conn) {
-            Some((_,id)) => {
-                self.data.remove(&id)
+    /// add a connected player who watches without taking a slot
+    pub fn insert_spectator(&mut self, id: PlayerId, conn: ConnectionId, name: String) -> PlayerData {
+        let player = PlayerData::new(id,name).watching();
+        self.ids.insert(conn,id);
+        self.data.insert(id,player.clone());
+        player
+    }
+
+    /// forget a player entirely, returning their connection
+    pub fn remove(&mut self, id: &PlayerId) -> Option<ConnectionId> {
+        self.tokens.retain(|_,v| v != id);
+        self.data.shift_remove(id);
+        self.ids.remove_by_right(id).map(|(c,_)| c)
+    }
+
+    /// the number of players holding a slot
+    pub fn count(&self) -> usize {
+        self.data
+            .values()
+            .filter(|p| !p.spectator)
+            .count()
+    }
+
+    /// add a player that isn't connected, such as a
+    /// computer player or the local player when offline
+    pub fn add(&mut self, player: PlayerData) {
+        let player = match player.spectator {
+            true => player,
+            false => player.seated(self.free_slot()),
+        };
+        self.data.insert(player.id,player);
+    }
+
+    /// the first lobby slot nobody has taken, or the
+    /// next one after them if the lobby is full
+    pub fn free_slot(&self) -> usize {
+        (0..SLOTS)
+            .find(|s| self.data.values().all(|p| p.order != *s))
+            .unwrap_or(self.data.len())
+    }
+
+    /// move a player to a slot if it's free, and change
+    /// their side and team
+    pub fn seat(&mut self, id: &PlayerId, slot: usize, side: Side, team: u8) -> bool {
+        let taken = self.data
+            .values()
+            .any(|p| p.order == slot && p.id != *id);
+
+        if taken || slot >= SLOTS {
+            return false;
+        }
+
+        match self.get_mut(id) {
+            Some(player) if !player.spectator => {
+                player.order = slot;
+                player.side = side;
+                player.team = team;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// drop the connection but keep the player, so that
+    /// their slot can be resumed with their token
+    pub fn disconnect(&mut self, conn: &ConnectionId) -> Option<PlayerId> {
+        let (_,id) = self.ids.remove_by_left(conn)?;
+        if let Some(player) = self.get_mut(&id) {
+            player.connected = false;
+            player.ready = false;
+        }
+        Some(id)
+    }
+
+    /// bind a new connection to the player holding the token
+    pub fn resume(&mut self, token: Token, conn: ConnectionId) -> Option<PlayerId> {
+        let id = *self.tokens.get(&token)?;
+        let player = self.data.get_mut(&id)?;
+        player.connected = true;
+        self.ids.insert(conn,id);
+        Some(id)
+    }
+
+    pub fn has_token(&self, token: Token) -> bool {
+        self.tokens.contains_key(&token)
+    }
+
+    /// the token for a player, creating one if needed
+    pub fn token(&mut self, id: &PlayerId) -> Token {
+        match self.tokens.iter().find(|(_,v)| *v == id) {
+            Some((t,_)) => *t,
+            None => {
+                let token = rand::thread_rng().gen();
+                self.tokens.insert(token,*id);
+                token
             },
-            None => None,
         }
     }
 
+    /// the connections of every player on a team
+    pub fn team(&self, team: u8) -> Vec<ConnectionId> {
+        self.data
+            .values()
+            .filter(|p| p.team == team)
+            .filter_map(|p| self.connection(&p.id))
+            .cloned()
+            .collect()
+    }
+
     pub fn clear(&mut self) {
         self.ids.clear();
+        self.tokens.clear();
         self.data.clear();
     }
 
@@ -171,6 +359,28 @@ impl Players {
             player.name = name;
         }
     }
+
+    pub fn set_ready(&mut self, id: &PlayerId, ready: bool) {
+        if let Some(player) = self.get_mut(id) {
+            player.ready = ready;
+        }
+    }
+
+    pub fn clear_ready(&mut self) {
+        for (_,player) in self.data.iter_mut() {
+            player.ready = false;
+        }
+    }
+
+    /// true if there are players and all connected ones
+    /// are ready, so that a dropped player can't stall a turn
+    pub fn all_ready(&self) -> bool {
+        !self.data.is_empty() && self
+            .data
+            .values()
+            .filter(|p| p.connected && !p.spectator)
+            .all(|p| p.ready)
+    }
 }
 
 impl NetworkEvents {
@@ -194,26 +404,27 @@ impl NetworkEvents {
         ));
     }
 
-    pub fn move_event(&mut self, sender: PlayerId, name: String, selections: &Vec<Selection>) {
+    pub fn move_event(&mut self, sender: PlayerId, name: String, context: &Context, selections: &Vec<Selection>) {
         self.messages.push((None,
             MessageData::Move(
                 MoveData {
                     header: HeaderData::new(sender,name),
                     moves: selections
                         .iter()
-                        .map(|s| (s.unit(),s.end_point(),s.current()))
+                        .map(|s| (s.unit(),s.end_point(context),s.current()))
                         .collect(),
                 }
             )
         ));
     }
 
-    pub fn chat_event(&mut self, sender: PlayerId, name: String, message: String) {
+    pub fn chat_event(&mut self, sender: PlayerId, name: String, message: String, channel: Channel) {
         self.messages.push((None,
             MessageData::Chat(
                 ChatData {
                     header: HeaderData::new(sender,name),
                     message,
+                    channel,
                 }
             )
         ));
@@ -226,20 +437,29 @@ impl NetworkEvents {
                     header: HeaderData::new(sender,name),
                     seed: state.seed(),
                     turn: state.turn(),
+                    context: *state.context(),
                     factors: state.factors(),
+                    timer: state.timer.limit,
+                    lobby: state.in_lobby(),
                     players: players,
+                    victory: state.victory.clone(),
+                    scenario: state.scenario.clone(),
+                    tiles: state.terrain.overrides.clone(),
                 }
             )
         ));
     }
 
-    pub fn join_event(&mut self, sender: PlayerId, name: String, code: usize) {
+    pub fn join_event(&mut self, sender: PlayerId, name: String, code: usize, token: Option<Token>, password: String, spectate: bool) {
         self.messages.push((None,
             MessageData::Join(
                 JoinData {
                     header: HeaderData::new(sender,name.clone()),
                     name,
                     code,
+                    token,
+                    password,
+                    spectate,
                 }
             )
         ));
@@ -257,6 +477,78 @@ impl NetworkEvents {
         ));
     }
 
+    pub fn ack_event(&mut self, conn: &ConnectionId, sender: PlayerId, name: String, seq: u64) {
+        self.messages.push((Some(*conn),
+            MessageData::Ack(
+                AckData {
+                    header: HeaderData::new(sender,name),
+                    seq,
+                }
+            )
+        ));
+    }
+
+    pub fn reject_event(&mut self, conn: &ConnectionId, sender: PlayerId, name: String, reason: String) {
+        self.messages.push((Some(*conn),
+            MessageData::Reject(
+                RejectData {
+                    header: HeaderData::new(sender,name),
+                    reason,
+                }
+            )
+        ));
+    }
+
+    pub fn session_event(&mut self, conn: &ConnectionId, sender: PlayerId, name: String, player: PlayerId, token: Token) {
+        self.messages.push((Some(*conn),
+            MessageData::Session(
+                SessionData {
+                    header: HeaderData::new(sender,name),
+                    player,
+                    token,
+                }
+            )
+        ));
+    }
+
+    pub fn snapshot_event(&mut self, conn: &ConnectionId, sender: PlayerId, name: String, snapshot: Snapshot) {
+        self.messages.push((Some(*conn),
+            MessageData::Snapshot(
+                SnapshotData {
+                    header: HeaderData::new(sender,name),
+                    snapshot,
+                }
+            )
+        ));
+    }
+
+    pub fn checksum_event(&mut self, sender: PlayerId, name: String, turn: u32, checksum: u64, units: Vec<Summary>) {
+        self.messages.push((None,
+            MessageData::Checksum(
+                ChecksumData {
+                    header: HeaderData::new(sender,name),
+                    turn,
+                    checksum,
+                    units,
+                }
+            )
+        ));
+    }
+
+    pub fn sync_event(&mut self, conn: &ConnectionId, sender: PlayerId, name: String, turn: u32, units: Vec<UnitDelta>, fortifications: Fortifications, pending: Vec<Attack>) {
+        self.messages.push((Some(*conn),
+            MessageData::Sync(
+                SyncData {
+                    header: HeaderData::new(sender,name),
+                    turn,
+                    units,
+                    fortifications,
+                    pending,
+                }
+            )
+        ));
+    }
+
     pub fn refresh_event(&mut self, sender: PlayerId, name: String) {
         self.messages.push((None,
             MessageData::Refresh(
@@ -278,6 +570,67 @@ impl NetworkEvents {
         ));
     }
 
+    pub fn ready_event(&mut self, sender: PlayerId, name: String, turn: u32, ready: bool) {
+        self.messages.push((None,
+            MessageData::Ready(
+                ReadyData {
+                    header: HeaderData::new(sender,name),
+                    turn,
+                    ready,
+                }
+            )
+        ));
+    }
+
+    pub fn seat_event(&mut self, sender: PlayerId, name: String, slot: usize, side: Side, team: u8) {
+        self.messages.push((None,
+            MessageData::Seat(
+                SeatData {
+                    header: HeaderData::new(sender,name),
+                    slot,
+                    side,
+                    team,
+                }
+            )
+        ));
+    }
+
+    pub fn attack_event(&mut self, sender: PlayerId, name: String, attack: Attack) {
+        self.messages.push((None,
+            MessageData::Attack(
+                AttackData {
+                    header: HeaderData::new(sender,name),
+                    units: attack.units,
+                    target: attack.target,
+                    fire: attack.fire,
+                }
+            )
+        ));
+    }
+
+    pub fn order_event(&mut self, sender: PlayerId, name: String, orders: Vec<(Id,Vec<Order>)>) {
+        self.messages.push((None,
+            MessageData::Order(
+                OrderData {
+                    header: HeaderData::new(sender,name),
+                    orders,
+                }
+            )
+        ));
+    }
+
+    pub fn turn_event(&mut self, sender: PlayerId, name: String, turn: u32, changes: Vec<Change>) {
+        self.messages.push((None,
+            MessageData::Turn(
+                TurnData {
+                    header: HeaderData::new(sender,name),
+                    turn,
+                    changes,
+                }
+            )
+        ));
+    }
+
 }
 
 impl NetworkState {
@@ -287,6 +640,8 @@ impl NetworkState {
         self.mode = Mode::Server;
         self.address = address;
         self.port = port;
+        self.inbox.clear();
+        self.outbox.clear();
     }
     
     pub fn connect(&mut self, address: String, port: u16) {
@@ -294,25 +649,48 @@ impl NetworkState {
         self.mode = Mode::Client;
         self.address = address;
         self.port = port;
+        self.rejection = None;
+        self.inbox.clear();
+        self.outbox.clear();
     }
 
     pub fn disconnect(&mut self) {
         self.flags.set(NetworkFlag::Disconnect);
     }
 
+    /// leave a server that refused this client, keeping the reason
+    pub fn reject(&mut self, reason: String) {
+        self.rejection = Some(reason);
+        self.disconnect();
+    }
+
+    /// connect to the last server again, resuming
+    /// the previous player if the server knows them
+    pub fn reconnect(&mut self) {
+        self.flags.set(NetworkFlag::Connect);
+        self.mode = Mode::Client;
+        self.rejection = None;
+    }
+
     pub fn send_create_event(&mut self, unit: Unit) {
         self.flags.set(NetworkFlag::Send);
         self.events.create_event(self.id(), self.name(), unit);
     }
 
-    pub fn send_move_event(&mut self, selections: &Vec<Selection>) {
+    pub fn send_move_event(&mut self, context: &Context, selections: &Vec<Selection>) {
         self.flags.set(NetworkFlag::Send);
-        self.events.move_event(self.id(), self.name(), selections);
+        self.events.move_event(self.id(), self.name(), context, selections);
     }
 
-    pub fn send_chat_event(&mut self, message: String) {
+    pub fn send_chat_event(&mut self, message: String, channel: Channel) {
         self.flags.set(NetworkFlag::Send);
-        self.events.chat_event(self.id(), self.name(), message);
+        self.events.chat_event(self.id(), self.name(), message, channel);
+    }
+
+    /// announce something about the game to every player
+    pub fn send_system_event(&mut self, message: String) {
+        self.flags.set(NetworkFlag::Send);
+        self.events.chat_event(self.id(), "Server".into(), message, Channel::System);
     }
 
     pub fn send_update_event(&mut self, state: &State) {
@@ -323,7 +701,7 @@ impl NetworkState {
 
     pub fn send_join_event(&mut self, code: usize) {
         self.flags.set(NetworkFlag::Send);
-        self.events.join_event(self.id(), self.name(), code);
+        self.events.join_event(self.id(), self.name(), code, self.token, self.password(), self.spectate);
     }
 
     pub fn send_confirm_event(&mut self, conn: &ConnectionId, code: usize) {
@@ -331,6 +709,82 @@ impl NetworkState {
         self.events.confirm_event(conn,self.id(), self.name(), self.motd(),code);
     }
 
+    pub fn send_ack_event(&mut self, conn: &ConnectionId, seq: u64) {
+        self.flags.set(NetworkFlag::Send);
+        self.events.ack_event(conn, self.id(), self.name(), seq);
+    }
+
+    pub fn send_reject_event(&mut self, conn: &ConnectionId, reason: String) {
+        self.flags.set(NetworkFlag::Send);
+        self.events.reject_event(conn, self.id(), self.name(), reason);
+    }
+
+    /// tell a connection why it can't join and drop it
+    pub fn refuse(&mut self, conn: &ConnectionId, reason: String) {
+        self.send_reject_event(conn, reason);
+        self.dropping.push(*conn);
+    }
+
+    /// remove a player from the game, dropping their connection
+    pub fn kick(&mut self, id: &PlayerId, state: &State, reason: String) {
+        if *id == self.id() {
+            return;
+        }
+
+        let name = match self.players.get(id) {
+            Some(player) => player.name.clone(),
+            None => return,
+        };
+
+        if let Some(conn) = self.players.remove(id) {
+            self.refuse(&conn, reason);
+        }
+
+        info!("removed player {} ({})", name, id);
+        self.send_system_event(format!("{} was removed from the game", name));
+        self.send_update_event(state);
+    }
+
+    /// kick a player and refuse them or their address later
+    pub fn ban(&mut self, id: &PlayerId, state: &State) {
+        if *id == self.id() {
+            return;
+        }
+
+        let address = self.players
+            .connection(id)
+            .map(|c| c.address().ip());
+
+        let token = self.players.token(id);
+        self.bans.add(token, address);
+        self.kick(id, state, "You are banned from this game".into());
+    }
+
+    pub fn send_session_event(&mut self, conn: &ConnectionId, player: PlayerId, token: Token) {
+        self.flags.set(NetworkFlag::Send);
+        self.events.session_event(conn, self.id(), self.name(), player, token);
+    }
+
+    pub fn send_snapshot_event(&mut self, conn: &ConnectionId, snapshot: Snapshot) {
+        self.flags.set(NetworkFlag::Send);
+        self.events.snapshot_event(conn, self.id(), self.name(), snapshot);
+    }
+
+    pub fn send_checksum_event(&mut self, turn: u32, checksum: u64, units: Vec<Summary>) {
+        self.flags.set(NetworkFlag::Send);
+        self.events.checksum_event(self.id(), self.name(), turn, checksum, units);
+    }
+
+    /// send a peer only the units that differ from its summaries
+    pub fn send_sync_event(&mut self, conn: &ConnectionId, state: &State, summaries: &[Summary]) {
+        let units = delta::diff(&state.units.units(), summaries);
+        let fortifications = state.fortifications.clone();
+        let pending = state.pending.clone();
+
+        self.flags.set(NetworkFlag::Send);
+        self.events.sync_event(conn, self.id(), self.name(), state.turn(), units, fortifications, pending);
+    }
+
     pub fn send_refresh_event(&mut self) {
         self.flags.set(NetworkFlag::Send);
         self.events.refresh_event(self.id(), self.name());
@@ -341,6 +795,37 @@ impl NetworkState {
         self.events.change_event(self.id(), self.name(), changes);
     }
 
+    pub fn send_ready_event(&mut self, turn: u32, ready: bool) {
+        self.flags.set(NetworkFlag::Send);
+        self.events.ready_event(self.id(), self.name(), turn, ready);
+    }
+
+    pub fn send_seat_event(&mut self, slot: usize, side: Side, team: u8) {
+        self.flags.set(NetworkFlag::Send);
+        self.events.seat_event(self.id(), self.name(), slot, side, team);
+    }
+
+    pub fn send_attack_event(&mut self, attack: Attack) {
+        self.flags.set(NetworkFlag::Send);
+        self.events.attack_event(self.id(), self.name(), attack);
+    }
+
+    pub fn send_order_event(&mut self, orders: Vec<(Id,Vec<Order>)>) {
+        self.flags.set(NetworkFlag::Send);
+        self.events.order_event(self.id(), self.name(), orders);
+    }
+
+    pub fn send_turn_event(&mut self, turn: u32, changes: Vec<Change>) {
+        self.flags.set(NetworkFlag::Send);
+        self.events.turn_event(self.id(), self.name(), turn, changes);
+    }
+
+    /// queue events on behalf of a player hosted here
+    pub fn proxy(&mut self) -> &mut NetworkEvents {
+        self.flags.set(NetworkFlag::Send);
+        &mut self.events
+    }
+
     pub fn host_requested(&self) -> bool {
         self.flags.get(NetworkFlag::Host)
     }
@@ -353,6 +838,10 @@ impl NetworkState {
         self.flags.get(NetworkFlag::Disconnect)
     }
 
+    pub fn resync_requested(&self) -> bool {
+        self.flags.get(NetworkFlag::Resync)
+    }
+
     pub fn send_requested(&self) -> bool {
         self.flags.get(NetworkFlag::Send)
     }
@@ -381,6 +870,10 @@ impl NetworkState {
         self.mode == Mode::Client
     }
 
+    pub fn is_offline(&self) -> bool {
+        self.mode == Mode::None
+    }
+
     pub fn is_confirmed(&self) -> bool {
         self.confirm
     }
@@ -389,6 +882,11 @@ impl NetworkState {
         self.flags.get(NetworkFlag::Hosting)
     }
 
+    /// true if a server has given this player a token
+    pub fn can_resume(&self) -> bool {
+        self.token.is_some()
+    }
+
     pub fn set_server(&mut self) {
         self.mode = Mode::Server;
     }
@@ -405,6 +903,10 @@ impl NetworkState {
         self.player = id;
     }
 
+    pub fn set_token(&mut self, token: Token) {
+        self.token = Some(token);
+    }
+
     pub fn set_motd(&mut self, motd: String) {
         self.motd = motd;
     }
@@ -437,18 +939,186 @@ impl NetworkState {
         self.players.clear();
     }
 
+    pub fn clear_token(&mut self) {
+        self.token = None;
+    }
+
+    pub fn clear_bans(&mut self) {
+        self.bans.clear();
+    }
+
+    pub fn password(&self) -> String {
+        self.password.clone()
+    }
+
+    pub fn max_players(&self) -> usize {
+        self.max_players
+    }
+
+    /// true if connections past the player limit may watch
+    pub fn allows_spectators(&self) -> bool {
+        self.spectators
+    }
+
+    /// true if the player only watches the game
+    pub fn is_spectator(&self, id: &PlayerId) -> bool {
+        self.players
+            .get(id)
+            .map(|p| p.spectator)
+            .unwrap_or(false)
+    }
+
+    pub fn is_spectating(&self) -> bool {
+        self.is_spectator(&self.id())
+    }
+
+    /// true if this client asks to watch when it joins
+    pub fn wants_spectate(&self) -> bool {
+        self.spectate
+    }
+
+    pub fn view(&self) -> View {
+        self.view
+    }
+
+    /// the player whose sight limits what is drawn, or
+    /// None if a spectator is shown everything
+    pub fn viewer(&self) -> Option<PlayerId> {
+        match (self.is_spectating(), self.view) {
+            (true, View::All) => None,
+            (true, View::Player(id)) => Some(id),
+            (false, _) => Some(self.id()),
+        }
+    }
+
+    /// true if the connection's address or the token it
+    /// tries to resume with was banned
+    pub fn is_banned(&self, conn: &ConnectionId, token: Option<Token>) -> bool {
+        self.bans.has_address(&conn.address().ip()) ||
+        token.map(|t| self.bans.has_token(t)).unwrap_or(false)
+    }
+
+    pub fn set_password(&mut self, password: String) {
+        self.password = password;
+    }
+
+    pub fn set_max_players(&mut self, count: usize) {
+        self.max_players = count.min(SLOTS);
+    }
+
+    pub fn set_spectators(&mut self, allowed: bool) {
+        self.spectators = allowed;
+    }
+
+    pub fn set_spectate(&mut self, spectate: bool) {
+        self.spectate = spectate;
+    }
+
+    pub fn set_view(&mut self, view: View) {
+        self.view = view;
+    }
+
+    /// hold a message until every one sent before it has arrived.
+    /// Returns false if it was already received or can't be bound
+    /// to the connection it came from
+    pub fn receive(&mut self, source: ConnectionId, mut message: MessageData) -> bool {
+        if self.is_server() && message.header().id.is_none() && !self.bind(&source, &mut message) {
+            return false;
+        }
+
+        // a client can't claim another player's stream
+        let stream = match (message.header().id, self.players.id(&source)) {
+            (Some(_), _) => Stream::Server,
+            (None, Some(id)) => Stream::Player(*id),
+            (None, None) => Stream::Connection(source),
+        };
+        let seq = message.header().seq;
+        self.inbox.push(stream, seq, (source,message))
+    }
+
+    /// replace the sender a client claims with the player its
+    /// connection belongs to. Only joins come from connections
+    /// without a player, so anything else from them is dropped
+    fn bind(&self, source: &ConnectionId, message: &mut MessageData) -> bool {
+        if let MessageData::Join(_) = message {
+            return true;
+        }
+
+        let player = match self.players.id(source).and_then(|id| self.players.get(id)) {
+            Some(p) => p,
+            None => {
+                warn!("dropping message from {} before it joined", source.address());
+                return false;
+            }
+        };
+
+        let header = message.header_mut();
+        if header.sender != player.id {
+            warn!("{} sent a message as another player", player.name);
+        }
+        header.sender = player.id;
+        header.name = player.name.clone();
+        true
+    }
+
+    /// the received messages that are next in their sequence. A
+    /// client that had to skip missing messages asks for a resync
+    pub fn take_received(&mut self) -> Vec<(ConnectionId,MessageData)> {
+        let (messages, gaps) = self.inbox.release();
+        for stream in gaps.into_iter() {
+            warn!("gave up waiting for messages from {:?}", stream);
+            if stream == Stream::Server && self.is_client() {
+                self.flags.set(NetworkFlag::Resync);
+            }
+        }
+        messages
+    }
+
+    /// the server has everything up to the sequence
+    pub fn acknowledge(&mut self, seq: u64) {
+        self.outbox.ack(seq);
+    }
+
+    /// queue every unacknowledged message to be sent again
+    pub fn resend(&mut self) {
+        let pending = self.outbox.pending();
+        if !pending.is_empty() {
+            info!("resending {} messages", pending.len());
+            self.flags.set(NetworkFlag::Send);
+            self.events.extend(pending.into_iter().map(|m| (None,m)));
+        }
+    }
+
+    /// keep an unpacked message for the system that applies it
+    pub fn defer(&mut self, message: MessageData) {
+        self.deferred.push(message);
+    }
+
+    pub fn take_deferred(&mut self) -> Vec<MessageData> {
+        std::mem::take(&mut self.deferred)
+    }
+
+    /// connections to close once their last messages are sent
+    pub fn take_dropping(&mut self) -> Vec<ConnectionId> {
+        std::mem::take(&mut self.dropping)
+    }
+
+    /// why the last server refused this client, if it did
+    pub fn rejection(&self) -> Option<String> {
+        self.rejection.clone()
+    }
+
     pub fn player_data(&self) -> Option<PlayerData> {
         self.players.data.get(&self.id()).cloned()
     }
 
+    /// every player, in slot order
     pub fn players(&self) -> Vec<PlayerData> {
         self.players
             .data
-            .iter()
-            .enumerate()
-            .map(|(i,(_,v))| v
-                .clone()
-                .ordered(i))
+            .values()
+            .cloned()
+            .sorted_by_key(|p| p.order)
             .collect()
     }
 
@@ -486,19 +1156,41 @@ impl NetworkState {
         }
 
         if !message.is_registered() {
+            // only broadcasts are sequenced, so that every
+            // client can tell when one of them went missing
+            let seq = match target {
+                Some(_) => 0,
+                None => {
+                    self.sequence += 1;
+                    self.sequence
+                }
+            };
             message.set_id(MessageId::new());
+            message.set_seq(seq);
             debug!("server sending: {:#?}",&message);
-            match target {
-                Some(id) => match server.send_message(id,message) {
-                    Err(e) => warn!("Send failed: {}",e),
-                    _ => (),
-                },
-                None => server.broadcast(message),
+
+            // bulky messages go out packed
+            match packing::pack(&message.data()) {
+                Some(packed) => Self::deliver(server,target,PackedMessage::new(packed)),
+                None => Self::deliver(server,target,message),
             };
         }
     }
 
-    fn send_client_message<T>(&mut self, client: &NetworkClient, message: T)
+    fn deliver<T>(server: &NetworkServer, target: Option<ConnectionId>, message: T)
+        where 
+            T: ClientMessage + NetworkMessage + Clone + Message + std::fmt::Debug
+    {
+        match target {
+            Some(id) => match server.send_message(id,message) {
+                Err(e) => warn!("Send failed: {}",e),
+                _ => (),
+            },
+            None => server.broadcast(message),
+        };
+    }
+
+    fn send_client_message<T>(&mut self, client: &NetworkClient, mut message: T)
         where 
             T: ServerMessage + NetworkMessage + Clone + Message + std::fmt::Debug
     {
@@ -507,8 +1199,24 @@ impl NetworkState {
         }
 
         if !message.is_registered() {
+            // resent messages keep the sequence they were first given
+            if message.seq() == 0 {
+                let seq = self.outbox.stamp();
+                message.set_seq(seq);
+
+                // the host's client can't lose its connection
+                if !self.is_server() && message.data().is_ordered() {
+                    self.outbox.retain(seq, message.data());
+                }
+            }
+
             debug!("client sending: {:#?}",&message);
-            if let Err(e) = client.send_message(message) {
+            let result = match packing::pack(&message.data()) {
+                Some(packed) => client.send_message(PackedMessage::new(packed)),
+                None => client.send_message(message),
+            };
+
+            if let Err(e) = result {
                 warn!("Send failed: {}",e);
             };
         }
@@ -516,7 +1224,7 @@ impl NetworkState {
 }
 
 fn host_system(
-    state: ResMut<State>,
+    mut state: ResMut<State>,
     mut server: ResMut<NetworkServer>,
     mut client: ResMut<NetworkClient>,
     mut network: ResMut<NetworkState>,
@@ -535,6 +1243,8 @@ fn host_system(
     network.clear_mode();
     network.clear_flags();
     network.clear_players();
+    network.clear_token();
+    network.clear_bans();
 
     // start listening to new address
     match network.address() {
@@ -542,6 +1252,7 @@ fn host_system(
             Ok(()) => {
                 info!("Hosting at {:?} ({})",address,network.id());
                 network.set_server();
+                state.open_lobby();
                 client.connect(address, NetworkSettings {
                     max_packet_length: 10 * 1024 * 1024,
                 });
@@ -588,7 +1299,7 @@ fn connect_system(
 }
 
 fn disconnect_system(
-    state: ResMut<State>,
+    mut state: ResMut<State>,
     mut server: ResMut<NetworkServer>,
     mut client: ResMut<NetworkClient>,
     mut network: ResMut<NetworkState>,
@@ -609,16 +1320,32 @@ fn disconnect_system(
     network.clear_players();
     network.clear_confirm();
 
+    // an offline game has nobody left to wait for
+    if state.in_lobby() {
+        state.start();
+    }
+
     info!("Disconnected");
 }
 
 fn event_system(
     mut commands: Commands,
     server: Res<NetworkServer>,
+    state: Res<State>,
     mut network: ResMut<NetworkState>,
     mut events: EventReader<ServerNetworkEvent>,
+    mut client_events: EventReader<ClientNetworkEvent>,
 ) {
 
+    for event in client_events.iter() {
+        if let ClientNetworkEvent::Disconnected = event {
+            if network.is_client() {
+                warn!("Lost connection to server");
+                network.clear_confirm();
+            }
+        }
+    }
+
     if !network.is_server() {
         return;
     }
@@ -626,23 +1353,87 @@ fn event_system(
     for event in events.iter() {
         match event {
             ServerNetworkEvent::Connected(conn) => {
-                let code = network.start_waiting(*conn);
-                network.send_confirm_event(conn,code);
+                if network.bans.has_address(&conn.address().ip()) {
+                    info!("refused banned address {}", conn.address());
+                    network.refuse(conn, "You are banned from this game".into());
+                    continue;
+                }
+
+                // versions are compared before any game message is sent
+                if let Err(e) = server.send_message(*conn, ServerHello::current()) {
+                    warn!("failed to greet connection: {}", e);
+                }
             },
             ServerNetworkEvent::Disconnected(conn) => {
-                network.players.destroy(conn);
+                // the player is kept so they can resume later
+                if let Some(id) = network.players.disconnect(conn) {
+                    info!("player {} disconnected", id);
+                    if let Some(player) = network.players.get(&id).cloned() {
+                        network.send_system_event(format!("{} disconnected", player.name));
+                    }
+                    network.send_update_event(&state);
+                }
             },
             _ => ()
         };
     }
 }
 
+/// Compare versions with the frozen hello messages, so that
+/// clients that can't join are told why before anything else
+fn handshake_system(
+    client: Res<NetworkClient>,
+    server: Res<NetworkServer>,
+    mut gui: ResMut<GuiState>,
+    mut network: ResMut<NetworkState>,
+    mut server_hellos: EventReader<NetworkData<ServerHello>>,
+    mut client_hellos: EventReader<NetworkData<ClientHello>>,
+) {
+    for hello in server_hellos.iter() {
+        if !network.is_client() {
+            continue;
+        }
+
+        match Version::current().check(&hello.version) {
+            Ok(_) => if let Err(e) = client.send_message(ClientHello::current()) {
+                warn!("failed to greet server: {}", e);
+            },
+            Err(e) => {
+                network.reject(e.to_string());
+                gui.add_system(e.to_string());
+            }
+        }
+    }
+
+    for hello in client_hellos.iter() {
+        if !network.is_server() {
+            continue;
+        }
+
+        let conn = hello.source();
+        match hello.version.check(&Version::current()) {
+            Ok(_) => {
+                let code = network.start_waiting(*conn);
+                network.send_confirm_event(conn,code);
+            },
+            Err(e) => {
+                // the client can't read a reject from this build, so just drop it
+                info!("refused {}: {}", conn.address(), e);
+                if let Err(e) = server.disconnect(*conn) {
+                    warn!("failed to drop connection: {}", e);
+                }
+            }
+        }
+    }
+}
+
 fn send_system(
-    state: ResMut<State>,
+    mut state: ResMut<State>,
     client: Res<NetworkClient>,
     server: Res<NetworkServer>,
     mut gui: ResMut<GuiState>,
     mut network: ResMut<NetworkState>,
+    mut mailbox: ResMut<Mailbox>,
 ) {
     if !state.is_loaded() {
         return;
@@ -655,91 +1446,363 @@ fn send_system(
     network.clear_flags();
 
     for (target,message) in network.events.take().into_iter() {
+        // local play is recorded as it's first sent, remote play as it arrives
+        let fresh = message.header().seq == 0;
+        if fresh && (network.is_offline() || message.header().sender == network.id()) {
+            state.recording.message(message.clone());
+        }
+
+        // turns played by mail are kept until they're sent
+        if fresh && network.is_offline() {
+            mailbox.message(message.clone());
+        }
+
         match message {
             MessageData::Update(v)  => network.send_server_message(&server,target,UpdateMessage::new(v)),
             MessageData::Confirm(v) => network.send_server_message(&server,target,ConfirmMessage::new(v)),
+            MessageData::Reject(v) => network.send_server_message(&server,target,RejectMessage::new(v)),
+            MessageData::Ack(v)     => network.send_server_message(&server,target,AckMessage::new(v)),
+            MessageData::Session(v) => network.send_server_message(&server,target,SessionMessage::new(v)),
+            MessageData::Snapshot(v) => network.send_server_message(&server,target,SnapshotMessage::new(v)),
+            MessageData::Sync(v)    => network.send_server_message(&server,target,SyncMessage::new(v)),
+            MessageData::Checksum(v) => network.send_client_message(&client,ChecksumMessage::new(v)),
             MessageData::Chat(v)    => network.send_client_message(&client,ChatMessage::new(v)),
             MessageData::Create(v)  => network.send_client_message(&client,CreateMessage::new(v)),
             MessageData::Move(v)    => network.send_client_message(&client,MoveMessage::new(v)),
             MessageData::Refresh(v) => network.send_client_message(&client,RefreshMessage::new(v)),
             MessageData::Change(v)  => network.send_client_message(&client,ChangeMessage::new(v)),
             MessageData::Join(v)    => network.send_client_message(&client,JoinMessage::new(v)),
+            MessageData::Ready(v)   => network.send_client_message(&client,ReadyMessage::new(v)),
+            MessageData::Seat(v)    => network.send_client_message(&client,SeatMessage::new(v)),
+            MessageData::Attack(v)  => network.send_client_message(&client,AttackMessage::new(v)),
+            MessageData::Turn(v)    => network.send_server_message(&server,target,TurnMessage::new(v)),
+            MessageData::Order(v)   => network.send_client_message(&client,OrderMessage::new(v)),
             _ => (),
         };
     }
+
+    // refused and kicked players are dropped after their reason is sent
+    for conn in network.take_dropping().into_iter() {
+        if let Err(e) = server.disconnect(conn) {
+            warn!("failed to drop connection: {}", e);
+        }
+    }
+}
+
+/// Readers for messages that change the game
+#[derive(SystemParam)]
+struct GameReaders<'a> {
+    create: EventReader<'a, NetworkData<CreateMessage>>,
+    change: EventReader<'a, NetworkData<ChangeMessage>>,
+    moves: EventReader<'a, NetworkData<MoveMessage>>,
+    order: EventReader<'a, NetworkData<OrderMessage>>,
+    chat: EventReader<'a, NetworkData<ChatMessage>>,
+    update: EventReader<'a, NetworkData<UpdateMessage>>,
+    refresh: EventReader<'a, NetworkData<RefreshMessage>>,
+    ready: EventReader<'a, NetworkData<ReadyMessage>>,
+    attack: EventReader<'a, NetworkData<AttackMessage>>,
+    turn: EventReader<'a, NetworkData<TurnMessage>>,
+    seat: EventReader<'a, NetworkData<SeatMessage>>,
+}
+
+/// Readers for messages that manage the connection
+#[derive(SystemParam)]
+struct SessionReaders<'a> {
+    join: EventReader<'a, NetworkData<JoinMessage>>,
+    confirm: EventReader<'a, NetworkData<ConfirmMessage>>,
+    session: EventReader<'a, NetworkData<SessionMessage>>,
+    snapshot: EventReader<'a, NetworkData<SnapshotMessage>>,
+    sync: EventReader<'a, NetworkData<SyncMessage>>,
+    ack: EventReader<'a, NetworkData<AckMessage>>,
+    checksum: EventReader<'a, NetworkData<ChecksumMessage>>,
+    packed: EventReader<'a, NetworkData<PackedMessage>>,
 }
 
+/// Apply every sequenced message in the order it was sent
 fn receive_system(
-    client: Res<NetworkClient>,
     server: Res<NetworkServer>,
 
     mut state: ResMut<State>,
+    mut context: ResMut<Context>,
     mut gui: ResMut<GuiState>,
     mut network: ResMut<NetworkState>,
     mut tilemap: Query<&mut Tilemap>,
 
-    mut join_messages: EventReader<NetworkData<JoinMessage>>,
-    mut confirm_messages: EventReader<NetworkData<ConfirmMessage>>,
-    mut change_messages: EventReader<NetworkData<ChangeMessage>>,
-    mut create_messages: EventReader<NetworkData<CreateMessage>>,
-    mut move_messages: EventReader<NetworkData<MoveMessage>>,
-    mut chat_messages: EventReader<NetworkData<ChatMessage>>,
-    mut update_messages: EventReader<NetworkData<UpdateMessage>>,
-    mut refresh_messages: EventReader<NetworkData<RefreshMessage>>,
+    mut game: GameReaders,
+    mut session: SessionReaders,
 ) {
+    // gather everything that arrived so it can be applied in the order
+    // each peer sent it, rather than in the order of the readers below
+    let mut inbox: Vec<(ConnectionId,MessageData)> = vec![];
+    inbox.extend(game.create.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(game.change.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(game.moves.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(game.order.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(game.chat.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(game.update.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(game.refresh.iter().map(|m| (*m.source(),m.data())));
+    inbox.extend(game.ready.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(game.attack.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(game.turn.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(game.seat.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(session.join.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(session.confirm.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(session.session.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(session.snapshot.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(session.sync.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(session.ack.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+    inbox.extend(session.checksum.iter().filter(|m| !m.is_applied()).map(|m| (*m.source(),m.data())));
+
+    for message in session.packed.iter() {
+        match packing::unpack(message.value()) {
+            Ok(data) => inbox.push((*message.source(),data)),
+            Err(e) => warn!("failed to unpack message: {}", e),
+        }
+    }
+
+    // sequences only increase, so a stable sort keeps each peer's
+    // order. Anything that arrives ahead of a gap is held back
+    inbox.sort_by_key(|(_,d)| d.header().seq);
+    for (source,data) in inbox.into_iter() {
+        if !network.receive(source, data) {
+            debug!("dropping message");
+        }
+    }
+
+    // messages that arrive while loading are held until the map is ready
     if !state.is_loaded() {
         return;
     }
 
     let mut map = tilemap.single_mut().expect("Need tilemap");
 
-    for message in create_messages.iter().filter(|m| !m.is_applied()) {
-        debug!("received create message");
-        message.apply(&network, &mut map, &mut state);
-        rebroadcast!(network,server,message);
+    let mut advanced = false;
+    let mut acks: HashMap<ConnectionId,u64> = HashMap::new();
+    for (source,data) in network.take_received().into_iter() {
+
+        // clients are told what the server has so they can resend the rest
+        if network.is_server() && data.header().id.is_none() {
+            let seq = acks.entry(source).or_insert(0);
+            *seq = (*seq).max(data.header().seq);
+        }
+
+        if data.header().id.is_some() && data.header().sender != network.id() {
+            state.recording.message(data.clone());
+        }
+
+        match data {
+            MessageData::Create(v) => {
+                debug!("received create message");
+                let message = CreateMessage::new(v);
+                skip_spectator!(network,message);
+
+                // deployment zones are enforced here, not just by the client
+                if network.is_server() && !state.can_create(&message.sender(), &message.value().unit) {
+                    warn!("refusing unit placed by {}", message.name());
+                    continue;
+                }
+
+                message.apply(&network, &mut map, &mut state);
+                rebroadcast!(network,server,message);
+            },
+            MessageData::Change(v) => {
+                debug!("received change message");
+                let message = ChangeMessage::new(v);
+                skip_spectator!(network,message);
+                message.apply(&network, &mut map, &mut state);
+                rebroadcast!(network,server,message);
+            },
+            MessageData::Move(v) => {
+                debug!("received move message");
+                let message = MoveMessage::new(v);
+                skip_spectator!(network,message);
+                message.apply(&network, &mut map, &mut state);
+                rebroadcast!(network,server,message);
+            },
+            MessageData::Order(v) => {
+                debug!("received order message");
+                let message = OrderMessage::new(v);
+                skip_spectator!(network,message);
+                message.apply(&network, &mut state);
+                rebroadcast!(network,server,message);
+            },
+            MessageData::Chat(mut v) => {
+                debug!("received chat message");
+
+                // only the server makes announcements, and players
+                // can only talk on their own team's channel
+                if network.is_server() && v.header.id.is_none() {
+                    match v.channel {
+                        Channel::System => {
+                            warn!("ignoring announcement from {}", v.header.name);
+                            continue;
+                        },
+                        Channel::Team(_) => v.channel = Channel::Team(state.teams.team(&v.header.sender)),
+                        Channel::All => (),
+                    }
+                }
+
+                let message = ChatMessage::new(v);
+                message.apply(&network, &mut gui);
+
+                // team messages only go to the team
+                match message.value().channel {
+                    Channel::Team(team) if network.is_server() => {
+                        for conn in network.players.team(team) {
+                            network.send_server_message(&server,Some(conn),message.clone());
+                        }
+                    },
+                    _ => rebroadcast!(network,server,message),
+                }
+            },
+            // will only apply to the server
+            MessageData::Join(v) => {
+                debug!("received join message");
+                JoinMessage::new(v).apply(&mut network, &mut state);
+            },
+            // will only apply to client
+            MessageData::Confirm(v) => {
+                debug!("received confirm message");
+                ConfirmMessage::new(v).apply(&mut network, &mut gui);
+            },
+            // will only apply to client
+            MessageData::Update(v) => {
+                debug!("received update message");
+                UpdateMessage::new(v).apply(&mut network, &mut context, &mut state);
+            },
+            // will only apply to the server
+            MessageData::Refresh(v) => {
+                debug!("received refresh message");
+                RefreshMessage::new(v).apply(&mut network, &state);
+            },
+            // will only apply to the server
+            MessageData::Attack(v) => {
+                debug!("received attack message");
+                let message = AttackMessage::new(v);
+                skip_spectator!(network,message);
+                message.apply(&network, &mut state);
+            },
+            // will only apply to the server
+            MessageData::Ready(v) => {
+                debug!("received ready message");
+                ReadyMessage::new(v).apply(&mut network, &source, &state);
+            },
+            MessageData::Turn(v) => {
+                debug!("received turn message");
+                TurnMessage::new(v).apply(&mut network, &mut map, &mut state);
+                advanced = true;
+            },
+            // will only apply to the server
+            MessageData::Checksum(v) => {
+                debug!("received checksum message");
+                ChecksumMessage::new(v).apply(&mut network, &state);
+            },
+            // will only apply to the server
+            MessageData::Seat(v) => {
+                debug!("received seat message");
+                SeatMessage::new(v).apply(&mut network, &state);
+            },
+            // will only apply to clients
+            MessageData::Session(v) => {
+                debug!("received session message");
+                SessionMessage::new(v).apply(&mut network);
+            },
+            // will only apply to clients
+            MessageData::Ack(v) => {
+                debug!("received ack message");
+                AckMessage::new(v).apply(&mut network);
+            },
+            // snapshots and syncs are restored by the session system
+            data @ MessageData::Snapshot(_) |
+            data @ MessageData::Sync(_) => network.defer(data),
+            _ => (),
+        }
     }
 
-    for message in change_messages.iter().filter(|m| !m.is_applied()) {
-        debug!("received change message");
-        message.apply(&network, &mut map, &mut state);
-        rebroadcast!(network,server,message);
+    if advanced {
+        gui.add_system(format!("{} has begun", state.calendar));
     }
 
-    for message in move_messages.iter().filter(|m| !m.is_applied()) {
-        debug!("received move message");
-        message.apply(&network, &mut map, &mut state);
-        rebroadcast!(network,server,message);
+    // let the server check that this peer agrees with it
+    if advanced && network.is_client() {
+        let checksum = state.checksum();
+        network.send_checksum_event(state.turn(), checksum, state.summaries());
     }
 
-    for message in chat_messages.iter().filter(|m| !m.is_applied()) {
-        debug!("received chat message");
-        message.apply(&network, &mut gui);
-        rebroadcast!(network,server,message);
+    // the server decides when the game is over
+    if advanced && network.is_server() && state.judge() {
+        info!("game over on turn {}", state.turn());
+        network.send_update_event(&state);
+        network.send_system_event("The game is over".into());
     }
 
-    // will only apply to the server
-    for message in join_messages.iter().filter(|m| !m.is_applied()) {
-        debug!("received join message");
-        message.apply(&mut network, &state);
+    for (conn,seq) in acks.into_iter() {
+        network.send_ack_event(&conn, seq);
     }
+}
 
-    // will only apply to client
-    for message in confirm_messages.iter().filter(|m| !m.is_applied()) {
-        debug!("received confirm message");
+/// Take the id and token given by the server and restore
+/// snapshots once the map matches the server's.
+fn session_system(
+    mut state: ResMut<State>,
+    mut context: ResMut<Context>,
+    mut network: ResMut<NetworkState>,
+    mut gui: ResMut<GuiState>,
+    mut tilemap: Query<&mut Tilemap>,
+    mut waiting: Local<Option<Snapshot>>,
+    mut reject_messages: EventReader<NetworkData<RejectMessage>>,
+) {
+    // will only apply to clients
+    for message in reject_messages.iter().filter(|m| !m.is_applied()) {
+        debug!("received reject message");
         message.apply(&mut network, &mut gui);
     }
 
-    // will only apply to client
-    for message in update_messages.iter().filter(|m| !m.is_applied()) {
-        debug!("received update message");
-        message.apply(&mut network, &mut state);
+    // snapshots and syncs are put in order by the receive system
+    let mut snapshots: Vec<SnapshotMessage> = vec![];
+    let mut syncs: Vec<SyncMessage> = vec![];
+    for data in network.take_deferred().into_iter() {
+        match data {
+            MessageData::Snapshot(v) => snapshots.push(SnapshotMessage::new(v)),
+            MessageData::Sync(v) => syncs.push(SyncMessage::new(v)),
+            _ => (),
+        }
+    }
+
+    // will only apply to clients
+    for message in snapshots.iter().filter(|m| !m.is_applied()) {
+        debug!("received snapshot message");
+        if let Some(snapshot) = message.apply(&network) {
+            state.resize(&mut context, snapshot.context);
+            *waiting = Some(snapshot);
+        }
     }
 
-    // should only be received by server
-    if refresh_messages.iter().count() > 0 {
-        if network.is_server() {
-            debug!("broadcasting update message");
-            network.send_update_event(&state);
+    // wait for a rebuilt map before placing units
+    if !state.is_loaded() || state.events.receive(Action::RebuildMap) {
+        return;
+    }
+
+    let mut map = tilemap.single_mut().expect("Need tilemap");
+
+    if let Some(snapshot) = waiting.take() {
+        state.restore(&mut map, snapshot);
+    }
+
+    // a map resized mid-game loses its units, so send the server
+    // what's left and let it sync everything that differs
+    if network.resync_requested() {
+        network.clear_flag(NetworkFlag::Resync);
+        let checksum = state.checksum();
+        network.send_checksum_event(state.turn(), checksum, state.summaries());
+    }
+
+    // will only apply to clients. While a snapshot is being
+    // restored syncs are dropped, since it replaces everything
+    for message in syncs.iter().filter(|m| !m.is_applied()) {
+        debug!("received sync message");
+        if let Some(data) = message.apply(&network) {
+            state.patch(&mut map, data);
         }
     }
 }
@@ -750,7 +1813,102 @@ impl Plugin for NetworkPlugin {
            .add_system(connect_system.system())
            .add_system(disconnect_system.system())
            .add_system(event_system.system())
+           .add_system(handshake_system.system())
            .add_system(send_system.system())
-           .add_system(receive_system.system());
+           .add_system(receive_system.system())
+           .add_system(session_system.system());
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_players_take_free_slots() {
+        let mut players = Players::default();
+        let (a, b, c) = (PlayerId::new(), PlayerId::new(), PlayerId::new());
+
+        players.add(PlayerData::new(a, "a".into()));
+        players.add(PlayerData::new(b, "b".into()));
+        assert!(players.seat(&b, 3, Side::Green, 2));
+
+        players.add(PlayerData::new(c, "c".into()));
+        assert_eq!(players.get(&c).map(|p| p.order), Some(1));
+        assert_eq!(players.get(&b).map(|p| p.side), Some(Side::Green));
+    }
+
+    #[test]
+    fn test_taken_slot_is_refused() {
+        let mut players = Players::default();
+        let (a, b) = (PlayerId::new(), PlayerId::new());
+
+        players.add(PlayerData::new(a, "a".into()));
+        players.add(PlayerData::new(b, "b".into()));
+
+        assert!(!players.seat(&b, 0, Side::Blue, 1));
+        assert!(!players.seat(&b, SLOTS, Side::Blue, 1));
+        assert_eq!(players.get(&b).map(|p| p.order), Some(1));
+    }
+
+    #[test]
+    fn test_dropped_players_dont_stall_turn() {
+        let mut players = Players::default();
+        let (a, b) = (PlayerId::new(), PlayerId::new());
+
+        players.add(PlayerData::new(a, "a".into()));
+        players.add(PlayerData::new(b, "b".into()));
+        players.set_ready(&a, true);
+        assert!(!players.all_ready());
+
+        if let Some(player) = players.get_mut(&b) {
+            player.connected = false;
+        }
+        assert!(players.all_ready());
+    }
+
+    #[test]
+    fn test_bans_follow_tokens_and_addresses() {
+        let mut bans = Bans::default();
+        let loopback: IpAddr = "127.0.0.1".parse().unwrap();
+        let remote: IpAddr = "10.0.0.2".parse().unwrap();
+
+        bans.add(7, Some(loopback));
+        bans.add(8, Some(remote));
+
+        assert!(bans.has_token(7));
+        assert!(!bans.has_token(9));
+        assert!(!bans.has_address(&loopback));
+        assert!(bans.has_address(&remote));
+    }
+
+    #[test]
+    fn test_removed_player_cannot_resume() {
+        let mut players = Players::default();
+        let (a, b) = (PlayerId::new(), PlayerId::new());
+
+        players.add(PlayerData::new(a, "a".into()));
+        players.add(PlayerData::new(b, "b".into()));
+        let token = players.token(&b);
+
+        players.remove(&b);
+        assert!(!players.has_token(token));
+        assert_eq!(players.count(), 1);
+        assert_eq!(players.free_slot(), 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_spectator_view() {
+        let mut network = NetworkState::default();
+        let other = PlayerId::new();
+
+        assert_eq!(network.viewer(), Some(network.id()));
+
+        let id = network.id();
+        network.players.add(PlayerData::new(id, "a".into()).watching());
+        assert_eq!(network.viewer(), None);
+        assert_eq!(network.players.count(), 0);
+
+        network.set_view(View::Player(other));
+        assert_eq!(network.viewer(), Some(other));
+    }
+}
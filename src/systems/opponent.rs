@@ -0,0 +1,283 @@
+use bevy::prelude::*;
+use bevy_tilemap::Tilemap;
+use std::collections::{HashMap, HashSet};
+
+use crate::behavior::{Difficulty, Opponent, Plan};
+use crate::generation::{Id, PlayerId, Specialty, Unit};
+use crate::networking::messages::PlayerData;
+use crate::objects::Point;
+use crate::state::traits::*;
+use crate::state::State;
+use crate::systems::network::NetworkState;
+
+/// units given to a computer player when it joins
+const FORCE: [Specialty; 5] = [
+    Specialty::Infantry,
+    Specialty::Infantry,
+    Specialty::Armor,
+    Specialty::Militia,
+    Specialty::Artillery,
+];
+
+pub struct OpponentPlugin;
+
+/// Computer players run by this game
+#[derive(Default)]
+pub struct OpponentState {
+    opponents: Vec<Opponent>,
+    requests: Vec<Difficulty>,
+    planned: HashMap<PlayerId, u32>,
+
+    /// players whose units have reached the board
+    deployed: HashSet<PlayerId>,
+
+    /// plans sent to the server that must be applied before
+    /// the player is ready, with the turn they were made for
+    sent: HashMap<PlayerId, (u32, Plan)>,
+}
+
+impl OpponentState {
+    /// ask for a computer player to join the game
+    pub fn request(&mut self, difficulty: Difficulty) {
+        self.requests.push(difficulty);
+    }
+
+    /// add a computer player that brings its own units
+    pub fn add(&mut self, opponent: Opponent) {
+        self.opponents.push(opponent);
+    }
+
+    pub fn opponents(&self) -> &Vec<Opponent> {
+        &self.opponents
+    }
+}
+
+/// Add requested computer players, deploy their units and keep
+/// them in a player slot for as long as this game is running.
+fn opponent_join_system(
+    mut state: ResMut<State>,
+    mut network: ResMut<NetworkState>,
+    mut opponents: ResMut<OpponentState>,
+    mut map_query: Query<&mut Tilemap>,
+) {
+    if !state.is_loaded() || network.is_client() {
+        return;
+    }
+
+    // a single-player game needs a slot for the local player
+    if network.is_offline() && !opponents.requests.is_empty() && network.player_data().is_none() {
+        let player = PlayerData::new(network.id(), network.name());
+        network.players.add(player);
+    }
+
+    let mut joined = vec![];
+    for difficulty in opponents.requests.drain(..).collect::<Vec<_>>() {
+        let name = format!("Computer {}", opponents.opponents.len() + 1);
+        let opponent = Opponent::new(name, difficulty);
+        opponents.opponents.push(opponent.clone());
+        joined.push(opponent);
+    }
+
+    let mut changed = false;
+    for opponent in opponents.opponents.iter() {
+        if network.players.get(&opponent.id()).is_none() {
+            let player = PlayerData::new(opponent.id(), opponent.name())
+                .with_computer(opponent.difficulty());
+            network.players.add(player);
+            changed = true;
+        }
+    }
+
+    if changed && network.is_server() {
+        network.send_update_event(&state);
+    }
+
+    if joined.is_empty() {
+        return;
+    }
+
+    let mut map = map_query.single_mut().expect("Need tilemap");
+
+    for opponent in joined.into_iter() {
+        let data = match network.players().into_iter().find(|p| p.id == opponent.id()) {
+            Some(d) => d,
+            None => continue,
+        };
+
+        let units = deploy(&state, &opponent, data);
+
+        // offline units are placed directly, hosted units
+        // are created through the server like any other
+        for unit in units.into_iter() {
+            if network.is_offline() {
+                let point = *unit.position();
+                unit.insert(&mut map);
+                state.units.add(point, unit);
+            }
+            else {
+                network.proxy().create_event(opponent.id(), opponent.name(), unit);
+            }
+        }
+    }
+}
+
+/// Plan the turn for every computer player once per turn and
+/// send the orders the same way a human player would, readying
+/// only once the server has applied them.
+fn opponent_turn_system(
+    mut state: ResMut<State>,
+    mut network: ResMut<NetworkState>,
+    mut opponents: ResMut<OpponentState>,
+) {
+    if !state.is_loaded() || state.in_lobby() || network.is_client() {
+        return;
+    }
+
+    let turn = state.turn();
+
+    // hosted units arrive through the server some time after joining
+    let arrived: Vec<PlayerId> = opponents
+        .opponents
+        .iter()
+        .map(|o| o.id())
+        .filter(|id| state.units.units().iter().any(|u| u.player_id() == id))
+        .collect();
+    opponents.deployed.extend(arrived);
+
+    let applied: Vec<(PlayerId, u32)> = opponents
+        .sent
+        .iter()
+        .filter(|(id, (_, plan))| is_applied(&state, id, plan))
+        .map(|(id, (planned, _))| (*id, *planned))
+        .collect();
+
+    for (id, planned) in applied.into_iter() {
+        opponents.sent.remove(&id);
+
+        // plans for a turn that already ended are dropped
+        let name = opponents.opponents.iter().find(|o| o.id() == id).map(|o| o.name());
+        if let (Some(name), true) = (name, planned == turn) {
+            network.proxy().ready_event(id, name, turn, true);
+        }
+    }
+
+    let waiting: Vec<Opponent> = opponents
+        .opponents
+        .iter()
+        .filter(|o| opponents.deployed.contains(&o.id()))
+        .filter(|o| opponents.planned.get(&o.id()) != Some(&turn))
+        .cloned()
+        .collect();
+
+    for opponent in waiting.into_iter() {
+        let mut plan = opponent.plan(&state);
+        let id = opponent.id();
+        let name = opponent.name();
+
+        if network.is_offline() {
+            state.units.set_orders(&id, &plan.orders);
+            for attack in plan.attacks.into_iter() {
+                state.queue_attack(attack);
+            }
+        }
+        else {
+            // only send what the server will accept, so that it's
+            // possible to tell when all of it has been applied
+            let mut used: HashSet<Id> = HashSet::new();
+            plan.attacks = plan.attacks
+                .iter()
+                .map(|a| state.affordable(a))
+                .map(|mut a| {
+                    a.units.retain(|u| used.insert(*u));
+                    a
+                })
+                .filter(|a| !a.units.is_empty())
+                .collect();
+
+            if !plan.orders.is_empty() {
+                network.proxy().order_event(id, name.clone(), plan.orders.clone());
+            }
+
+            for attack in plan.attacks.iter() {
+                network.proxy().attack_event(id, name.clone(), attack.clone());
+            }
+
+            opponents.sent.insert(id, (turn, plan));
+        }
+
+        opponents.planned.insert(id, turn);
+    }
+}
+
+/// true once the server holds every order and attack in a plan
+fn is_applied(state: &State, player: &PlayerId, plan: &Plan) -> bool {
+    let ordered = plan.orders.iter().all(|(id, orders)| {
+        state.units
+            .get_all_ids(&vec![*id])
+            .first()
+            .map(|u| u.orders().list() == orders)
+            .unwrap_or(true)
+    });
+
+    let attacked = plan.attacks.iter().all(|attack| {
+        state.pending
+            .iter()
+            .filter(|p| p.player == *player && p.target == attack.target)
+            .any(|p| attack.units.iter().all(|u| p.units.contains(u)))
+    });
+
+    ordered && attacked
+}
+
+/// build a force for a computer player on the free ground
+/// farthest from everyone else
+fn deploy(state: &State, opponent: &Opponent, data: PlayerData) -> Vec<Unit> {
+    let others: Vec<Point> = state.units
+        .units()
+        .into_iter()
+        .filter(|u| u.player_id() != &opponent.id())
+        .map(|u| *u.position())
+        .collect();
+
+    let start = state.areas
+        .keys()
+        .map(|l| Point::from(*l))
+        .filter(|p| state.units.space(p) > 0)
+        .max_by_key(|p| others
+            .iter()
+            .map(|o| o.distance(*p))
+            .min()
+            .unwrap_or(0));
+
+    let start = match start {
+        Some(p) => p,
+        None => return vec![],
+    };
+
+    let mut points = vec![start];
+    points.extend(start
+        .neighbors(state.context())
+        .into_iter()
+        .filter(|p| state.areas.contains_key(&p.integers())));
+
+    FORCE
+        .iter()
+        .zip(points.into_iter().cycle())
+        .enumerate()
+        .map(|(i, (specialty, point))| Unit::new(opponent.id())
+            .with_name(format!("{:?} {}", specialty, i + 1))
+            .with_player(data.clone())
+            .with_specialty(specialty.clone())
+            .with_soldiers(100)
+            .with_position(point)
+            .build(state))
+        .collect()
+}
+
+impl Plugin for OpponentPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<OpponentState>()
+           .add_system(opponent_join_system.system())
+           .add_system(opponent_turn_system.system());
+    }
+}
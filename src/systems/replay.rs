@@ -0,0 +1,251 @@
+use bevy::prelude::*;
+use bevy_tilemap::Tilemap;
+
+use crate::generation::id::MessageId;
+use crate::networking::messages::*;
+use crate::state::{Action, Context, Replay, Snapshot, State};
+use crate::systems::gui::GuiState;
+use crate::systems::network::NetworkState;
+
+/// seconds between steps while playing
+const DELAY: f32 = 0.5;
+
+pub struct ReplayPlugin;
+
+/// A replay being watched and how far it has got
+pub struct Playback {
+    replay: Option<Replay>,
+    frame: usize,
+    step: usize,
+    playing: bool,
+    stepping: bool,
+    elapsed: f32,
+    seek: Option<usize>,
+    waiting: Option<Snapshot>,
+
+    /// stands in for the server that sent the recorded messages
+    network: NetworkState,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            replay: None,
+            frame: 0,
+            step: 0,
+            playing: false,
+            stepping: false,
+            elapsed: 0.0,
+            seek: None,
+            waiting: None,
+            network: NetworkState::default(),
+        }
+    }
+}
+
+impl Playback {
+    /// start watching a replay from its first turn
+    pub fn load(&mut self, replay: Replay) {
+        self.replay = Some(replay);
+        self.playing = false;
+        self.seek(0);
+    }
+
+    pub fn close(&mut self) {
+        self.replay = None;
+        self.playing = false;
+        self.waiting = None;
+        self.seek = None;
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// the number of recorded turns
+    pub fn len(&self) -> usize {
+        self.replay
+            .as_ref()
+            .map(|r| r.len())
+            .unwrap_or(0)
+    }
+
+    /// the recorded turn being played
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// the message within the turn that plays next
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// play a single message or turn resolution
+    pub fn step_once(&mut self) {
+        self.playing = false;
+        self.stepping = true;
+    }
+
+    /// jump to the start of a recorded turn
+    pub fn seek(&mut self, frame: usize) {
+        if frame < self.len() {
+            self.seek = Some(frame);
+        }
+    }
+
+    /// true if the next step is due
+    fn tick(&mut self, delta: f32) -> bool {
+        if self.stepping {
+            self.stepping = false;
+            return true;
+        }
+
+        if !self.playing {
+            return false;
+        }
+
+        self.elapsed += delta;
+        if self.elapsed < DELAY {
+            return false;
+        }
+
+        self.elapsed = 0.0;
+        true
+    }
+
+    /// apply the next recorded message, or resolve the
+    /// turn once they have all been applied
+    fn next(&mut self, map: &mut Tilemap, state: &mut State, gui: &mut GuiState) {
+        let index = self.frame;
+        let frame = match self.replay.as_ref().and_then(|r| r.frames.get(index)) {
+            Some(frame) => frame,
+            None => {
+                self.playing = false;
+                return;
+            }
+        };
+
+        if let Some(message) = frame.messages.get(self.step) {
+            apply(&self.network, map, state, gui, message.clone());
+            self.step += 1;
+            return;
+        }
+
+        state.advance(map, frame.turn, &frame.changes);
+
+        // a different result means application isn't deterministic
+        if state.checksum() != frame.checksum {
+            warn!("replay drifted on turn {}", frame.turn);
+            gui.add_system(format!("The replay drifted from the recording on turn {}", frame.turn));
+        }
+
+        gui.add_system(format!("{} has begun", state.calendar));
+        if state.judge() {
+            gui.add_system("The game is over".into());
+        }
+
+        self.frame += 1;
+        self.step = 0;
+    }
+}
+
+/// apply a recorded message as though the server had just sent it,
+/// with a new id so that it isn't mistaken for one already applied
+pub fn apply(network: &NetworkState, map: &mut Tilemap, state: &mut State, gui: &mut GuiState, message: MessageData) {
+    match message {
+        MessageData::Create(v) => {
+            let mut message = CreateMessage::new(v);
+            message.set_id(MessageId::new());
+            message.apply(network, map, state);
+        },
+        MessageData::Change(v) => {
+            let mut message = ChangeMessage::new(v);
+            message.set_id(MessageId::new());
+            message.apply(network, map, state);
+        },
+        MessageData::Move(v) => {
+            let mut message = MoveMessage::new(v);
+            message.set_id(MessageId::new());
+            message.apply(network, map, state);
+        },
+        MessageData::Order(v) => {
+            let mut message = OrderMessage::new(v);
+            message.set_id(MessageId::new());
+            message.apply(network, state);
+        },
+        MessageData::Chat(v) => {
+            let mut message = ChatMessage::new(v);
+            message.set_id(MessageId::new());
+            message.apply(network, gui);
+        },
+        _ => (),
+    }
+}
+
+/// Rebuild the recorded game at the start of a turn and
+/// play it forward one message at a time.
+fn replay_system(
+    time: Res<Time>,
+    mut state: ResMut<State>,
+    mut context: ResMut<Context>,
+    mut gui: ResMut<GuiState>,
+    mut playback: ResMut<Playback>,
+    mut tilemap: Query<&mut Tilemap>,
+) {
+    if !state.is_loaded() || !playback.is_loaded() {
+        return;
+    }
+
+    if let Some(index) = playback.seek.take() {
+        let (seed, factors, frame) = match playback.replay.as_ref() {
+            Some(r) => (r.seed, r.factors.clone(), r.frames[index].clone()),
+            None => return,
+        };
+
+        state.terrain.seed = format!("{}", seed);
+        state.factors = factors;
+        state.victory = frame.victory;
+        state.log.clear();
+        state.resize(&mut context, frame.start.context);
+        state.events.send(Action::UpdateTerrain);
+
+        playback.frame = index;
+        playback.step = 0;
+        playback.waiting = Some(frame.start);
+    }
+
+    // wait for a rebuilt map before placing units
+    if state.events.receive(Action::RebuildMap) {
+        return;
+    }
+
+    let mut map = tilemap.single_mut().expect("Need tilemap");
+
+    if let Some(snapshot) = playback.waiting.take() {
+        state.restore(&mut map, snapshot);
+        return;
+    }
+
+    if playback.tick(time.delta_seconds()) {
+        playback.next(&mut map, &mut state, &mut gui);
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Playback>()
+           .add_system(replay_system.system());
+    }
+}
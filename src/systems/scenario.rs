@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use bevy_tilemap::Tilemap;
+
+use crate::behavior::Opponent;
+use crate::generation::Unit;
+use crate::networking::messages::PlayerData;
+use crate::objects::Point;
+use crate::state::traits::*;
+use crate::state::{Action, State};
+use crate::systems::network::NetworkState;
+use crate::systems::opponent::OpponentState;
+
+pub struct ScenarioPlugin;
+
+/// Fill the slots of a loaded scenario and place the starting
+/// units of every claimed slot once its terrain is ready.
+fn scenario_deploy_system(
+    mut state: ResMut<State>,
+    mut network: ResMut<NetworkState>,
+    mut opponents: ResMut<OpponentState>,
+    mut map_query: Query<&mut Tilemap>,
+) {
+    if !state.is_loaded() || network.is_client() {
+        return;
+    }
+
+    if state.events.receive(Action::UpdateTerrain) || state.events.receive(Action::RebuildMap) {
+        return;
+    }
+
+    let mut scenario = match state.scenario.take() {
+        Some(s) => s,
+        None => return,
+    };
+
+    // the local player takes the first open slot
+    scenario.claim(network.id());
+
+    for slot in scenario.slots.iter_mut() {
+        if let (Some(difficulty), None) = (slot.computer, slot.player) {
+            let opponent = Opponent::new(slot.name.clone(), difficulty);
+            slot.player = Some(opponent.id());
+            opponents.add(opponent);
+        }
+    }
+
+    // a single-player game needs a slot for the local player
+    if network.is_offline() && network.player_data().is_none() {
+        let player = PlayerData::new(network.id(), network.name());
+        network.players.add(player);
+    }
+
+    let mut map = map_query.single_mut().expect("Need tilemap");
+    let mut deployed = false;
+
+    for slot in scenario.slots.iter_mut().filter(|s| !s.deployed) {
+        let player = match slot.player {
+            Some(p) => p,
+            None => continue,
+        };
+
+        // computer players get their slot on the next update
+        let data = match network.players().into_iter().find(|p| p.id == player) {
+            Some(d) => d,
+            None => continue,
+        };
+
+        for spec in slot.units.iter() {
+            let point = Point::from(spec.position);
+            if !state.areas.contains_key(&spec.position) || state.units.space(&point) == 0 {
+                warn!("cannot place {} at {:?}", spec.name, spec.position);
+                continue;
+            }
+
+            let unit = Unit::new(player)
+                .with_name(spec.name.clone())
+                .with_player(data.clone())
+                .with_specialty(spec.specialty)
+                .with_soldiers(spec.soldiers)
+                .with_position(point)
+                .build(&state);
+
+            unit.insert(&mut map);
+            state.units.add(point, unit.clone());
+
+            if network.is_server() {
+                network.send_create_event(unit);
+            }
+        }
+
+        slot.deployed = true;
+        deployed = true;
+    }
+
+    state.scenario = Some(scenario);
+
+    if deployed && network.is_server() {
+        network.send_update_event(&state);
+    }
+}
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(scenario_deploy_system.system());
+    }
+}
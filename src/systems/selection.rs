@@ -3,12 +3,12 @@ use bevy::input::keyboard::KeyCode;
 use bevy::prelude::*;
 use bevy_tilemap::{Tile, Tilemap};
 use std::collections::HashSet;
-use crate::generation::{LayerUse, Specialty, Unit};
+use crate::generation::{LayerUse, Specialty, Unit, Order};
 use crate::math::MidRound;
-use crate::state::{traits::*, Action, State};
+use crate::state::{traits::*, Action, Attack, Context, Editor, Fire, State};
 use crate::systems::camera::Camera;
 use crate::behavior::Pathfinder;
-use crate::generation::{Id,Units};
+use crate::generation::Id;
 use crate::resources::Label;
 use crate::systems::network::NetworkState;
 use crate::networking::messages::*;
@@ -21,6 +21,7 @@ pub struct SelectionPlugin;
 pub enum SelectionFlag {
     Place,
     Hovering,
+    Targeting,
 }
 
 #[derive(Debug, Clone)]
@@ -56,6 +57,12 @@ pub struct Selection {
     /// the key that cancels selection
     pub release: KeyCode,
 
+    /// the key that queues an order instead of moving
+    pub queue: KeyCode,
+
+    /// the key that switches to ranged targeting
+    pub target: KeyCode,
+
     /// ids of multiple selected units
     pub units: HashSet<Id>,
 
@@ -117,6 +124,8 @@ impl Default for Selection {
             path: vec![],
             button: MouseButton::Left,
             release: KeyCode::Escape,
+            queue: KeyCode::LShift,
+            target: KeyCode::T,
             units: HashSet::new(),
             request: None,
         }
@@ -164,6 +173,7 @@ fn selected_position_system(
 /// and update the selection.
 fn selected_hovered_system(
     state: ResMut<State>,
+    context: Res<Context>,
     windows: Res<Windows>,
     mut sel_query: Query<&mut Selection>,
     mut map_query: Query<&mut Tilemap>,
@@ -185,7 +195,7 @@ fn selected_hovered_system(
         let x = selection.position.x;
         let y = selection.position.y;
 
-        let point = Point::from_global(x,y).integers();
+        let point = Point::from_global(x,y,&context).integers();
         
         if state.areas.get(&point).is_some() {
             selection.hovered = point;
@@ -198,11 +208,13 @@ fn selected_highlight_system(
     windows: Res<Windows>,
     inputs: Res<Input<MouseButton>>,
     keyboard: Res<Input<KeyCode>>,
+    editor: Res<Editor>,
     mut network: ResMut<NetworkState>,
     mut sel_query: Query<&mut Selection>,
     mut map_query: Query<&mut Tilemap>,
 ) {
-    if !state.is_loaded() {
+    // the editor takes over the mouse while it's open
+    if !state.is_loaded() || editor.enabled {
         return;
     }
 
@@ -224,6 +236,17 @@ fn selected_highlight_system(
     if keyboard.just_pressed(selection.release) && state.units.has_selection() {
         state.units.select_return(&mut map);
         selection.clear_path(&mut map,layer);
+        selection.flags.unset(SelectionFlag::Targeting);
+    }
+
+    // holding the target key shows the range of the selected
+    // units until the selection is released
+    if keyboard.pressed(selection.target) &&
+       state.units.has_selection() &&
+       !selection.flags.get(SelectionFlag::Targeting) {
+        selection.clear_path(&mut map,layer);
+        selection.path = state.units.draw_range(&mut map,layer,blank);
+        selection.flags.set(SelectionFlag::Targeting);
     }
 
     if window.cursor_position().is_some() {
@@ -244,6 +267,7 @@ fn selected_highlight_system(
                     selection.units.drain().collect(),
                 );
             }
+            selection.path = state.units.draw_waypoints(&mut map,layer,blank);
         }
         // if the selection button has just been released, then deselect
         // whatever units are selected
@@ -251,23 +275,90 @@ fn selected_highlight_system(
             let player = network.id();
             let target = selection.hovered.into();
             let selected = state.units.selected();
+            let enemy = state.units.has_enemy(&target,&player,&state.teams);
+
+            // in targeting mode, units fire at the target from
+            // where they started instead of moving
+            if selection.flags.get(SelectionFlag::Targeting) {
+                let (indirect, direct): (Vec<&Unit>,Vec<&Unit>) = state
+                    .units
+                    .selected_units()
+                    .into_iter()
+                    .filter(|u| u.specialty().range() > 0)
+                    .partition(|u| u.specialty().is_indirect());
+
+                let attacks: Vec<Attack> = vec![(direct,Fire::Direct),(indirect,Fire::Indirect)]
+                    .into_iter()
+                    .filter(|(u,_)| !u.is_empty())
+                    .map(|(u,f)| Attack::new(
+                        player,
+                        u.iter().map(|u| *u.id()).collect(),
+                        target,
+                    ).with_fire(f))
+                    .collect();
+
+                state.units.select_return(&mut map);
+                for attack in attacks.into_iter() {
+                    queue_attack(&mut state,&mut network,attack);
+                }
+
+                selection.flags.unset(SelectionFlag::Targeting);
+            }
+            // holding the queue key adds a standing order instead
+            // of moving or attacking right away
+            else if keyboard.pressed(selection.queue) {
+                let order = match enemy {
+                    true => Order::AttackMove(target),
+                    false => Order::Move(target),
+                };
+
+                let orders: Vec<(Id,Vec<Order>)> = state
+                    .units
+                    .selected_units()
+                    .iter()
+                    .map(|u| {
+                        let mut list = u.orders().list().clone();
+                        list.push(order);
+                        (*u.id(),list)
+                    })
+                    .collect();
+
+                state.units.select_return(&mut map);
+                issue_orders(&mut state,&mut network,orders);
+            }
+            else {
+                if enemy {
+                    let units: Vec<Id> = state
+                        .units
+                        .selected_units()
+                        .iter()
+                        .map(|u| u.id().clone())
+                        .collect();
+
+                    if !units.is_empty() {
+                        queue_attack(&mut state,&mut network,Attack::new(player, units, target));
+                    }
+                }
 
-            if state.units.has_enemy(&target,&player) {
-                let friends = state.units.selected_units();
-                if friends.len() > 0 {
-                    let enemies = state.units.targeted_units(&target,&player);
+                if !selected.is_empty() {
+                    network.send_move_event(state.context(), &selected);
 
-                    let friendly = Units::aggregate(friends);
-                    let enemy = Units::aggregate(enemies);
-    
-                    let result = friendly.attack(&enemy);
-                    network.send_change_event(result);
+                    // moved units may see different works
+                    state.events.send(Action::UpdateStructures);
                 }
-            }
 
-            if !selected.is_empty() {
-                network.send_move_event(&state.units.selected());
+                // units that ran out of actions keep moving next turn
+                let orders: Vec<(Id,Vec<Order>)> = selected
+                    .iter()
+                    .filter(|s| !enemy && s.end_point(state.context()) != target)
+                    .map(|s| (s.unit(),vec![Order::Move(target)]))
+                    .collect();
+
+                if !orders.is_empty() {
+                    issue_orders(&mut state,&mut network,orders);
+                }
             }
+
             state.units.select_none();
             selection.clear_path(&mut map,layer);
             selection.units.clear();
@@ -276,20 +367,27 @@ fn selected_highlight_system(
         // location is hovering over a new tile, then trigger dragging
         else if inputs.pressed(selection.button)
         {
-            if selection.dragging != selection.hovered && state.units.has_selection() {
+            let targeting = selection.flags.get(SelectionFlag::Targeting);
+            if selection.dragging != selection.hovered && state.units.has_selection() && !targeting {
                 let player = network.id();
                 let target = selection.hovered.into();
 
-                if !state.units.has_enemy(&target,&player) {
-                    let impedance = state.impedance_map();
+                if !state.units.has_enemy(&target,&player,&state.teams) {
+                    let control = state.control(&player);
+                    let mut impedance = state.impedance_map();
+                    control.weigh(&mut impedance);
+
                     selection.clear_path(&mut map,layer);
                     selection.path = state.units.pathto(
                         &mut map,
                         &impedance,
+                        &control,
                         &target,
                         layer,
                         blank
                     );
+                    selection.path.append(
+                        &mut state.units.draw_waypoints(&mut map,layer,blank));
                 }
             }
         }
@@ -307,6 +405,27 @@ fn selected_highlight_system(
 
 }
 
+/// Queue an attack to be resolved at the end of the turn
+pub fn queue_attack(state: &mut State, network: &mut NetworkState, attack: Attack) {
+    // the server charges its own attacks when the message comes back
+    if network.is_server() {
+        network.send_attack_event(attack);
+    }
+    else if let Some(attack) = state.queue_attack(attack) {
+        if network.is_client() {
+            network.send_attack_event(attack);
+        }
+    }
+}
+
+/// Set standing orders locally and share them with other players
+pub fn issue_orders(state: &mut State, network: &mut NetworkState, orders: Vec<(Id,Vec<Order>)>) {
+    state.units.set_orders(&network.id(),&orders);
+    if !network.is_offline() {
+        network.send_order_event(orders);
+    }
+}
+
 /// React to placement requests and create a new unit.
 fn selected_place_system(
     mut state: ResMut<State>,
@@ -325,7 +444,18 @@ fn selected_place_system(
     if selection.place_requested() {
         if inputs.just_pressed(selection.button) {
             if let Some(PlaceRequest { name, specialty }) = selection.request.take() {
-                if let Some(_) = state.areas.get(&selection.hovered) {
+                let allowed = state.scenario
+                    .as_ref()
+                    .map(|s| s.can_deploy(&network.id(), &selection.hovered))
+                    .unwrap_or(true);
+
+                if network.is_spectating() {
+                    warn!("spectators cannot place units");
+                }
+                else if !allowed {
+                    warn!("units must be placed in the deployment zone");
+                }
+                else if let Some(_) = state.areas.get(&selection.hovered) {
                     let point: Point = selection.hovered.into();
                     
                     if let Some(data) = network.player_data() {
@@ -349,12 +479,33 @@ fn selected_place_system(
     }
 }
 
+/// Redraw units when their textures may have changed.
+fn selected_refresh_system(
+    mut state: ResMut<State>,
+    mut map_query: Query<&mut Tilemap>,
+) {
+    if !state.is_loaded() {
+        return;
+    }
+
+    if !state.events.receive(Action::UpdateUnits) {
+        return;
+    }
+
+    let mut tilemap = map_query.single_mut().expect("Need tilemap");
+    let textures = state.textures.clone();
+
+    state.units.refresh(&mut tilemap, &textures);
+    state.events.clear(Action::UpdateUnits);
+}
+
 impl Plugin for SelectionPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_system(selected_position_system.system())
            .add_system(selected_hovered_system.system())
            .add_system(selected_highlight_system.system())
-           .add_system(selected_place_system.system());
+           .add_system(selected_place_system.system())
+           .add_system(selected_refresh_system.system());
     }
 }
 
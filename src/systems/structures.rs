@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use bevy_tilemap::{Tile, Tilemap};
+use std::mem;
+
+use crate::behavior::Sight;
+use crate::generation::{LayerUse, Structure, Unit};
+use crate::objects::{Location, Point};
+use crate::resources::Label;
+use crate::state::{Action, State};
+use crate::systems::network::NetworkState;
+
+pub struct StructurePlugin;
+
+/// Redraw the roads and buildings of edited tiles and the
+/// fortifications that the local player (or the player a
+/// spectator follows) owns or can see.
+fn structure_update_system(
+    mut state: ResMut<State>,
+    network: Res<NetworkState>,
+    mut map_query: Query<&mut Tilemap>,
+) {
+    if !state.is_loaded() {
+        return;
+    }
+
+    if !state.events.receive(Action::UpdateStructures) {
+        return;
+    }
+
+    state.events.clear(Action::UpdateStructures);
+
+    let mut map = map_query.single_mut().expect("Need tilemap");
+
+    let layer = state
+        .layers
+        .get(&LayerUse::Structures)
+        .expect("Must have structures layer");
+
+    let texture = state.textures.get(Label::Blank);
+
+    let drawn: Vec<(Location, usize)> = mem::take(&mut state.fortifications.drawn)
+        .into_iter()
+        .map(|l| (l, layer))
+        .collect();
+
+    if let Err(e) = map.clear_tiles(drawn) {
+        log::warn!("{:?}", e);
+    }
+
+    let viewer = network.viewer();
+    let sight = Sight::new(&state.areas);
+
+    let spotters: Vec<&Unit> = state.units
+        .units()
+        .into_iter()
+        .filter(|u| Some(*u.player_id()) == viewer)
+        .collect();
+
+    let mut tiles = vec![];
+    let mut locations = vec![];
+
+    // roads and buildings placed in the editor are always visible
+    for tile in state.terrain.overrides.iter() {
+        let tint = match (tile.structure, tile.road) {
+            (Some(Structure::None), false) | (None, false) => continue,
+            (Some(Structure::None), true) | (None, true) => Color::rgba(0.6, 0.55, 0.45, 0.6),
+            (Some(Structure::Village), _) => Color::rgba(0.5, 0.5, 0.5, 0.5),
+            (Some(Structure::Town), _) => Color::rgba(0.4, 0.4, 0.4, 0.7),
+            (Some(Structure::City), _) => Color::rgba(0.3, 0.3, 0.3, 0.9),
+            (Some(_), _) => Color::rgba(0.35, 0.4, 0.25, 0.7),
+        };
+
+        tiles.push(Tile {
+            point: tile.location,
+            sprite_order: layer,
+            sprite_index: texture,
+            tint,
+        });
+
+        locations.push(tile.location);
+    }
+
+    for (location, site) in state.fortifications.sites() {
+        let visible = match viewer {
+            Some(player) => site.owner() == &player ||
+                sight.spotted(&spotters, &Point::from(*location)),
+            None => true,
+        };
+
+        if !visible {
+            continue;
+        }
+
+        // works become more visible as they're dug
+        let alpha = 0.2 + 0.6 * site.completion();
+
+        tiles.push(Tile {
+            point: *location,
+            sprite_order: layer,
+            sprite_index: texture,
+            tint: Color::rgba(0.45, 0.3, 0.15, alpha),
+        });
+
+        locations.push(*location);
+    }
+
+    if let Err(e) = map.insert_tiles(tiles) {
+        log::warn!("{:?}", e);
+    }
+
+    state.fortifications.drawn = locations;
+}
+
+impl Plugin for StructurePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(structure_update_system.system());
+    }
+}
@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+use bevy_tilemap::Tilemap;
+
+use crate::state::{Action, State};
+use crate::systems::network::NetworkState;
+use crate::systems::gui::GuiState;
+use crate::systems::mail::Mailbox;
+
+pub struct TurnPlugin;
+
+/// React to end-turn requests by resolving the turn locally
+/// or telling the server that this player is ready.
+fn turn_end_system(
+    mut state: ResMut<State>,
+    mut gui: ResMut<GuiState>,
+    mut network: ResMut<NetworkState>,
+    mailbox: Res<Mailbox>,
+    mut map_query: Query<&mut Tilemap>,
+) {
+    if !state.is_loaded() {
+        return;
+    }
+
+    if !state.events.receive(Action::EndTurn) {
+        return;
+    }
+
+    state.events.clear(Action::EndTurn);
+
+    if state.victory.is_over() || state.in_lobby() {
+        return;
+    }
+
+    // the turn is resolved by whoever opens the last player's file
+    if mailbox.is_active() {
+        gui.add_system("Send your turn to end it".into());
+    }
+    else if network.is_offline() {
+        let mut map = map_query.single_mut().expect("Need tilemap");
+        let turn = state.turn() + 1;
+        let changes = state.resolve();
+        state.advance(&mut map, turn, &changes);
+        gui.add_system(format!("{} has begun", state.calendar));
+
+        if state.judge() {
+            gui.add_system("The game is over".into());
+        }
+    }
+    else {
+        let turn = state.turn();
+        network.send_ready_event(turn, true);
+    }
+}
+
+/// Count down the turn timer and, on the server, resolve the
+/// turn once every player is ready or time runs out.
+fn turn_resolve_system(
+    time: Res<Time>,
+    mut state: ResMut<State>,
+    mut network: ResMut<NetworkState>,
+) {
+    if !state.is_loaded() || state.in_lobby() {
+        return;
+    }
+
+    state.timer.tick(time.delta_seconds());
+
+    if !network.is_server() || state.victory.is_over() {
+        return;
+    }
+
+    if network.players.all_ready() || state.timer.expired() {
+        info!("resolving turn {}", state.turn());
+        let turn = state.turn() + 1;
+        let changes = state.resolve();
+        state.timer.reset();
+        network.players.clear_ready();
+        network.send_turn_event(turn, changes);
+    }
+}
+
+impl Plugin for TurnPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(turn_end_system.system())
+           .add_system(turn_resolve_system.system());
+    }
+}